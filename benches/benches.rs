@@ -3,7 +3,9 @@
 
 use iai::black_box;
 
-use colony::Colony;
+use colony::{
+    Colony, FlagGuard, GenerationGuard, InterleavedStorage, ReusePolicy, SeparatedStorage, Storage,
+};
 
 struct Random {
     state: u128,
@@ -20,6 +22,8 @@ impl Random {
     }
 }
 
+// Large enough that `SeparatedStorage` keeps freelist links in place of the element,
+// so with `GenerationGuard` a slot is 37 bytes when separated and 41 bytes when interleaved
 #[derive(Copy, Clone)]
 struct Data([usize; 4]);
 
@@ -29,13 +33,29 @@ impl Data {
     }
 }
 
-fn grow_then_iter(size: usize, iters: usize) {
-    let mut colony = Colony::new();
+fn grow_then_iter<S: Storage>(size: usize, iters: usize) {
+    let mut colony = Colony::<_, GenerationGuard, S>::default();
 
     for i in 0..black_box(size) {
         colony.insert(Data::new(i));
     }
 
+    for _ in 0..black_box(iters) {
+        for (handle, &value) in &colony {
+            black_box((handle, value.0));
+        }
+    }
+}
+
+// Small enough that `SeparatedStorage` needs a separate array of links, but packs the elements densely,
+// so with `FlagGuard` iterating reads 1 byte of element per slot when separated rather than a 24 byte slot
+fn small_grow_then_iter<S: Storage>(size: usize, iters: usize) {
+    let mut colony = Colony::<_, FlagGuard, S>::default();
+
+    for i in 0..black_box(size) {
+        colony.insert(i as u8);
+    }
+
     for _ in 0..black_box(iters) {
         for (handle, &value) in &colony {
             black_box((handle, value));
@@ -44,15 +64,20 @@ fn grow_then_iter(size: usize, iters: usize) {
 }
 
 fn grow(size: usize) {
-    grow_then_iter(size, 0)
+    grow_then_iter::<InterleavedStorage>(size, 0)
 }
 
-fn simulate(size: usize, steps: usize) {
+fn separated_grow(size: usize) {
+    grow_then_iter::<SeparatedStorage>(size, 0)
+}
+
+fn simulate<S: Storage>(size: usize, steps: usize, policy: ReusePolicy) {
     assert!(size.is_power_of_two());
     let index_mask = (size * 2) - 1;
 
     let mut random = Random::new();
-    let mut colony = Colony::flagged();
+    let mut colony = Colony::<_, FlagGuard, S>::default();
+    colony.set_reuse_policy(policy);
 
     for _ in 0..black_box(steps) {
         let modifications = size / 10;
@@ -68,37 +93,89 @@ fn simulate(size: usize, steps: usize) {
         }
 
         for (handle, &value) in &colony {
-            black_box((handle, value));
+            black_box((handle, value.0));
         }
     }
 }
 
 fn grow_then_iter_1x(size: usize) {
-    grow_then_iter(size, 1)
+    grow_then_iter::<InterleavedStorage>(size, 1)
 }
 
 fn grow_then_iter_10x(size: usize) {
-    grow_then_iter(size, 10)
+    grow_then_iter::<InterleavedStorage>(size, 10)
 }
 
 fn grow_then_iter_100x(size: usize) {
-    grow_then_iter(size, 100)
+    grow_then_iter::<InterleavedStorage>(size, 100)
 }
 
 fn grow_then_iter_1000x(size: usize) {
-    grow_then_iter(size, 1000)
+    grow_then_iter::<InterleavedStorage>(size, 1000)
+}
+
+fn separated_grow_then_iter_10x(size: usize) {
+    grow_then_iter::<SeparatedStorage>(size, 10)
+}
+
+fn separated_grow_then_iter_100x(size: usize) {
+    grow_then_iter::<SeparatedStorage>(size, 100)
+}
+
+fn small_grow_then_iter_10x(size: usize) {
+    small_grow_then_iter::<InterleavedStorage>(size, 10)
+}
+
+fn separated_small_grow_then_iter_10x(size: usize) {
+    small_grow_then_iter::<SeparatedStorage>(size, 10)
 }
 
 fn simulate_small() {
-    simulate(128, 100_000);
+    simulate::<InterleavedStorage>(128, 100_000, ReusePolicy::Lifo);
 }
 
 fn simulate_medium() {
-    simulate(16 * 1024, 500);
+    simulate::<InterleavedStorage>(16 * 1024, 500, ReusePolicy::Lifo);
 }
 
 fn simulate_large() {
-    simulate(1024 * 1024, 50);
+    simulate::<InterleavedStorage>(1024 * 1024, 50, ReusePolicy::Lifo);
+}
+
+fn separated_simulate_small() {
+    simulate::<SeparatedStorage>(128, 100_000, ReusePolicy::Lifo);
+}
+
+fn separated_simulate_medium() {
+    simulate::<SeparatedStorage>(16 * 1024, 500, ReusePolicy::Lifo);
+}
+
+fn separated_simulate_large() {
+    simulate::<SeparatedStorage>(1024 * 1024, 50, ReusePolicy::Lifo);
+}
+
+fn lowest_first_simulate_small() {
+    simulate::<InterleavedStorage>(128, 100_000, ReusePolicy::LowestFirst);
+}
+
+fn lowest_first_simulate_medium() {
+    simulate::<InterleavedStorage>(16 * 1024, 500, ReusePolicy::LowestFirst);
+}
+
+fn lowest_first_simulate_large() {
+    simulate::<InterleavedStorage>(1024 * 1024, 50, ReusePolicy::LowestFirst);
+}
+
+fn fill_current_simulate_small() {
+    simulate::<InterleavedStorage>(128, 100_000, ReusePolicy::FillCurrent);
+}
+
+fn fill_current_simulate_medium() {
+    simulate::<InterleavedStorage>(16 * 1024, 500, ReusePolicy::FillCurrent);
+}
+
+fn fill_current_simulate_large() {
+    simulate::<InterleavedStorage>(1024 * 1024, 50, ReusePolicy::FillCurrent);
 }
 
 macro_rules! cases {
@@ -184,9 +261,23 @@ cases! {
     simulate_small;
     simulate_medium;
     simulate_large;
+    separated_simulate_small;
+    separated_simulate_medium;
+    separated_simulate_large;
+    lowest_first_simulate_small;
+    lowest_first_simulate_medium;
+    lowest_first_simulate_large;
+    fill_current_simulate_small;
+    fill_current_simulate_medium;
+    fill_current_simulate_large;
     1..1m grow;
     1..1m grow_then_iter_1x;
     1..1m grow_then_iter_10x;
     1..100k grow_then_iter_100x;
     1..10k grow_then_iter_1000x;
+    1..1m separated_grow;
+    1..1m separated_grow_then_iter_10x;
+    1..100k separated_grow_then_iter_100x;
+    1..1m small_grow_then_iter_10x;
+    1..1m separated_small_grow_then_iter_10x;
 }
@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 use libfuzzer_sys::arbitrary::Arbitrary;
 use libfuzzer_sys::{arbitrary, fuzz_target};
-use colony::Colony;
+use colony::{Colony, ReusePolicy};
 
 type T = u8;
 
@@ -11,6 +11,7 @@ type T = u8;
 enum Operation {
     Insert(T),
     Remove(usize),
+    SetReusePolicy(u8),
 }
 
 fuzz_target!(|operations: Vec<Operation>| {
@@ -29,10 +30,19 @@ fuzz_target!(|operations: Vec<Operation>| {
                 let actual = colony.remove(index);
                 assert_eq!(actual, expected);
             }
+            Operation::SetReusePolicy(policy) => {
+                colony.set_reuse_policy(match policy % 3 {
+                    0 => ReusePolicy::Lifo,
+                    1 => ReusePolicy::LowestFirst,
+                    _ => ReusePolicy::FillCurrent,
+                });
+            }
         }
     }
 
     for (index, value) in &colony {
         assert_eq!(Some(value), values.get(&index));
     }
+
+    assert_eq!(colony.validate(), Ok(()));
 });
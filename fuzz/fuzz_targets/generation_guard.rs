@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::num::NonZeroU64;
 use libfuzzer_sys::arbitrary::Arbitrary;
 use libfuzzer_sys::{arbitrary, fuzz_target};
-use colony::{Colony, Handle, Generation};
+use colony::{Colony, Handle, Generation, ReusePolicy};
 
 type T = u8;
 
@@ -12,6 +12,7 @@ type T = u8;
 enum Operation {
     Insert(T),
     Remove((usize, u64)),
+    SetReusePolicy(u8),
 }
 
 fuzz_target!(|operations: Vec<Operation>| {
@@ -38,10 +39,19 @@ fuzz_target!(|operations: Vec<Operation>| {
                 let actual = colony.remove(handle);
                 assert_eq!(actual, expected);
             }
+            Operation::SetReusePolicy(policy) => {
+                colony.set_reuse_policy(match policy % 3 {
+                    0 => ReusePolicy::Lifo,
+                    1 => ReusePolicy::LowestFirst,
+                    _ => ReusePolicy::FillCurrent,
+                });
+            }
         }
     }
 
     for (index, value) in &colony {
         assert_eq!(Some(value), values.get(&index));
     }
+
+    assert_eq!(colony.validate(), Ok(()));
 });
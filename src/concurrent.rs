@@ -0,0 +1,668 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::sync::{LockResult, PoisonError, TryLockError};
+
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(loom)]
+use loom::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(loom))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(loom))]
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{Colony, ColonyId, GenerationGuard, Guard, Handle};
+
+const DEFAULT_SHARDS: usize = 8;
+
+/// A colony that can be accessed and modified from many threads at once through a shared reference.
+///
+/// The elements are spread over a fixed number of *shards*, each of which is a [`Colony`] behind its own lock.
+/// Lookups only take a read lock on the shard holding the element,
+/// and insertions take a write lock on whichever shard is free, so threads rarely wait on each other.
+///
+/// Handles are [`Handle`]s with the shard encoded in their index,
+/// and behave exactly as with [`GenerationGuard`]:
+/// a handle is never valid for any element but the one it was created for, in this or any other colony.
+/// Every handle has the identity of the concurrent colony as its [`colony_id`](Handle::colony_id),
+/// so handles can be routed to it by a [`ColonyRegistry`](crate::ColonyRegistry) like those of any colony.
+///
+/// Elements can either be removed immediately with [`remove`](ConcurrentColony::remove),
+/// which waits for readers of the shard to finish,
+/// or queued for removal with [`defer_remove`](ConcurrentColony::defer_remove), which never waits on a shard.
+///
+/// A thread holding a [`ConcurrentRef`] must not insert, remove or call [`with_mut`](ConcurrentColony::with_mut) itself,
+/// since it would wait on its own read lock forever. Deferred removals are safe to queue at any time.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::ConcurrentColony;
+/// # use std::thread;
+/// let colony = ConcurrentColony::new();
+///
+/// let handles: Vec<_> = thread::scope(|scope| {
+///     let workers: Vec<_> = (0..4)
+///         .map(|i| scope.spawn({
+///             let colony = &colony;
+///             move || colony.insert(i)
+///         }))
+///         .collect();
+///
+///     workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+/// });
+///
+/// for (i, &handle) in handles.iter().enumerate() {
+///     assert_eq!(*colony.get(handle).unwrap(), i);
+/// }
+/// ```
+pub struct ConcurrentColony<T> {
+    shards: Box<[Shard<T>]>,
+    next_shard: AtomicUsize,
+    // Given to every handle in place of the identity of its shard
+    id: ColonyId,
+}
+
+struct Shard<T> {
+    colony: RwLock<Colony<T>>,
+    // Handles with their index local to the shard, only locked briefly and never while waiting on the colony lock
+    pending: Mutex<Vec<Handle>>,
+}
+
+impl<T> ConcurrentColony<T> {
+    /// Creates an empty concurrent colony with the default number of shards.
+    ///
+    /// Does not allocate space for any elements.
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    /// Creates an empty concurrent colony with the given number of shards.
+    ///
+    /// More shards make it less likely for threads to contend on the same lock,
+    /// but make [`len`](ConcurrentColony::len) and [`flush_removals`](ConcurrentColony::flush_removals) slower.
+    ///
+    /// # Panics
+    ///
+    /// If `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "a concurrent colony needs at least one shard");
+
+        let shards = (0..shards)
+            .map(|_| Shard {
+                colony: RwLock::new(Colony::new()),
+                pending: Mutex::new(Vec::new()),
+            })
+            .collect();
+
+        Self {
+            shards,
+            next_shard: AtomicUsize::new(0),
+            id: ColonyId::new(GenerationGuard::__new_id()).unwrap(),
+        }
+    }
+
+    /// Returns the identity of the concurrent colony, which is shared by every handle it creates.
+    ///
+    /// Unlike [`Colony::id`], a concurrent colony is given its identity upon creation, and it never changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::ConcurrentColony;
+    /// let colony = ConcurrentColony::with_shards(2);
+    /// let first = colony.insert("foo");
+    /// let second = colony.insert("bar");
+    ///
+    /// assert_eq!(first.colony_id(), colony.id());
+    /// assert_eq!(second.colony_id(), colony.id());
+    /// ```
+    pub fn id(&self) -> ColonyId {
+        self.id
+    }
+
+    /// Returns the number of shards.
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Inserts an element into the colony and returns its handle.
+    ///
+    /// This takes the first shard that isn't locked, waiting only if every shard is locked.
+    /// Any removals deferred on that shard are applied at the same time.
+    ///
+    /// This may deadlock if the calling thread holds a [`ConcurrentRef`].
+    ///
+    /// # Panics
+    ///
+    /// See [`Colony::reserve`].
+    pub fn insert(&self, value: T) -> Handle {
+        let start = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+
+        let locked = (0..self.shards.len()).find_map(|offset| {
+            let shard = (start + offset) % self.shards.len();
+
+            match self.shards[shard].colony.try_write() {
+                Ok(colony) => Some((shard, colony)),
+                Err(TryLockError::Poisoned(error)) => Some((shard, error.into_inner())),
+                Err(TryLockError::WouldBlock) => None,
+            }
+        });
+
+        let (shard, mut colony) =
+            locked.unwrap_or_else(|| (start, ignore_poison(self.shards[start].colony.write())));
+
+        self.flush_locked(shard, &mut colony);
+
+        let local = colony.insert(value);
+        let Some(index) = local
+            .index
+            .checked_mul(self.shards.len())
+            .and_then(|index| index.checked_add(shard))
+        else {
+            colony.remove(local);
+            panic!("capacity overflow");
+        };
+
+        Handle {
+            index,
+            generation: local.generation.with_colony_id(self.id),
+        }
+    }
+
+    /// Returns a read guard for the element with the given handle, if it exists.
+    ///
+    /// Other elements of the same shard can be read while the guard is held, but not inserted or removed,
+    /// so the guard must be dropped before the same thread inserts or removes an element.
+    pub fn get(&self, handle: Handle) -> Option<ConcurrentRef<'_, T>> {
+        let (shard, local) = self.split(handle)?;
+        let colony = ignore_poison(self.shards[shard].colony.read());
+
+        colony.get(localize(&colony, local)?)?;
+
+        Some(ConcurrentRef {
+            colony,
+            index: local.index,
+        })
+    }
+
+    /// Returns a mutable reference to the element with the given handle, if it exists.
+    ///
+    /// This requires exclusive access to the colony, so no locking is needed.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let (shard, local) = self.split(handle)?;
+        let colony = ignore_poison(self.shards[shard].colony.get_mut());
+        colony.get_mut(localize(colony, local)?)
+    }
+
+    /// Returns `true` if an element exists with the given handle.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Calls `f` with a mutable reference to the element with the given handle, returning its result.
+    ///
+    /// This holds a write lock on the shard of the element for the duration of `f`.
+    /// Returns `None` without calling `f` if the handle is invalid.
+    ///
+    /// This may deadlock if the calling thread holds a [`ConcurrentRef`].
+    pub fn with_mut<R, F: FnOnce(&mut T) -> R>(&self, handle: Handle, f: F) -> Option<R> {
+        let (shard, local) = self.split(handle)?;
+        let mut colony = self.write(shard);
+        let local = localize(&colony, local)?;
+        colony.get_mut(local).map(f)
+    }
+
+    /// Removes the element with the given handle, if it exists.
+    ///
+    /// This waits for any readers of the element's shard to release their guards,
+    /// so may deadlock if the calling thread holds a [`ConcurrentRef`].
+    /// See [`defer_remove`](ConcurrentColony::defer_remove) for a non-blocking alternative.
+    pub fn remove(&self, handle: Handle) -> Option<T> {
+        let (shard, local) = self.split(handle)?;
+        let mut colony = self.write(shard);
+        let local = localize(&colony, local)?;
+        colony.remove(local)
+    }
+
+    /// Queues the element with the given handle for removal, without waiting on its shard.
+    ///
+    /// The element remains accessible until the removal is applied, which happens either on the next insertion into its shard,
+    /// or on a call to [`flush_removals`](ConcurrentColony::flush_removals).
+    /// The element is then dropped by whichever thread applies the removal.
+    /// Invalid handles are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::ConcurrentColony;
+    /// let colony = ConcurrentColony::new();
+    /// let handle = colony.insert("foo");
+    ///
+    /// colony.defer_remove(handle);
+    /// assert!(colony.contains(handle));
+    ///
+    /// colony.flush_removals();
+    /// assert!(!colony.contains(handle));
+    /// ```
+    pub fn defer_remove(&self, handle: Handle) {
+        let Some((shard, local)) = self.split(handle) else {
+            return;
+        };
+
+        ignore_poison(self.shards[shard].pending.lock()).push(local);
+    }
+
+    /// Applies all removals queued by [`defer_remove`](ConcurrentColony::defer_remove).
+    ///
+    /// This takes a write lock on each shard with removals queued, one at a time.
+    pub fn flush_removals(&self) {
+        for shard in 0..self.shards.len() {
+            if self.has_pending(shard) {
+                let mut colony = self.write(shard);
+                self.flush_locked(shard, &mut colony);
+            }
+        }
+    }
+
+    /// Returns the number of elements in the colony.
+    ///
+    /// Shards are counted one at a time, so the result may be outdated if other threads are modifying the colony.
+    /// Removals that have been deferred but not yet applied are counted.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| ignore_poison(shard.colony.read()).len())
+            .sum()
+    }
+
+    /// Returns `true` if the colony contains no elements, see [`len`](ConcurrentColony::len).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consumes the concurrent colony, applying any deferred removals and returning its shards.
+    ///
+    /// The handles of the shard at index `i` are related to handles of the concurrent colony
+    /// by multiplying their index by the number of shards and adding `i`,
+    /// and by replacing the identity of the shard with that of the concurrent colony.
+    pub fn into_shards(self) -> Vec<Colony<T>> {
+        self.flush_removals();
+
+        Vec::from(self.shards)
+            .into_iter()
+            .map(|shard| ignore_poison(shard.colony.into_inner()))
+            .collect()
+    }
+
+    // Returns the shard of a handle and the handle with its index local to that shard,
+    // or None if the handle was created by another colony
+    fn split(&self, handle: Handle) -> Option<(usize, Handle)> {
+        if handle.colony_id() != self.id {
+            return None;
+        }
+
+        let shard = handle.index % self.shards.len();
+
+        let local = Handle {
+            index: handle.index / self.shards.len(),
+            generation: handle.generation,
+        };
+
+        Some((shard, local))
+    }
+
+    fn write(&self, shard: usize) -> RwLockWriteGuard<'_, Colony<T>> {
+        ignore_poison(self.shards[shard].colony.write())
+    }
+
+    fn has_pending(&self, shard: usize) -> bool {
+        !ignore_poison(self.shards[shard].pending.lock()).is_empty()
+    }
+
+    fn flush_locked(&self, shard: usize, colony: &mut Colony<T>) {
+        let pending = {
+            let mut pending = ignore_poison(self.shards[shard].pending.lock());
+
+            if pending.is_empty() {
+                return;
+            }
+
+            std::mem::take(&mut *pending)
+        };
+
+        // Handles deferred twice or removed in the meantime are stale, and can be ignored
+        let pending: Vec<_> = pending
+            .into_iter()
+            .filter_map(|handle| localize(colony, handle))
+            .collect();
+        colony.remove_many(pending);
+    }
+}
+
+impl<T> Default for ConcurrentColony<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Colony<T>> for ConcurrentColony<T> {
+    /// Creates a concurrent colony with a single shard, keeping the handles and identity of the colony.
+    fn from(mut colony: Colony<T>) -> Self {
+        let id = colony.ensure_id();
+        let shards = Box::new([Shard {
+            colony: RwLock::new(colony),
+            pending: Mutex::new(Vec::new()),
+        }]);
+
+        Self {
+            shards,
+            next_shard: AtomicUsize::new(0),
+            id,
+        }
+    }
+}
+
+impl<T> Debug for ConcurrentColony<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ConcurrentColony")
+            .field("shards", &self.shards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A read guard for an element of a [`ConcurrentColony`], as returned by [`ConcurrentColony::get`].
+///
+/// The shard holding the element cannot be modified until the guard is dropped,
+/// and the thread holding the guard must not try to.
+pub struct ConcurrentRef<'a, T> {
+    colony: RwLockReadGuard<'a, Colony<T>>,
+    // Occupied for as long as the read lock is held
+    index: usize,
+}
+
+impl<T> Deref for ConcurrentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.colony.get_unchecked(self.index) }
+    }
+}
+
+impl<T: Debug> Debug for ConcurrentRef<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+// Returns the handle of a shard for a handle with its index local to the shard,
+// or None if the shard has never allocated, so cannot hold the element
+fn localize<T>(colony: &Colony<T>, handle: Handle) -> Option<Handle> {
+    Some(Handle {
+        index: handle.index,
+        generation: handle.generation.with_colony_id(colony.id()?),
+    })
+}
+
+// A panic while a lock is held cannot leave a colony in an inconsistent state
+fn ignore_poison<T>(result: LockResult<T>) -> T {
+    result.unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(all(test, not(loom)))]
+mod test {
+    use std::thread;
+
+    use crate::{Colony, ColonyRegistry, ConcurrentColony};
+
+    #[test]
+    fn single_thread() {
+        let colony = ConcurrentColony::with_shards(3);
+        let handles: Vec<_> = (0..100).map(|i| colony.insert(i)).collect();
+        assert_eq!(colony.len(), 100);
+
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(*colony.get(handle).unwrap(), i);
+        }
+
+        assert_eq!(colony.remove(handles[10]), Some(10));
+        assert_eq!(colony.remove(handles[10]), None);
+        assert!(colony.get(handles[10]).is_none());
+
+        assert_eq!(colony.with_mut(handles[20], std::mem::take), Some(20));
+        assert_eq!(*colony.get(handles[20]).unwrap(), 0);
+
+        let readded = colony.insert(1_000);
+        assert_ne!(readded, handles[10]);
+        assert_eq!(colony.len(), 100);
+    }
+
+    #[test]
+    fn foreign_handles() {
+        let colony = ConcurrentColony::with_shards(2);
+        let other = ConcurrentColony::<i32>::with_shards(2);
+        let handle = colony.insert(0);
+
+        assert!(other.get(handle).is_none());
+        other.defer_remove(handle);
+        other.flush_removals();
+        assert!(colony.contains(handle));
+    }
+
+    #[test]
+    fn routing() {
+        let first = ConcurrentColony::with_shards(2);
+        let second = ConcurrentColony::with_shards(2);
+
+        let mut registry = ColonyRegistry::new();
+        registry.insert(first.id(), &first);
+        registry.insert(second.id(), &second);
+
+        let handles: Vec<_> = (0..4).map(|i| first.insert(i)).collect();
+        let other = second.insert(4);
+
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(handle.colony_id(), first.id());
+            assert_eq!(*registry.route(handle).unwrap().get(handle).unwrap(), i);
+        }
+
+        assert_eq!(*registry.route(other).unwrap().get(other).unwrap(), 4);
+        assert!(second.get(handles[0]).is_none());
+    }
+
+    #[test]
+    fn deferred_removal() {
+        let colony = ConcurrentColony::with_shards(1);
+        let handles: Vec<_> = (0..10).map(|i| colony.insert(i)).collect();
+
+        colony.defer_remove(handles[3]);
+        colony.defer_remove(handles[3]);
+        colony.defer_remove(handles[4]);
+        assert_eq!(*colony.get(handles[3]).unwrap(), 3);
+
+        // With a single shard, insertion must apply the removals
+        colony.insert(10);
+        assert!(!colony.contains(handles[3]));
+        assert!(!colony.contains(handles[4]));
+        assert_eq!(colony.len(), 9);
+
+        let shards = colony.into_shards();
+        assert_eq!(shards.len(), 1);
+        assert_eq!(shards[0].validate(), Ok(()));
+    }
+
+    #[test]
+    fn from_colony() {
+        let mut colony = Colony::new();
+        let handle = colony.insert("foo");
+
+        let mut concurrent = ConcurrentColony::from(colony);
+        assert_eq!(concurrent.id(), handle.colony_id());
+        assert_eq!(*concurrent.get(handle).unwrap(), "foo");
+        *concurrent.get_mut(handle).unwrap() = "bar";
+        assert_eq!(*concurrent.get(handle).unwrap(), "bar");
+
+        let shards = concurrent.into_shards();
+        assert_eq!(shards[0][handle], "bar");
+    }
+
+    #[test]
+    fn many_threads() {
+        let colony = ConcurrentColony::with_shards(4);
+
+        let handles: Vec<Vec<_>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..8)
+                .map(|thread| {
+                    let colony = &colony;
+
+                    scope.spawn(move || {
+                        let mut kept = Vec::new();
+
+                        for i in 0..1_000 {
+                            let handle = colony.insert((thread, i));
+                            assert_eq!(*colony.get(handle).unwrap(), (thread, i));
+
+                            match i % 3 {
+                                0 => assert_eq!(colony.remove(handle), Some((thread, i))),
+                                1 => colony.defer_remove(handle),
+                                _ => kept.push((handle, i)),
+                            }
+                        }
+
+                        kept
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        colony.flush_removals();
+        assert_eq!(colony.len(), 8 * 333);
+
+        for (thread, kept) in handles.into_iter().enumerate() {
+            for (handle, i) in kept {
+                assert_eq!(*colony.get(handle).unwrap(), (thread, i));
+            }
+        }
+
+        for shard in colony.into_shards() {
+            assert_eq!(shard.validate(), Ok(()));
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_test {
+    use loom::sync::Arc;
+    use loom::thread;
+
+    use crate::ConcurrentColony;
+
+    // Every model uses a single shard, so that the threads contend on the same locks
+
+    #[test]
+    fn concurrent_insert() {
+        loom::model(|| {
+            let colony = Arc::new(ConcurrentColony::with_shards(1));
+
+            let inserters: Vec<_> = (0..2)
+                .map(|i| {
+                    let colony = colony.clone();
+                    thread::spawn(move || colony.insert(i))
+                })
+                .collect();
+
+            let handles: Vec<_> = inserters
+                .into_iter()
+                .map(|inserter| inserter.join().unwrap())
+                .collect();
+
+            assert_ne!(handles[0], handles[1]);
+            assert_eq!(*colony.get(handles[0]).unwrap(), 0);
+            assert_eq!(*colony.get(handles[1]).unwrap(), 1);
+            assert_eq!(colony.len(), 2);
+        });
+    }
+
+    #[test]
+    fn get_during_remove() {
+        loom::model(|| {
+            let colony = Arc::new(ConcurrentColony::with_shards(1));
+            let handle = colony.insert(String::from("foo"));
+
+            let reader = thread::spawn({
+                let colony = colony.clone();
+                move || {
+                    if let Some(value) = colony.get(handle) {
+                        assert_eq!(*value, "foo");
+                    }
+                }
+            });
+
+            let remover = thread::spawn({
+                let colony = colony.clone();
+                move || colony.remove(handle)
+            });
+
+            reader.join().unwrap();
+            assert_eq!(remover.join().unwrap().as_deref(), Some("foo"));
+            assert!(colony.get(handle).is_none());
+        });
+    }
+
+    #[test]
+    fn deferred_removal_during_insert() {
+        loom::model(|| {
+            let colony = Arc::new(ConcurrentColony::with_shards(1));
+            let removed = colony.insert(0);
+
+            let deferrer = thread::spawn({
+                let colony = colony.clone();
+                move || colony.defer_remove(removed)
+            });
+
+            let inserter = thread::spawn({
+                let colony = colony.clone();
+                move || colony.insert(1)
+            });
+
+            deferrer.join().unwrap();
+            let inserted = inserter.join().unwrap();
+            colony.flush_removals();
+
+            assert!(colony.get(removed).is_none());
+            assert_eq!(*colony.get(inserted).unwrap(), 1);
+            assert_eq!(colony.len(), 1);
+        });
+    }
+
+    #[test]
+    fn insert_during_remove() {
+        loom::model(|| {
+            let colony = Arc::new(ConcurrentColony::with_shards(1));
+            let removed = colony.insert(0);
+
+            let remover = thread::spawn({
+                let colony = colony.clone();
+                move || colony.remove(removed)
+            });
+
+            let inserter = thread::spawn({
+                let colony = colony.clone();
+                move || colony.insert(1)
+            });
+
+            assert_eq!(remover.join().unwrap(), Some(0));
+            let inserted = inserter.join().unwrap();
+
+            assert_ne!(inserted, removed);
+            assert!(colony.get(removed).is_none());
+            assert_eq!(*colony.get(inserted).unwrap(), 1);
+        });
+    }
+}
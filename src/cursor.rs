@@ -0,0 +1,168 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::mem;
+
+use crate::guard::{CheckedGuard, Guard};
+use crate::skipfield::{LEFT, RIGHT};
+use crate::{Colony, GenerationGuard, InterleavedStorage, Storage};
+
+/// A cursor over the elements of a colony which can remove and replace elements as it moves.
+///
+/// The cursor is either positioned at an element, or at a "ghost" position which sits past the last element
+/// and before the first.
+/// Moving forward from the ghost position goes to the first element, and moving backward goes to the last.
+///
+/// Elements are visited in the same order as [`Colony::iter`].
+///
+/// This is returned by [`Colony::cursor_front_mut`] and [`Colony::cursor_back_mut`].
+pub struct CursorMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: &'a mut Colony<T, G, S>,
+    // Either occupied or none for the ghost position
+    index: Option<usize>,
+}
+
+impl<'a, T, G: Guard, S: Storage> CursorMut<'a, T, G, S> {
+    pub(super) fn front(colony: &'a mut Colony<T, G, S>) -> Self {
+        let mut cursor = Self {
+            colony,
+            index: None,
+        };
+
+        cursor.move_next();
+        cursor
+    }
+
+    pub(super) fn back(colony: &'a mut Colony<T, G, S>) -> Self {
+        let mut cursor = Self {
+            colony,
+            index: None,
+        };
+
+        cursor.move_prev();
+        cursor
+    }
+
+    /// Returns the index of the current element, or `None` at the ghost position.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Returns the handle of the current element, or `None` at the ghost position.
+    pub fn handle(&self) -> Option<G::Handle> {
+        let index = self.index?;
+        unsafe {
+            Some(G::__new_handle(
+                self.colony.guard(index),
+                index,
+                self.colony.id,
+            ))
+        }
+    }
+
+    /// Returns a mutable reference to the current element, or `None` at the ghost position.
+    pub fn current(&mut self) -> Option<&mut T> {
+        let index = self.index?;
+        unsafe { Some(self.colony.occupied_mut(index)) }
+    }
+
+    /// Moves to the next element, or to the ghost position if the cursor was at the last element.
+    pub fn move_next(&mut self) {
+        let start = match self.index {
+            Some(index) => index + 1,
+            None => 0,
+        };
+
+        self.index = self.next_from(start);
+    }
+
+    /// Moves to the previous element, or to the ghost position if the cursor was at the first element.
+    pub fn move_prev(&mut self) {
+        let end = match self.index {
+            Some(index) => index,
+            None => self.colony.touched,
+        };
+
+        if end == 0 {
+            self.index = None;
+            return;
+        }
+
+        // The slot before an element or the end is either occupied or the tail of a skipblock
+        let skipped = unsafe { self.colony.skipfield().read::<LEFT>(end as isize - 1) };
+        self.index = (end - 1).checked_sub(skipped);
+    }
+
+    /// Moves to the element with the given handle, returning whether it exists.
+    ///
+    /// If the handle is invalid, the cursor does not move.
+    pub fn move_to(&mut self, handle: G::Handle) -> bool
+    where
+        G: CheckedGuard,
+    {
+        let index = G::__extract_index(&handle);
+
+        if self.colony.get(handle).is_none() {
+            return false;
+        }
+
+        self.index = Some(index);
+        true
+    }
+
+    /// Removes the current element and moves to the next one, returning the removed element.
+    ///
+    /// At the ghost position this returns `None` and does nothing.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let index = self.index?;
+
+        // Removing joins the slot with any adjacent skipblocks, so the next element must be found first
+        let next = self.next_from(index + 1);
+        let value = unsafe { self.colony.remove_unchecked(index) };
+
+        self.index = next;
+        Some(value)
+    }
+
+    /// Replaces the current element, returning the old one.
+    ///
+    /// The element keeps its handle.
+    /// At the ghost position this returns `None` and drops `value`.
+    pub fn replace_current(&mut self, value: T) -> Option<T> {
+        let current = self.current()?;
+        Some(mem::replace(current, value))
+    }
+
+    /// Inserts an element into the colony, without moving the cursor.
+    ///
+    /// The new element may be placed before or after the cursor, see [`Colony::insert`].
+    pub fn insert(&mut self, value: T) -> G::Handle {
+        self.colony.insert(value)
+    }
+
+    /// Returns a shared reference to the underlying colony.
+    pub fn as_colony(&self) -> &Colony<T, G, S> {
+        self.colony
+    }
+
+    // Returns the first element at or after start, if any
+    // Preconditions:
+    // * start is 0 or the slot before it is occupied
+    fn next_from(&self, start: usize) -> Option<usize> {
+        if start >= self.colony.touched {
+            return None;
+        }
+
+        // Either occupied, or the head of a skipblock which must be followed by an element or the end
+        let skipped = unsafe { self.colony.skipfield().read::<RIGHT>(start as isize) };
+        let index = start + skipped;
+        (index < self.colony.touched).then_some(index)
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> Debug for CursorMut<'a, T, G, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("CursorMut")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
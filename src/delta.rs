@@ -0,0 +1,167 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::guard::Guard;
+#[cfg(doc)]
+use crate::Colony;
+use crate::GenerationGuard;
+
+/// The differences between two versions of a colony, as returned by [`Colony::diff`].
+///
+/// Applying the delta with [`Colony::apply_delta`] places every element at the same index and generation as in the
+/// newer version, so handles to the newer version can be used with the colony the delta was applied to.
+pub struct ColonyDelta<T, G: Guard = GenerationGuard> {
+    pub(crate) old_id: G::__Id,
+    pub(crate) new_id: G::__Id,
+    // Set when the versions have different identities, so every element is removed and reinserted
+    pub(crate) reset: bool,
+    // Each list is sorted by index
+    pub(crate) removed: Vec<Entry<()>>,
+    pub(crate) changed: Vec<Entry<T>>,
+    pub(crate) inserted: Vec<Entry<T>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct Entry<T> {
+    pub index: usize,
+    pub state: u32,
+    pub value: T,
+}
+
+impl<T, G: Guard> ColonyDelta<T, G> {
+    /// Returns an iterator over the handles of the elements removed, as they were in the older version.
+    pub fn removed(&self) -> impl Iterator<Item = G::Handle> + '_ {
+        self.removed
+            .iter()
+            .map(|entry| Self::handle(entry, self.old_id))
+    }
+
+    /// Returns an iterator over the handles and new values of the elements whose values changed.
+    pub fn changed(&self) -> impl Iterator<Item = (G::Handle, &T)> + '_ {
+        self.changed
+            .iter()
+            .map(|entry| (Self::handle(entry, self.new_id), &entry.value))
+    }
+
+    /// Returns an iterator over the handles and values of the elements inserted.
+    pub fn inserted(&self) -> impl Iterator<Item = (G::Handle, &T)> + '_ {
+        self.inserted
+            .iter()
+            .map(|entry| (Self::handle(entry, self.new_id), &entry.value))
+    }
+
+    /// Returns `true` if the two versions had the same elements, with the same values.
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.changed.is_empty() && self.inserted.is_empty()
+    }
+
+    fn handle<U>(entry: &Entry<U>, id: G::__Id) -> G::Handle {
+        // Entries are only created from occupied slots, whose states are always valid
+        let guard = G::__with_state(entry.state).expect("invalid state in delta");
+        unsafe { guard.__new_handle(entry.index, id) }
+    }
+}
+
+impl<T: Clone, G: Guard> Clone for ColonyDelta<T, G> {
+    fn clone(&self) -> Self {
+        Self {
+            old_id: self.old_id,
+            new_id: self.new_id,
+            reset: self.reset,
+            removed: self.removed.clone(),
+            changed: self.changed.clone(),
+            inserted: self.inserted.clone(),
+        }
+    }
+}
+
+impl<T: Debug, G: Guard> Debug for ColonyDelta<T, G>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ColonyDelta")
+            .field("removed", &self.removed().collect::<Vec<_>>())
+            .field("changed", &self.changed().collect::<Vec<_>>())
+            .field("inserted", &self.inserted().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// The error returned by [`Colony::apply_delta`] when a delta does not match the colony it is applied to.
+///
+/// When this is returned the colony has not been modified.
+/// Each variant with an index identifies the first slot at which the mismatch was found.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ApplyDeltaError {
+    /// An element removed or changed by the delta does not exist, or has a different generation.
+    Missing {
+        /// The index of the element.
+        index: usize,
+    },
+    /// An element inserted by the delta cannot be placed at its index with its generation.
+    Unavailable {
+        /// The index of the element.
+        index: usize,
+    },
+    /// The delta was computed from a colony with a different identity, and does not replace every element,
+    /// or it would replace every element of a [registered](crate::ColonyRegistry::register) colony.
+    ForeignDelta,
+}
+
+impl Display for ApplyDeltaError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Missing { index } => {
+                write!(
+                    f,
+                    "the element at index {index} to remove or change is missing"
+                )
+            }
+            Self::Unavailable { index } => {
+                write!(
+                    f,
+                    "the slot at index {index} cannot be filled with the inserted element"
+                )
+            }
+            Self::ForeignDelta => write!(f, "the delta was computed from a different colony"),
+        }
+    }
+}
+
+impl Error for ApplyDeltaError {}
+
+/// The error returned by [`Colony::insert_at`] when an element cannot be placed at the given handle.
+///
+/// When this is returned the colony has not been modified, and the element has been dropped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum InsertAtError {
+    /// The slot at the handle's index is already occupied.
+    Occupied,
+    /// The slot at the handle's index cannot be given the handle's generation,
+    /// since a handle created for an earlier element in the slot could then refer to the new element.
+    Unavailable,
+    /// The handle was created by a different colony, and this colony has already allocated.
+    ForeignHandle,
+    /// The colony cannot grow to include the handle's index,
+    /// either because its capacity would overflow or because allocation failed.
+    CannotReserve,
+}
+
+impl Display for InsertAtError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Occupied => write!(f, "the slot is already occupied"),
+            Self::Unavailable => write!(f, "the slot cannot be given the handle's generation"),
+            Self::ForeignHandle => write!(f, "the handle belongs to a different colony"),
+            Self::CannotReserve => {
+                write!(f, "the colony cannot grow to include the handle's index")
+            }
+        }
+    }
+}
+
+impl Error for InsertAtError {}
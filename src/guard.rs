@@ -20,11 +20,17 @@ pub trait Guard: Sealed {
     type Handle;
 
     #[doc(hidden)]
-    type __Id: Copy;
+    type __Id: Copy + Eq;
 
     #[doc(hidden)]
     fn __new() -> Self;
 
+    // Creates the guard of an empty slot which has never been occupied, and so can be filled with any valid state
+    #[doc(hidden)]
+    fn __new_unused() -> Self
+    where
+        Self: Sized;
+
     #[doc(hidden)]
     fn __sentinel_id() -> Self::__Id;
 
@@ -39,11 +45,51 @@ pub trait Guard: Sealed {
     #[doc(hidden)]
     fn __extract_index(handle: &Self::Handle) -> usize;
 
+    // The state the guard of the handle's slot had when the handle was created
+    #[doc(hidden)]
+    fn __extract_state(handle: &Self::Handle) -> u32;
+
+    #[doc(hidden)]
+    fn __extract_id(handle: &Self::Handle) -> Self::__Id;
+
     #[doc(hidden)]
     unsafe fn __fill(&mut self);
 
     #[doc(hidden)]
     unsafe fn __empty(&mut self) -> bool;
+
+    // Returns None if the guard does not track whether its slot is occupied
+    #[doc(hidden)]
+    fn __occupied(&self) -> Option<bool>;
+
+    // Returns whether the slot was emptied for the last time, so can never be filled again
+    #[doc(hidden)]
+    fn __retired(&self) -> bool;
+
+    // The part of an occupied slot's guard considered by structural equality
+    #[doc(hidden)]
+    fn __state(&self) -> u32;
+
+    // Invalidates every existing handle to either of two occupied slots, if handles can be invalidated
+    // Returns false without modifying either guard if the slots have run out of handles
+    #[doc(hidden)]
+    unsafe fn __invalidate_pair(a: &mut Self, b: &mut Self) -> bool;
+
+    // Creates the guard of a newly used, occupied slot with the given state, if it is valid
+    #[doc(hidden)]
+    fn __with_state(state: u32) -> Option<Self>
+    where
+        Self: Sized;
+
+    // Returns whether the slot can be filled with the given state once it is empty, without reusing any handle
+    #[doc(hidden)]
+    fn __can_fill_with(&self, state: u32) -> bool;
+
+    // Preconditions:
+    // * the slot is empty
+    // * __can_fill_with(state) is true
+    #[doc(hidden)]
+    unsafe fn __fill_with(&mut self, state: u32);
 }
 
 /// A marker trait for a [`Guard`] that enables use of safe methods like [`Colony::get`].
@@ -67,6 +113,10 @@ impl Guard for NoGuard {
         Self
     }
 
+    fn __new_unused() -> Self {
+        Self
+    }
+
     fn __sentinel_id() {}
 
     fn __new_id() {}
@@ -79,11 +129,43 @@ impl Guard for NoGuard {
         *handle
     }
 
+    fn __extract_state(_handle: &usize) -> u32 {
+        0
+    }
+
+    fn __extract_id(_handle: &usize) {}
+
     unsafe fn __fill(&mut self) {}
 
     unsafe fn __empty(&mut self) -> bool {
         true
     }
+
+    fn __occupied(&self) -> Option<bool> {
+        None
+    }
+
+    fn __retired(&self) -> bool {
+        false
+    }
+
+    fn __state(&self) -> u32 {
+        0
+    }
+
+    unsafe fn __invalidate_pair(_a: &mut Self, _b: &mut Self) -> bool {
+        true
+    }
+
+    fn __with_state(state: u32) -> Option<Self> {
+        (state == 0).then_some(Self)
+    }
+
+    fn __can_fill_with(&self, state: u32) -> bool {
+        state == 0
+    }
+
+    unsafe fn __fill_with(&mut self, _state: u32) {}
 }
 
 impl Sealed for NoGuard {}
@@ -104,6 +186,10 @@ impl Guard for FlagGuard {
         Self { occupied: true }
     }
 
+    fn __new_unused() -> Self {
+        Self { occupied: false }
+    }
+
     fn __sentinel_id() {}
 
     fn __new_id() {}
@@ -116,6 +202,12 @@ impl Guard for FlagGuard {
         *handle
     }
 
+    fn __extract_state(_handle: &usize) -> u32 {
+        0
+    }
+
+    fn __extract_id(_handle: &usize) {}
+
     unsafe fn __fill(&mut self) {
         self.occupied = true;
     }
@@ -124,6 +216,34 @@ impl Guard for FlagGuard {
         self.occupied = false;
         true
     }
+
+    fn __occupied(&self) -> Option<bool> {
+        Some(self.occupied)
+    }
+
+    fn __retired(&self) -> bool {
+        false
+    }
+
+    fn __state(&self) -> u32 {
+        0
+    }
+
+    unsafe fn __invalidate_pair(_a: &mut Self, _b: &mut Self) -> bool {
+        true
+    }
+
+    fn __with_state(state: u32) -> Option<Self> {
+        (state == 0).then_some(Self { occupied: true })
+    }
+
+    fn __can_fill_with(&self, state: u32) -> bool {
+        state == 0
+    }
+
+    unsafe fn __fill_with(&mut self, _state: u32) {
+        self.occupied = true;
+    }
 }
 
 impl CheckedGuard for FlagGuard {
@@ -141,6 +261,8 @@ const SENTINEL_COLONY_ID: u64 = 0;
 
 const GENERATION_BITS: u32 = u64::BITS - COLONY_ID_BITS;
 const MAX_GENERATION: u32 = u32::pow(2, GENERATION_BITS) - 1;
+// Never stored in a handle, since it exceeds MAX_GENERATION
+const UNUSED_GENERATION: u32 = u32::MAX;
 
 /// An opaque generation assigned to a [`Handle`].
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -177,6 +299,11 @@ impl Generation {
     fn colony_id(&self) -> u64 {
         self.state.get() >> GENERATION_BITS
     }
+
+    // Returns the same generation, belonging to another colony
+    pub(crate) fn with_colony_id(self, colony_id: ColonyId) -> Self {
+        unsafe { Self::new(colony_id.id.get(), self.generation()) }
+    }
 }
 
 impl Debug for Generation {
@@ -205,9 +332,54 @@ pub struct Handle {
     pub generation: Generation,
 }
 
+impl Handle {
+    /// Returns the identity of the colony that created the handle.
+    ///
+    /// This can be used to find the right colony for a handle when handles from many colonies are mixed,
+    /// for example with a [`ColonyRegistry`](crate::ColonyRegistry).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    /// assert_eq!(Some(handle.colony_id()), colony.id());
+    /// ```
+    pub fn colony_id(&self) -> ColonyId {
+        // Handles are only created once their colony has been given an identity
+        ColonyId::new(self.generation.colony_id()).expect("handle without a colony")
+    }
+}
+
+/// An opaque identity of a [`Colony`] using [`GenerationGuard`], as returned by [`Colony::id`] and [`Handle::colony_id`].
+///
+/// Every colony is given an identity distinct from every other colony's when it first allocates,
+/// and a new one whenever it is [cleared](Colony::clear), unless it has been [registered](crate::ColonyRegistry::register).
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ColonyId {
+    id: NonZeroU64,
+}
+
+impl ColonyId {
+    // Returns None for the sentinel ID
+    pub(crate) fn new(id: u64) -> Option<Self> {
+        Some(Self {
+            id: NonZeroU64::new(id)?,
+        })
+    }
+}
+
+impl Debug for ColonyId {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("ColonyId").field(&self.id).finish()
+    }
+}
+
 /// The default guard that guarantees globally unique handles.
 ///
 /// See [`Colony`] for more information about guards.
+#[derive(Clone)]
 #[allow(missing_debug_implementations)]
 pub struct GenerationGuard {
     generation: u32,
@@ -221,6 +393,13 @@ impl Guard for GenerationGuard {
         Self { generation: 0 }
     }
 
+    fn __new_unused() -> Self {
+        // Odd so the slot is empty, and filling it wraps around to the first generation
+        Self {
+            generation: UNUSED_GENERATION,
+        }
+    }
+
     fn __sentinel_id() -> u64 {
         SENTINEL_COLONY_ID
     }
@@ -250,9 +429,17 @@ impl Guard for GenerationGuard {
         handle.index
     }
 
+    fn __extract_state(handle: &Handle) -> u32 {
+        handle.generation.generation()
+    }
+
+    fn __extract_id(handle: &Handle) -> u64 {
+        handle.generation.colony_id()
+    }
+
     unsafe fn __fill(&mut self) {
         debug_assert!(self.generation % 2 == 1);
-        self.generation += 1;
+        self.generation = self.generation.wrapping_add(1);
     }
 
     unsafe fn __empty(&mut self) -> bool {
@@ -260,6 +447,49 @@ impl Guard for GenerationGuard {
         self.generation += 1;
         self.generation != MAX_GENERATION
     }
+
+    fn __occupied(&self) -> Option<bool> {
+        Some(self.generation % 2 == 0)
+    }
+
+    fn __retired(&self) -> bool {
+        self.generation == MAX_GENERATION
+    }
+
+    fn __state(&self) -> u32 {
+        self.generation
+    }
+
+    unsafe fn __invalidate_pair(a: &mut Self, b: &mut Self) -> bool {
+        debug_assert!(a.generation % 2 == 0 && b.generation % 2 == 0);
+
+        // Handles to either slot have generations no greater than the current one
+        let generation = u32::max(a.generation, b.generation) + 2;
+
+        if generation >= MAX_GENERATION {
+            return false;
+        }
+
+        a.generation = generation;
+        b.generation = generation;
+        true
+    }
+
+    fn __with_state(state: u32) -> Option<Self> {
+        (state % 2 == 0 && state < MAX_GENERATION).then_some(Self { generation: state })
+    }
+
+    fn __can_fill_with(&self, state: u32) -> bool {
+        // Emptying an occupied slot takes it to the next (odd) generation, so every handle
+        // ever created for the slot has a generation less than the one after it
+        let lowest = (self.generation | 1).wrapping_add(1);
+        state % 2 == 0 && state >= lowest && state < MAX_GENERATION
+    }
+
+    unsafe fn __fill_with(&mut self, state: u32) {
+        debug_assert!(self.generation % 2 == 1 && self.__can_fill_with(state));
+        self.generation = state;
+    }
 }
 
 impl CheckedGuard for GenerationGuard {
@@ -1,21 +1,21 @@
+use std::fmt;
 use std::fmt::{Debug, Formatter};
 use std::iter::FusedIterator;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
-use std::{fmt, ptr};
 
 use crate::guard::Guard;
 use crate::skipfield::{SkipfieldPtr, RIGHT};
-use crate::{Colony, GenerationGuard};
+use crate::{Colony, GenerationGuard, InterleavedStorage, Storage};
 
-struct RawIter<'a, T, G: Guard = GenerationGuard> {
-    colony: &'a Colony<T, G>,
+struct RawIter<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: &'a Colony<T, G, S>,
     current_index: usize,
     len: usize,
 }
 
-impl<'a, T, G: Guard> RawIter<'a, T, G> {
-    pub(super) fn new(colony: &'a Colony<T, G>) -> Self {
+impl<'a, T, G: Guard, S: Storage> RawIter<'a, T, G, S> {
+    pub(super) fn new(colony: &'a Colony<T, G, S>) -> Self {
         Self {
             colony,
             current_index: 0,
@@ -24,7 +24,7 @@ impl<'a, T, G: Guard> RawIter<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> Iterator for RawIter<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Iterator for RawIter<'a, T, G, S> {
     type Item = (G::Handle, NonNull<T>);
 
     fn next(&mut self) -> Option<(G::Handle, NonNull<T>)> {
@@ -37,12 +37,11 @@ impl<'a, T, G: Guard> Iterator for RawIter<'a, T, G> {
             let offset = skipfield.read::<RIGHT>(self.current_index as isize);
             self.current_index += offset;
 
-            let slot = self.colony.elements.as_ptr().add(self.current_index);
-            let guard = &(*slot).guard;
+            let guard = &*self.colony.slots.guard(self.current_index);
             let handle = G::__new_handle(guard, self.current_index, self.colony.id);
 
-            let elem = ptr::addr_of_mut!((*slot).inner.occupied);
-            let elem = NonNull::new_unchecked(elem as *mut T);
+            let elem = self.colony.slots.value(self.current_index);
+            let elem = NonNull::new_unchecked(elem);
 
             self.current_index += 1;
             self.len -= 1;
@@ -56,11 +55,11 @@ impl<'a, T, G: Guard> Iterator for RawIter<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> FusedIterator for RawIter<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> FusedIterator for RawIter<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> ExactSizeIterator for RawIter<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for RawIter<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> Clone for RawIter<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Clone for RawIter<'a, T, G, S> {
     fn clone(&self) -> Self {
         Self {
             colony: self.colony,
@@ -71,13 +70,13 @@ impl<'a, T, G: Guard> Clone for RawIter<'a, T, G> {
 }
 
 /// The iterator returned by [`Colony::iter`].
-pub struct Iter<'a, T, G: Guard = GenerationGuard> {
-    raw: RawIter<'a, T, G>,
+pub struct Iter<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    raw: RawIter<'a, T, G, S>,
     _marker: PhantomData<&'a T>,
 }
 
-impl<'a, T, G: Guard> Iter<'a, T, G> {
-    pub(super) fn new(colony: &'a Colony<T, G>) -> Self {
+impl<'a, T, G: Guard, S: Storage> Iter<'a, T, G, S> {
+    pub(super) fn new(colony: &'a Colony<T, G, S>) -> Self {
         Self {
             raw: RawIter::new(colony),
             _marker: PhantomData,
@@ -85,7 +84,7 @@ impl<'a, T, G: Guard> Iter<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> Iterator for Iter<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Iterator for Iter<'a, T, G, S> {
     type Item = (G::Handle, &'a T);
 
     fn next(&mut self) -> Option<(G::Handle, &'a T)> {
@@ -98,11 +97,11 @@ impl<'a, T, G: Guard> Iterator for Iter<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> FusedIterator for Iter<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> FusedIterator for Iter<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> ExactSizeIterator for Iter<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for Iter<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> Clone for Iter<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Clone for Iter<'a, T, G, S> {
     fn clone(&self) -> Self {
         Self {
             raw: self.raw.clone(),
@@ -111,7 +110,7 @@ impl<'a, T, G: Guard> Clone for Iter<'a, T, G> {
     }
 }
 
-impl<'a, T: Debug, G: Guard> Debug for Iter<'a, T, G>
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for Iter<'a, T, G, S>
 where
     G::Handle: Debug,
 {
@@ -121,19 +120,19 @@ where
 }
 
 /// The iterator returned by [`Colony::values`].
-pub struct Values<'a, T, G: Guard = GenerationGuard> {
-    iter: Iter<'a, T, G>,
+pub struct Values<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    iter: Iter<'a, T, G, S>,
 }
 
-impl<'a, T, G: Guard> Values<'a, T, G> {
-    pub(super) fn new(colony: &'a Colony<T, G>) -> Self {
+impl<'a, T, G: Guard, S: Storage> Values<'a, T, G, S> {
+    pub(super) fn new(colony: &'a Colony<T, G, S>) -> Self {
         Self {
             iter: Iter::new(colony),
         }
     }
 }
 
-impl<'a, T, G: Guard> Iterator for Values<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Iterator for Values<'a, T, G, S> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<&'a T> {
@@ -145,11 +144,11 @@ impl<'a, T, G: Guard> Iterator for Values<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> FusedIterator for Values<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> FusedIterator for Values<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> ExactSizeIterator for Values<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for Values<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> Clone for Values<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Clone for Values<'a, T, G, S> {
     fn clone(&self) -> Self {
         Self {
             iter: self.iter.clone(),
@@ -157,27 +156,27 @@ impl<'a, T, G: Guard> Clone for Values<'a, T, G> {
     }
 }
 
-impl<'a, T: Debug, G: Guard> Debug for Values<'a, T, G> {
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for Values<'a, T, G, S> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_list().entries(self.clone()).finish()
     }
 }
 
 /// The iterator returned by [`Colony::iter_mut`].
-pub struct IterMut<'a, T, G: Guard = GenerationGuard> {
-    raw: RawIter<'a, T, G>,
+pub struct IterMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    raw: RawIter<'a, T, G, S>,
     _marker: PhantomData<&'a mut T>,
 }
 
-impl<'a, T, G: Guard> IterMut<'a, T, G> {
-    pub(super) fn new(colony: &'a mut Colony<T, G>) -> Self {
+impl<'a, T, G: Guard, S: Storage> IterMut<'a, T, G, S> {
+    pub(super) fn new(colony: &'a mut Colony<T, G, S>) -> Self {
         Self {
             raw: RawIter::new(colony),
             _marker: PhantomData,
         }
     }
 
-    fn reborrow(&self) -> Iter<T, G> {
+    fn reborrow(&self) -> Iter<T, G, S> {
         Iter {
             raw: self.raw.clone(),
             _marker: PhantomData,
@@ -185,7 +184,7 @@ impl<'a, T, G: Guard> IterMut<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> Iterator for IterMut<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Iterator for IterMut<'a, T, G, S> {
     type Item = (G::Handle, &'a mut T);
 
     fn next(&mut self) -> Option<(G::Handle, &'a mut T)> {
@@ -198,11 +197,11 @@ impl<'a, T, G: Guard> Iterator for IterMut<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> FusedIterator for IterMut<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> FusedIterator for IterMut<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> ExactSizeIterator for IterMut<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for IterMut<'a, T, G, S> {}
 
-impl<'a, T: Debug, G: Guard> Debug for IterMut<'a, T, G>
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for IterMut<'a, T, G, S>
 where
     G::Handle: Debug,
 {
@@ -212,25 +211,25 @@ where
 }
 
 /// The iterator returned by [`Colony::values_mut`].
-pub struct ValuesMut<'a, T, G: Guard = GenerationGuard> {
-    iter: IterMut<'a, T, G>,
+pub struct ValuesMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    iter: IterMut<'a, T, G, S>,
 }
 
-impl<'a, T, G: Guard> ValuesMut<'a, T, G> {
-    pub(super) fn new(colony: &'a mut Colony<T, G>) -> Self {
+impl<'a, T, G: Guard, S: Storage> ValuesMut<'a, T, G, S> {
+    pub(super) fn new(colony: &'a mut Colony<T, G, S>) -> Self {
         Self {
             iter: IterMut::new(colony),
         }
     }
 
-    fn reborrow(&self) -> Values<T, G> {
+    fn reborrow(&self) -> Values<T, G, S> {
         Values {
             iter: self.iter.reborrow(),
         }
     }
 }
 
-impl<'a, T, G: Guard> Iterator for ValuesMut<'a, T, G> {
+impl<'a, T, G: Guard, S: Storage> Iterator for ValuesMut<'a, T, G, S> {
     type Item = &'a mut T;
 
     fn next(&mut self) -> Option<&'a mut T> {
@@ -242,11 +241,11 @@ impl<'a, T, G: Guard> Iterator for ValuesMut<'a, T, G> {
     }
 }
 
-impl<'a, T, G: Guard> FusedIterator for ValuesMut<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> FusedIterator for ValuesMut<'a, T, G, S> {}
 
-impl<'a, T, G: Guard> ExactSizeIterator for ValuesMut<'a, T, G> {}
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for ValuesMut<'a, T, G, S> {}
 
-impl<'a, T: Debug, G: Guard> Debug for ValuesMut<'a, T, G> {
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for ValuesMut<'a, T, G, S> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         f.debug_list().entries(self.reborrow()).finish()
     }
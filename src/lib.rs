@@ -3,23 +3,63 @@
 #![warn(missing_docs)]
 
 use std::alloc::{alloc, dealloc, handle_alloc_error, Layout, LayoutError};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
 use std::fmt::{Debug, Formatter};
-use std::mem::ManuallyDrop;
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut};
 use std::panic::{RefUnwindSafe, UnwindSafe};
 use std::ptr::NonNull;
 use std::{fmt, mem, ptr};
 
+pub use concurrent::*;
+pub use cursor::*;
+pub use delta::*;
 pub use guard::*;
 pub use iter::*;
-
+pub use lru::*;
+pub use ordered::*;
+pub use persistent::*;
+pub use registry::*;
+pub use remap::*;
+pub use removal::*;
+pub use reuse::*;
+pub use run::*;
+pub use set::*;
+pub use stats::*;
+pub use storage::*;
+pub use tracked::*;
+pub use validate::*;
+
+use crate::delta::Entry;
 use crate::index_opt::IndexOpt;
-use crate::skipfield::{SkipfieldElement, SkipfieldPtr};
+use crate::skipfield::{SkipfieldElement, SkipfieldPtr, LEFT, RIGHT};
+use crate::slots::{Slots, SlotsLayout, Unoccupied};
 
+mod concurrent;
+mod cursor;
+mod delta;
 mod guard;
 mod index_opt;
 mod iter;
+mod lru;
+mod ordered;
+mod persistent;
+mod registry;
+mod remap;
+mod removal;
+mod reuse;
+mod run;
+mod set;
 mod skipfield;
+mod slots;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod test_util;
+mod tracked;
+pub mod tree;
+mod validate;
 
 /// A `Colony` that uses `FlagGuard`, see the documentation for [`Colony`] for more information about guards.
 ///
@@ -31,13 +71,25 @@ pub type FlaggedColony<T> = Colony<T, FlagGuard>;
 /// Also see [`Colony::unguarded`].
 pub type UnguardedColony<T> = Colony<T, NoGuard>;
 
+/// A `Colony` that uses `SeparatedStorage`, see the documentation for [`Colony`] for more information about storages.
+///
+/// Also see [`Colony::separated`].
+pub type SeparatedColony<T, G = GenerationGuard> = Colony<T, G, SeparatedStorage>;
+
 const EMPTY_SKIPFIELD: &[SkipfieldElement] = &[0, 0];
 
 const MAX_CAPACITY: usize = isize::MAX as usize;
 
+// Why reserving failed, which is a panic or abort everywhere except insert_at
+enum ReserveError {
+    CapacityOverflow,
+    Layout,
+    Alloc(Layout),
+}
+
 #[doc = include_str!("./doc.md")]
-pub struct Colony<T, G: Guard = GenerationGuard> {
-    elements: NonNull<Slot<T, G>>,
+pub struct Colony<T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    slots: Slots<T, G, S>,
     // Initialized from [-1, capacity]
     // Element at -1 and elements in [len, capacity] are zero
     // Valid for reads (but not writes) even when capacity is zero
@@ -46,7 +98,18 @@ pub struct Colony<T, G: Guard = GenerationGuard> {
     touched: usize,
     len: usize,
     next_free: IndexOpt,
+    reuse: ReusePolicy,
+    // Every slot in the freelist when using `ReusePolicy::LowestFirst`, otherwise empty
+    // Slots filled from the middle of the freelist are recorded in `lowest_taken` rather than searched for,
+    // and are discarded from both heaps once they reach the top, so the top of `lowest_free` is always empty
+    lowest_free: BinaryHeap<Reverse<usize>>,
+    lowest_taken: BinaryHeap<Reverse<usize>>,
+    // Empty slots which can never be reused, so are skipped but not in the freelist
+    // They split the reusable slots of their skipblock into separate runs, each linked in order in the freelist
+    retired: BTreeSet<usize>,
     id: G::__Id,
+    // Set once the colony is registered, after which it keeps its identity, and so the generations of its slots, when cleared
+    pinned: bool,
 }
 
 impl<T> Colony<T> {
@@ -66,6 +129,44 @@ impl<T> Colony<T> {
     }
 }
 
+impl<T, S: Storage> Colony<T, GenerationGuard, S> {
+    /// Returns the identity of the colony, which is shared by every handle it creates.
+    ///
+    /// Returns `None` if the colony has not allocated yet, since it is only given an identity upon its first allocation.
+    /// The identity changes whenever the colony is [cleared](Colony::clear),
+    /// and can be taken on from another colony by [`apply_delta`](Colony::apply_delta) or [`insert_at`](Colony::insert_at),
+    /// unless the colony has been [registered](crate::ColonyRegistry::register), after which it never changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// assert_eq!(colony.id(), None);
+    ///
+    /// let handle = colony.insert("foo");
+    /// assert_eq!(colony.id(), Some(handle.colony_id()));
+    /// ```
+    pub fn id(&self) -> Option<ColonyId> {
+        ColonyId::new(self.id)
+    }
+
+    // Gives the colony an identity now if it has not allocated yet
+    fn ensure_id(&mut self) -> ColonyId {
+        if self.id == GenerationGuard::__sentinel_id() {
+            self.id = GenerationGuard::__new_id();
+        }
+
+        ColonyId::new(self.id).unwrap()
+    }
+
+    // Gives the colony an identity now if it has not allocated yet, and keeps it from then on
+    fn pin_id(&mut self) -> ColonyId {
+        self.pinned = true;
+        self.ensure_id()
+    }
+}
+
 impl<T> FlaggedColony<T> {
     /// Constructs an empty colony using [`FlagGuard`].
     ///
@@ -100,7 +201,25 @@ impl<T> UnguardedColony<T> {
     }
 }
 
-impl<T, G: Guard> Default for Colony<T, G> {
+impl<T> SeparatedColony<T> {
+    /// Constructs an empty colony using [`GenerationGuard`] and [`SeparatedStorage`].
+    ///
+    /// Does not allocate.
+    /// To use [`SeparatedStorage`] with another guard, use [`Colony::default`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, FlagGuard, SeparatedColony};
+    /// let colony: SeparatedColony<i32> = Colony::separated();
+    /// let colony: SeparatedColony<i32, FlagGuard> = Colony::default();
+    /// ```
+    pub fn separated() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, G: Guard, S: Storage> Default for Colony<T, G, S> {
     fn default() -> Self {
         let skipfield = unsafe {
             let ptr = EMPTY_SKIPFIELD.as_ptr().add(1) as *mut _;
@@ -108,18 +227,23 @@ impl<T, G: Guard> Default for Colony<T, G> {
         };
 
         Self {
-            elements: NonNull::dangling(),
+            slots: Slots::dangling(),
             skipfield,
             capacity: 0,
             touched: 0,
             len: 0,
             next_free: IndexOpt::none(),
+            reuse: ReusePolicy::default(),
+            lowest_free: BinaryHeap::new(),
+            lowest_taken: BinaryHeap::new(),
+            retired: BTreeSet::new(),
             id: G::__sentinel_id(),
+            pinned: false,
         }
     }
 }
 
-impl<T, G: Guard> Colony<T, G> {
+impl<T, G: Guard, S: Storage> Colony<T, G, S> {
     const MIN_NON_ZERO_CAP: usize = if mem::size_of::<T>() == 1 {
         8
     } else if mem::size_of::<T>() <= 1024 {
@@ -130,16 +254,41 @@ impl<T, G: Guard> Colony<T, G> {
 
     // Preconditions:
     // * index < touched
-    unsafe fn slot(&self, index: usize) -> &Slot<T, G> {
+    unsafe fn guard(&self, index: usize) -> &G {
+        debug_assert!(index < self.touched);
+        &*self.slots.guard(index)
+    }
+
+    // Preconditions:
+    // * index < touched
+    // * elements[index] is occupied
+    unsafe fn occupied(&self, index: usize) -> &T {
+        debug_assert!(index < self.touched);
+        &*self.slots.value(index)
+    }
+
+    // Preconditions:
+    // * index < touched
+    // * elements[index] is occupied
+    unsafe fn occupied_mut(&mut self, index: usize) -> &mut T {
+        debug_assert!(index < self.touched);
+        &mut *self.slots.value(index)
+    }
+
+    // Preconditions:
+    // * index < touched
+    // * elements[index] is unoccupied
+    unsafe fn unoccupied(&self, index: usize) -> &Unoccupied {
         debug_assert!(index < self.touched);
-        &*self.elements.as_ptr().add(index)
+        &*self.slots.links(index)
     }
 
     // Preconditions:
     // * index < touched
-    unsafe fn slot_mut(&mut self, index: usize) -> &mut Slot<T, G> {
+    // * elements[index] is unoccupied
+    unsafe fn unoccupied_mut(&mut self, index: usize) -> &mut Unoccupied {
         debug_assert!(index < self.touched);
-        &mut *self.elements.as_ptr().add(index)
+        &mut *self.slots.links(index)
     }
 
     fn skipfield(&self) -> SkipfieldPtr {
@@ -200,6 +349,307 @@ impl<T, G: Guard> Colony<T, G> {
         self.capacity
     }
 
+    /// Computes statistics about the memory usage and fragmentation of the colony.
+    ///
+    /// This is an `O(n)` operation, where `n` is the number of slots that have been used since the colony was created or last cleared.
+    /// See [`Stats`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handles: Vec<_> = (0..100).map(|i| colony.insert(i)).collect();
+    ///
+    /// for &handle in &handles[10..60] {
+    ///     colony.remove(handle);
+    /// }
+    ///
+    /// let stats = colony.stats();
+    /// assert_eq!(stats.len, 50);
+    /// assert_eq!(stats.touched, 100);
+    /// assert_eq!(stats.skipblocks, 1);
+    /// assert_eq!(stats.largest_skipblock, 50);
+    /// assert_eq!(stats.freelist_len, 50);
+    /// ```
+    pub fn stats(&self) -> Stats {
+        let mut skipblocks = 0;
+        let mut largest_skipblock = 0;
+        let mut index = 0;
+
+        unsafe {
+            while index < self.touched {
+                let skipped = self.skipfield().read::<RIGHT>(index as isize);
+
+                if skipped > 0 {
+                    skipblocks += 1;
+                    largest_skipblock = usize::max(largest_skipblock, skipped);
+                    index += skipped;
+                } else {
+                    index += self.skipfield().unskipped_run(index, self.touched - index);
+                }
+            }
+        }
+
+        let mut freelist_len = 0;
+        let mut next = self.next_free;
+
+        while let Some(free) = next.as_opt() {
+            freelist_len += 1;
+            next = unsafe { self.unoccupied(free).next };
+        }
+
+        let allocated_bytes = if self.capacity > 0 {
+            unsafe { Self::layout(self.capacity).unwrap_unchecked().layout.size() }
+        } else {
+            0
+        } + (self.lowest_free.capacity() + self.lowest_taken.capacity())
+            * mem::size_of::<usize>();
+
+        Stats {
+            len: self.len,
+            touched: self.touched,
+            capacity: self.capacity,
+            skipblocks,
+            largest_skipblock,
+            freelist_len,
+            retired: self.retired.len(),
+            allocated_bytes,
+        }
+    }
+
+    /// Checks the internal structure of the colony, returning the first inconsistency found.
+    ///
+    /// A colony can only become corrupted through incorrect use of unsafe methods,
+    /// so this is mostly useful to debug code calling methods such as [`remove_unchecked`](Colony::remove_unchecked).
+    /// This is an `O(n)` operation, where `n` is the number of slots that have been used since the colony was created or last cleared,
+    /// and it allocates a temporary buffer of that size.
+    ///
+    /// If the `debug-validate` feature is enabled, this is called after every operation that modifies the structure of a colony,
+    /// panicking if an inconsistency is found.
+    /// This makes each such operation `O(n)`, so the feature is only meant for debugging.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::flagged();
+    /// colony.extend(0..10);
+    ///
+    /// unsafe {
+    ///     colony.remove_unchecked(5);
+    /// }
+    ///
+    /// assert_eq!(colony.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), CorruptionError> {
+        #[derive(Copy, Clone, Eq, PartialEq)]
+        enum SlotState {
+            Occupied,
+            Head,
+            Skipped,
+            Free,
+        }
+
+        unsafe {
+            let raw_skipfield = self.skipfield.as_ptr();
+            let unused = std::iter::once(-1).chain(self.touched as isize..=self.capacity as isize);
+
+            for index in unused {
+                if *raw_skipfield.offset(index) != 0 {
+                    return Err(CorruptionError::UnzeroedSkipfield { index });
+                }
+            }
+
+            let mut states = Vec::with_capacity(self.touched);
+            let mut occupied = 0;
+
+            while states.len() < self.touched {
+                let start = states.len();
+                let size = self.skipfield().read::<RIGHT>(start as isize);
+
+                if size == 0 {
+                    states.push(SlotState::Occupied);
+                    occupied += 1;
+                    continue;
+                }
+
+                if size > self.touched - start {
+                    return Err(CorruptionError::SkipblockOutOfBounds { start, size });
+                }
+
+                let tail = self.skipfield().read::<LEFT>((start + size - 1) as isize);
+
+                if tail != size {
+                    let head = size;
+                    return Err(CorruptionError::SkipblockMismatch { start, head, tail });
+                }
+
+                if start > 0 && states[start - 1] != SlotState::Occupied {
+                    return Err(CorruptionError::AdjacentSkipblocks { start });
+                }
+
+                states.push(SlotState::Head);
+                states.extend(std::iter::repeat_n(SlotState::Skipped, size - 1));
+            }
+
+            for (index, &state) in states.iter().enumerate() {
+                let occupied = state == SlotState::Occupied;
+                let guard = self.guard(index);
+
+                if guard.__occupied().is_some_and(|guard| guard != occupied) {
+                    return Err(CorruptionError::GuardMismatch { index, occupied });
+                }
+
+                if self.retired.contains(&index) != (!occupied && guard.__retired()) {
+                    return Err(CorruptionError::RetiredMismatch { index });
+                }
+            }
+
+            if let Some(&index) = self.retired.range(self.touched..).next() {
+                return Err(CorruptionError::RetiredMismatch { index });
+            }
+
+            if occupied != self.len {
+                let len = self.len;
+                return Err(CorruptionError::LenMismatch { len, occupied });
+            }
+
+            let mut prev = None;
+            let mut next = self.next_free.as_opt();
+
+            while let Some(index) = next {
+                match states.get(index) {
+                    Some(SlotState::Head | SlotState::Skipped)
+                        if !self.retired.contains(&index) => {}
+                    Some(SlotState::Free) => return Err(CorruptionError::FreelistCycle { index }),
+                    _ => return Err(CorruptionError::InvalidFreelistNode { index }),
+                }
+
+                let links = self.unoccupied(index);
+
+                if links.prev.as_opt() != prev {
+                    return Err(CorruptionError::FreelistLinkMismatch { index });
+                }
+
+                // Insertion relies on the freelist always giving the first of a run of reusable slots
+                let follows_reusable = index > 0
+                    && states[index - 1] != SlotState::Occupied
+                    && !self.retired.contains(&(index - 1));
+
+                if follows_reusable && prev != Some(index - 1) {
+                    return Err(CorruptionError::FreelistOrder { index });
+                }
+
+                states[index] = SlotState::Free;
+                prev = Some(index);
+                next = links.next.as_opt();
+            }
+
+            let missing = states.iter().enumerate().position(|(index, &state)| {
+                matches!(state, SlotState::Head | SlotState::Skipped)
+                    && !self.retired.contains(&index)
+            });
+
+            if let Some(index) = missing {
+                return Err(CorruptionError::MissingFromFreelist { index });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the policy used to choose which empty slot to fill when inserting.
+    ///
+    /// See [`ReusePolicy`] for more information.
+    pub fn reuse_policy(&self) -> ReusePolicy {
+        self.reuse
+    }
+
+    /// Sets the policy used to choose which empty slot to fill when inserting.
+    ///
+    /// Switching to [`ReusePolicy::LowestFirst`] is an `O(m)` operation, where `m` is the number of empty slots,
+    /// as it needs to index them in a separate allocation.
+    /// Switching to any other policy is an `O(1)` operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, ReusePolicy};
+    /// let mut colony = Colony::flagged();
+    /// colony.set_reuse_policy(ReusePolicy::LowestFirst);
+    /// colony.extend(0..10);
+    ///
+    /// colony.remove(7);
+    /// colony.remove(2);
+    /// colony.remove(4);
+    ///
+    /// assert_eq!(colony.insert(10), 2);
+    /// assert_eq!(colony.insert(11), 4);
+    /// assert_eq!(colony.insert(12), 7);
+    /// ```
+    pub fn set_reuse_policy(&mut self, policy: ReusePolicy) {
+        if policy == ReusePolicy::LowestFirst {
+            if self.reuse != ReusePolicy::LowestFirst {
+                let mut free = Vec::new();
+                let mut next = self.next_free.as_opt();
+
+                while let Some(index) = next {
+                    free.push(Reverse(index));
+                    next = unsafe { self.unoccupied(index).next.as_opt() };
+                }
+
+                self.lowest_free = BinaryHeap::from(free);
+            }
+        } else {
+            self.lowest_free = BinaryHeap::new();
+        }
+
+        self.lowest_taken = BinaryHeap::new();
+        self.reuse = policy;
+    }
+
+    // Returns the slot the next insertion should fill, if any
+    fn first_free(&self) -> Option<usize> {
+        match self.reuse {
+            ReusePolicy::LowestFirst => self.lowest_free.peek().map(|&Reverse(index)| index),
+            ReusePolicy::Lifo | ReusePolicy::FillCurrent => self.next_free.as_opt(),
+        }
+    }
+
+    // Records that a slot in the freelist has been filled, when using `ReusePolicy::LowestFirst`
+    fn take_free(&mut self, index: usize) {
+        if self.reuse == ReusePolicy::LowestFirst {
+            self.lowest_taken.push(Reverse(index));
+            self.discard_taken();
+        }
+    }
+
+    // Discards slots from the top of `lowest_free` which have since been filled
+    fn discard_taken(&mut self) {
+        while let Some(&Reverse(taken)) = self.lowest_taken.peek() {
+            match self.lowest_free.peek() {
+                Some(&Reverse(free)) if free < taken => break,
+                Some(&Reverse(free)) if free == taken => {
+                    self.lowest_free.pop();
+                    self.lowest_taken.pop();
+                }
+                // The slot was never in the heap, which happens if it was emptied under a different policy
+                _ => {
+                    self.lowest_taken.pop();
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn debug_validate(&self) {
+        #[cfg(feature = "debug-validate")]
+        if let Err(error) = self.validate() {
+            panic!("colony is corrupted: {error}");
+        }
+    }
+
     /// Returns a reference to a element by the handle returned by [`insert`](Colony::insert).
     ///
     /// Some care needs to be taken with respect to aliasing of handles when not using [`GenerationGuard`].
@@ -227,13 +677,11 @@ impl<T, G: Guard> Colony<T, G> {
         }
 
         unsafe {
-            let slot = self.slot(index);
-
-            if !slot.guard.__check(&handle, self.id) {
+            if !self.guard(index).__check(&handle, self.id) {
                 return None;
             }
 
-            Some(slot.occupied())
+            Some(self.occupied(index))
         }
     }
 
@@ -258,7 +706,7 @@ impl<T, G: Guard> Colony<T, G> {
     /// }
     /// ```
     pub unsafe fn get_unchecked(&self, index: usize) -> &T {
-        self.slot(index).occupied()
+        self.occupied(index)
     }
 
     /// Returns a reference to a element by the handle returned by [`insert`](Colony::insert).
@@ -275,14 +723,11 @@ impl<T, G: Guard> Colony<T, G> {
         }
 
         unsafe {
-            let colony_id = self.id;
-            let slot = self.slot_mut(index);
-
-            if !slot.guard.__check(&handle, colony_id) {
+            if !self.guard(index).__check(&handle, self.id) {
                 return None;
             }
 
-            Some(slot.occupied_mut())
+            Some(self.occupied_mut(index))
         }
     }
 
@@ -294,17 +739,12 @@ impl<T, G: Guard> Colony<T, G> {
     ///
     /// An element must exist at the index provided.
     pub unsafe fn get_unchecked_mut(&mut self, index: usize) -> &mut T {
-        self.slot_mut(index).occupied_mut()
+        self.occupied_mut(index)
     }
 
-    /// Inserts an element into the colony at an unspecified index.
-    ///
-    /// Some care needs to be taken with respect to aliasing of handles when not using [`GenerationGuard`].
-    /// See [`Colony`] for more information.
+    /// Returns a handle to the element at an index, if there is one.
     ///
-    /// # Panics
-    ///
-    /// See [`reserve`](Self::reserve).
+    /// This converts an index (such as [`Handle::index`]) back into a full handle.
     ///
     /// # Examples
     ///
@@ -312,1042 +752,4346 @@ impl<T, G: Guard> Colony<T, G> {
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
     /// let handle = colony.insert("foo");
-    /// assert_eq!(colony[handle], "foo");
+    ///
+    /// assert_eq!(colony.handle_at(handle.index), Some(handle));
+    /// colony.remove(handle);
+    /// assert_eq!(colony.handle_at(handle.index), None);
     /// ```
-    pub fn insert(&mut self, value: T) -> G::Handle {
-        unsafe {
-            if let Some(free) = self.next_free.as_opt() {
-                self.insert_into_free(free, value)
-            } else {
-                self.insert_at_end(value)
-            }
-        }
-    }
-
-    // Preconditions:
-    // * elements[free] is unoccupied and the head of its skipblock
-    // * len < touched
-    unsafe fn insert_into_free(&mut self, free: usize, value: T) -> G::Handle {
-        debug_assert!(self.len < self.touched);
-
-        self.skipfield().unskip_leftmost(free);
-        self.remove_skipblock_from_skiplist(free, free);
-
-        self.len += 1;
-
-        let colony_id = self.id;
-        let slot = self.slot_mut(free);
-        slot.fill(value);
-        G::__new_handle(&slot.guard, free, colony_id)
-    }
-
-    // Preconditions:
-    // * len == touched
-    unsafe fn insert_at_end(&mut self, value: T) -> G::Handle {
-        if self.len == self.capacity {
-            self.reserve(1);
+    pub fn handle_at(&self, index: usize) -> Option<G::Handle>
+    where
+        G: CheckedGuard,
+    {
+        if index >= self.touched {
+            return None;
         }
 
-        self.insert_at_end_unchecked(value)
-    }
-
-    // Preconditions:
-    // * len == touched < capacity
-    unsafe fn insert_at_end_unchecked(&mut self, value: T) -> G::Handle {
-        debug_assert!(self.len == self.touched);
-
-        let slot = Slot::new_full(value);
-        let handle = G::__new_handle(&slot.guard, self.touched, self.id);
-
         unsafe {
-            self.elements.as_ptr().add(self.touched).write(slot);
-        }
+            let guard = self.guard(index);
 
-        self.touched += 1;
-        self.len += 1;
+            if guard.__occupied() != Some(true) {
+                return None;
+            }
 
-        handle
+            Some(G::__new_handle(guard, index, self.id))
+        }
     }
 
-    /// Removes the element with the given handle, if it exists.
+    /// Returns the element at an index along with its handle, if there is one.
     ///
-    /// Some care needs to be taken with respect to aliasing of handle when not using [`GenerationGuard`].
-    /// See [`Colony`] for more information.
+    /// This is useful when only the index of an element is known, such as from [`Handle::index`],
+    /// and the current handle for it is needed as well.
+    /// Note that if the element at the index was replaced, this returns the new element.
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
-    /// let handle = colony.insert("foo");
-    /// assert_eq!(colony.remove(handle), Some("foo"));
-    /// assert_eq!(colony.remove(handle), None);
+    /// let foo = colony.insert("foo");
+    ///
+    /// assert_eq!(colony.get_by_index(foo.index), Some((foo, &"foo")));
+    ///
+    /// colony.remove(foo);
+    /// assert_eq!(colony.get_by_index(foo.index), None);
+    ///
+    /// let bar = colony.insert("bar");
+    /// assert_eq!(bar.index, foo.index);
+    /// assert_eq!(colony.get_by_index(foo.index), Some((bar, &"bar")));
     /// ```
-    pub fn remove(&mut self, handle: G::Handle) -> Option<T>
+    pub fn get_by_index(&self, index: usize) -> Option<(G::Handle, &T)>
     where
         G: CheckedGuard,
     {
-        let index = G::__extract_index(&handle);
-
-        if index >= self.touched {
-            return None;
-        }
-
-        unsafe {
-            let colony_id = self.id;
-            let slot = self.slot_mut(index);
-
-            if !slot.guard.__check(&handle, colony_id) {
-                return None;
-            }
-
-            Some(self.remove_unchecked(index))
-        }
+        let handle = self.handle_at(index)?;
+        Some((handle, unsafe { self.occupied(index) }))
     }
 
-    /// Removes the element with the given index, assuming it exists.
+    /// Returns the element at an index mutably along with its handle, if there is one.
     ///
-    /// This is mostly useful with [`UnguardedColony`] where [`remove`](Colony::remove) cannot be used.
-    ///
-    /// # Safety
-    ///
-    /// An element must exist with the index provided.
+    /// See [`get_by_index`](Colony::get_by_index) for more information.
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
-    /// let handle = colony.insert("foo");
+    /// let handle = colony.insert(1);
     ///
-    /// unsafe {
-    ///     let result = colony.remove_unchecked(handle.index);
-    ///     assert_eq!(result, "foo");
+    /// if let Some((_, value)) = colony.get_by_index_mut(handle.index) {
+    ///     *value += 1;
     /// }
+    ///
+    /// assert_eq!(colony[handle], 2);
     /// ```
-    pub unsafe fn remove_unchecked(&mut self, index: usize) -> T {
-        unsafe {
-            let (result, reuse) = self.slot_mut(index).empty();
-            let (start, end) = self.skipfield().skip(index);
-
-            if reuse {
-                let has_left = start != index;
-                let has_right = end != index;
-
-                if !has_left && !has_right {
-                    self.stitch_no_left_no_right(index);
-                } else if has_left && !has_right {
-                    self.stitch_only_left(index);
-                } else if !has_left && has_right {
-                    self.stitch_only_right(index);
-                } else {
-                    self.stitch_left_and_right(index, start, end);
-                }
-            }
+    pub fn get_by_index_mut(&mut self, index: usize) -> Option<(G::Handle, &mut T)>
+    where
+        G: CheckedGuard,
+    {
+        let handle = self.handle_at(index)?;
+        Some((handle, unsafe { self.occupied_mut(index) }))
+    }
 
-            self.len -= 1;
-            result
+    /// Swaps the values of the elements with the given handles, returning whether both exist.
+    ///
+    /// The guards are left untouched, so both handles remain valid and each now refers to the other value.
+    /// Use [`swap_identities`](Colony::swap_identities) to move the handles along with the values instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let foo = colony.insert("foo");
+    /// let bar = colony.insert("bar");
+    ///
+    /// assert!(colony.swap(foo, bar));
+    /// assert_eq!(colony[foo], "bar");
+    /// assert_eq!(colony[bar], "foo");
+    /// ```
+    pub fn swap(&mut self, a: G::Handle, b: G::Handle) -> bool
+    where
+        G: CheckedGuard,
+    {
+        let (Some(a), Some(b)) = (self.checked_index(&a), self.checked_index(&b)) else {
+            return false;
+        };
+
+        if a != b {
+            unsafe {
+                ptr::swap_nonoverlapping(self.slots.value(a), self.slots.value(b), 1);
+            }
         }
-    }
 
-    unsafe fn stitch_no_left_no_right(&mut self, index: usize) {
-        self.add_skipblock_to_skiplist(index, index);
+        true
     }
 
-    unsafe fn stitch_only_left(&mut self, index: usize) {
-        let next = mem::replace(
-            &mut self.slot_mut(index - 1).unoccupied_mut().next,
-            IndexOpt::some(index),
-        );
+    /// Moves the elements with the given handles into each other's slots, returning their new handles.
+    ///
+    /// The first handle returned is for the element that was at `a`, and the second for the element that was at `b`.
+    /// With [`GenerationGuard`], the old handles are invalidated.
+    /// Returns `None` without modifying the colony if either handle is invalid,
+    /// or if the slots have run out of generations (see [`Colony`] for more information about the generation limit).
+    ///
+    /// With [`FlagGuard`], handles are indices, so this is equivalent to [`swap`](Colony::swap)
+    /// and the handles returned are `(b, a)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let foo = colony.insert("foo");
+    /// let bar = colony.insert("bar");
+    ///
+    /// let (new_foo, new_bar) = colony.swap_identities(foo, bar).unwrap();
+    /// assert_eq!(colony[new_foo], "foo");
+    /// assert_eq!(colony[new_bar], "bar");
+    /// assert_eq!(new_foo.index, bar.index);
+    ///
+    /// assert_eq!(colony.get(foo), None);
+    /// ```
+    pub fn swap_identities(&mut self, a: G::Handle, b: G::Handle) -> Option<(G::Handle, G::Handle)>
+    where
+        G: CheckedGuard,
+    {
+        let a_index = self.checked_index(&a)?;
+        let b_index = self.checked_index(&b)?;
 
-        if let Some(next) = next.as_opt() {
-            self.slot_mut(next).unoccupied_mut().prev = IndexOpt::some(index);
+        if a_index == b_index {
+            return Some((a, b));
         }
 
-        *self.slot_mut(index).unoccupied_mut() = Unoccupied {
-            prev: IndexOpt::some(index - 1),
-            next,
-        };
-    }
+        unsafe {
+            if !G::__invalidate_pair(
+                &mut *self.slots.guard(a_index),
+                &mut *self.slots.guard(b_index),
+            ) {
+                return None;
+            }
 
-    unsafe fn stitch_only_right(&mut self, index: usize) {
-        let prev = mem::replace(
-            &mut self.slot_mut(index + 1).unoccupied_mut().prev,
-            IndexOpt::some(index),
-        );
+            ptr::swap_nonoverlapping(self.slots.value(a_index), self.slots.value(b_index), 1);
 
-        match prev.as_opt() {
-            Some(prev) => self.slot_mut(prev).unoccupied_mut().next = IndexOpt::some(index),
-            None => self.next_free = IndexOpt::some(index),
+            let a = G::__new_handle(self.guard(b_index), b_index, self.id);
+            let b = G::__new_handle(self.guard(a_index), a_index, self.id);
+            Some((a, b))
         }
+    }
 
-        *self.slot_mut(index).unoccupied_mut() = Unoccupied {
-            prev,
-            next: IndexOpt::some(index + 1),
-        };
+    /// Replaces the value of the element with the given handle, returning the old value.
+    ///
+    /// The handle remains valid.
+    /// If the handle is invalid, `value` is dropped and `None` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    ///
+    /// assert_eq!(colony.replace(handle, "bar"), Some("foo"));
+    /// assert_eq!(colony[handle], "bar");
+    /// ```
+    pub fn replace(&mut self, handle: G::Handle, value: T) -> Option<T>
+    where
+        G: CheckedGuard,
+    {
+        self.get_mut(handle)
+            .map(|current| mem::replace(current, value))
     }
 
-    unsafe fn stitch_left_and_right(&mut self, index: usize, start: usize, end: usize) {
-        self.remove_skipblock_from_skiplist(start, index - 1);
-        self.remove_skipblock_from_skiplist(index + 1, end);
-        self.add_skipblock_to_skiplist(start, end);
+    /// Replaces the value of the element with the given handle by applying `f` to it, returning whether it exists.
+    ///
+    /// The handle remains valid.
+    /// If `f` panics, the element is removed from the colony.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert(vec![1, 2]);
+    ///
+    /// assert!(colony.take_with(handle, |values| values.into_iter().map(|x| x * 10).collect()));
+    /// assert_eq!(colony[handle], [10, 20]);
+    /// ```
+    pub fn take_with<F: FnOnce(T) -> T>(&mut self, handle: G::Handle, f: F) -> bool
+    where
+        G: CheckedGuard,
+    {
+        // Removes the element without dropping it if f panics
+        struct Taken<'a, T, G: Guard, S: Storage> {
+            colony: &'a mut Colony<T, G, S>,
+            index: usize,
+        }
 
-        self.slot_mut(index - 1).unoccupied_mut().next = IndexOpt::some(index);
-        self.slot_mut(index + 1).unoccupied_mut().prev = IndexOpt::some(index);
+        impl<T, G: Guard, S: Storage> Drop for Taken<'_, T, G, S> {
+            fn drop(&mut self) {
+                unsafe {
+                    mem::forget(self.colony.remove_unchecked(self.index));
+                }
+            }
+        }
 
-        *self.slot_mut(index).unoccupied_mut() = Unoccupied {
-            prev: IndexOpt::some(index - 1),
-            next: IndexOpt::some(index + 1),
+        let Some(index) = self.checked_index(&handle) else {
+            return false;
         };
-    }
 
-    // Preconditions:
-    // * start and end are part of the same skipblock
-    // * start <= end
-    unsafe fn remove_skipblock_from_skiplist(&mut self, start: usize, end: usize) {
-        // Careful not to alias first and last
-        let prev = self.slot_mut(start).unoccupied().prev;
-        let next = self.slot_mut(end).unoccupied().next;
+        unsafe {
+            let ptr = self.slots.value(index);
+            let taken = Taken {
+                colony: self,
+                index,
+            };
 
-        match prev.as_opt() {
-            Some(prev) => self.slot_mut(prev).unoccupied_mut().next = next,
-            None => self.next_free = next,
+            let value = f(ptr.read());
+            mem::forget(taken);
+            ptr.write(value);
         }
 
-        if let Some(next) = next.as_opt() {
-            self.slot_mut(next).unoccupied_mut().prev = prev;
-        }
+        true
     }
 
-    // Preconditions:
-    // * start <= end
-    // * indices from start through end are all unoccupied, but not in the skiplist
-    unsafe fn add_skipblock_to_skiplist(&mut self, start: usize, end: usize) {
-        self.slot_mut(start).unoccupied_mut().prev = IndexOpt::none();
-        self.slot_mut(end).unoccupied_mut().next = self.next_free;
+    // Returns the index of the element with the given handle, if it exists
+    fn checked_index(&self, handle: &G::Handle) -> Option<usize>
+    where
+        G: CheckedGuard,
+    {
+        let index = G::__extract_index(handle);
 
-        if let Some(old_head) = self.next_free.as_opt() {
-            self.slot_mut(old_head).unoccupied_mut().prev = IndexOpt::some(end);
+        if index < self.touched && unsafe { self.guard(index).__check(handle, self.id) } {
+            Some(index)
+        } else {
+            None
         }
-
-        self.next_free = IndexOpt::some(start);
     }
 
-    /// Removes all elements from the colony.
-    ///
-    /// This is equivalent to `*self = Colony::default()`, except that the capacity remains unchanged.
-    /// This is an `O(n)` operation even if `T` doesn't implement `Drop`.
-    ///
-    /// # Panics
+    /// Returns the handle of an element given a reference to it, or `None` if the reference does not point into this colony.
     ///
-    /// When using [`GenerationGuard`], this may panic if all colony IDs have been exhausted.
-    /// See [`Colony`] for more information about the colony ID limit.
+    /// This is useful when a pointer to an element is handed back from elsewhere,
+    /// such as from a callback or an intrusive data structure.
+    /// Always returns `None` if `T` is zero-sized, since the element cannot be determined from its address.
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
-    ///
-    /// let foo = colony.insert("foo");
-    /// colony.clear();
+    /// colony.insert("foo");
     /// let bar = colony.insert("bar");
     ///
-    /// assert_eq!(colony.get(foo), None);
-    /// assert_eq!(colony.get(bar), Some(&"bar"));
+    /// let value = colony.values().find(|value| **value == "bar").unwrap();
+    /// assert_eq!(colony.handle_of(value), Some(bar));
+    /// assert_eq!(colony.handle_of(&"bar"), None);
     /// ```
-    pub fn clear(&mut self) {
-        if mem::needs_drop::<(G, T)>() {
-            for value in self.values_mut() {
-                unsafe {
-                    ptr::drop_in_place(value);
-                }
-            }
+    pub fn handle_of(&self, value: &T) -> Option<G::Handle> {
+        let stride = Slots::<T, G, S>::VALUE_STRIDE;
+
+        if mem::size_of::<T>() == 0 || self.touched == 0 {
+            return None;
         }
 
         unsafe {
-            ptr::write_bytes(self.skipfield.as_ptr(), 0, self.touched);
-        }
+            let base = self.slots.value(0) as usize;
+            let offset = (value as *const T as usize).checked_sub(base)?;
 
-        self.id = G::__new_id();
-        self.len = 0;
-        self.touched = 0;
-        self.next_free = IndexOpt::none();
+            if offset % stride != 0 || offset / stride >= self.touched {
+                return None;
+            }
+
+            let index = offset / stride;
+            let guard = self.guard(index);
+
+            // Only possible if the reference was obtained unsafely
+            if guard.__occupied() == Some(false) {
+                return None;
+            }
+
+            Some(G::__new_handle(guard, index, self.id))
+        }
     }
 
-    /// Increases the capacity of the colony to at least `self.len() + additional`.
+    /// Inserts an element into the colony at an unspecified index.
     ///
-    /// If the colony is already sufficiently large, this is a no-op.
-    /// This can be used as an optimization, or as a way to make sure [`insert`](Colony::insert) won't panic.
+    /// Some care needs to be taken with respect to aliasing of handles when not using [`GenerationGuard`].
+    /// See [`Colony`] for more information.
     ///
     /// # Panics
     ///
-    /// * If this method allocates, an allocation failure may panic.
-    /// * If the capacity would grow over `isize::MAX`.
-    /// * When using [`GenerationGuard`], this method creates a unique ID for the colony upon the first allocation.
-    ///   This method may panic if all available IDs have been exhausted.
+    /// See [`reserve`](Self::reserve).
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
-    /// let mut colony = Colony::<i32>::new();
-    /// colony.reserve(100);
-    /// assert!(colony.capacity() >= 100);
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    /// assert_eq!(colony[handle], "foo");
     /// ```
-    pub fn reserve(&mut self, additional: usize) {
-        if additional > self.capacity - self.len {
-            unsafe {
-                self.do_reserve(additional);
+    pub fn insert(&mut self, value: T) -> G::Handle {
+        let handle = unsafe {
+            if let Some(free) = self.first_free() {
+                self.insert_into_free(free, value)
+            } else {
+                self.insert_at_end(value)
             }
-        }
-    }
-
-    // Preconditions:
-    // * len + additional > capacity
-    #[cold]
-    unsafe fn do_reserve(&mut self, additional: usize) {
-        let new_cap = self.len.checked_add(additional);
-        let new_cap = new_cap.filter(|&new_cap| new_cap < MAX_CAPACITY);
-        let Some(new_cap) = new_cap else {
-            panic!("capacity overflow");
-        };
-
-        let new_id = if self.capacity == 0 {
-            Some(G::__new_id())
-        } else {
-            None
         };
 
-        let new_cap = usize::max(new_cap, self.capacity * 2);
-        let new_cap = usize::max(new_cap, Self::MIN_NON_ZERO_CAP);
+        self.debug_validate();
+        handle
+    }
 
-        self.resize(new_cap);
+    /// Inserts an element at the index and generation of a handle, which is usually from another colony.
+    ///
+    /// This can be used to mirror a colony elsewhere, such that handles to one are valid in the other.
+    /// Slots before the index are left empty if it is past the end of the colony.
+    /// If the colony has never allocated it adopts the identity of the handle's colony,
+    /// otherwise the handle must come from this colony or one whose identity it has adopted.
+    /// A colony that has allocated keeps its identity even once it is empty,
+    /// since handles to its removed elements could otherwise be confused with the other colony's.
+    /// This includes a colony that has been [cleared](Colony::clear), which has a new identity (or its old one, if registered) rather than none,
+    /// so must be mirrored with [`apply_delta`](Colony::apply_delta) or replaced by a new colony instead.
+    ///
+    /// Elements inserted with [`insert`](Colony::insert) may be given the same handles as elements in the other
+    /// colony, so handles are only unique if the colonies are kept in sync.
+    ///
+    /// If the index is within the colony, the empty slots around it are found by walking outwards to the nearer end of
+    /// its skipblock, so this is cheap when filling a skipblock from either end.
+    /// Colonies using [`NoGuard`] cannot tell an empty slot from an occupied one without searching from the start,
+    /// so for them this is an `O(n)` operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, dropping the element, if the slot is occupied, if it cannot be given the handle's
+    /// generation without reusing an older handle, if the handle is from a different colony,
+    /// or if the colony cannot grow to include the handle's index.
+    ///
+    /// # Panics
+    ///
+    /// When using [`GenerationGuard`], this method may panic if all available IDs have been exhausted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, InsertAtError};
+    /// let mut server = Colony::new();
+    /// let foo = server.insert("foo");
+    /// let bar = server.insert("bar");
+    /// server.remove(foo);
+    ///
+    /// let mut client = Colony::new();
+    /// client.insert_at(bar, "bar").unwrap();
+    /// assert_eq!(client[bar], "bar");
+    /// assert_eq!(client.get(foo), None);
+    ///
+    /// assert_eq!(client.insert_at(bar, "baz"), Err(InsertAtError::Occupied));
+    /// ```
+    pub fn insert_at(&mut self, handle: G::Handle, value: T) -> Result<(), InsertAtError> {
+        let index = G::__extract_index(&handle);
+        let state = G::__extract_state(&handle);
+        let id = G::__extract_id(&handle);
 
-        if let Some(new_id) = new_id {
-            self.id = new_id;
+        if id != self.id && self.id != G::__sentinel_id() {
+            return Err(InsertAtError::ForeignHandle);
         }
-    }
 
-    // Preconditions:
-    // * new_cap >= touched
-    unsafe fn resize(&mut self, new_cap: usize) {
-        debug_assert!(new_cap >= self.touched);
-        let old_cap = self.capacity;
+        unsafe {
+            if index >= self.touched {
+                let guard = G::__with_state(state).ok_or(InsertAtError::Unavailable)?;
 
-        let (old_layout, _) = Self::layout(old_cap).unwrap_unchecked();
-        let Ok((new_layout, skipfield_offset)) = Self::layout(new_cap) else {
-            panic!("could not layout");
-        };
+                // Handles can have any index, so fail rather than panicking or aborting if it is too large
+                if index >= self.capacity {
+                    self.try_do_reserve((index - self.touched).saturating_add(1))
+                        .map_err(|_| InsertAtError::CannotReserve)?;
+                }
 
-        let old_alloc = self.elements.as_ptr() as *mut u8;
+                self.occupy_past_end(index, guard, value);
+            } else {
+                let (start, end) = match self.guard(index).__occupied() {
+                    Some(true) => return Err(InsertAtError::Occupied),
+                    Some(false) => self.skipblock_around(index),
+                    None => self
+                        .skipblock_containing(index)
+                        .ok_or(InsertAtError::Occupied)?,
+                };
 
-        debug_assert_ne!(new_layout.size(), 0);
-        let new_alloc = alloc(new_layout);
+                if !self.guard(index).__can_fill_with(state) {
+                    return Err(InsertAtError::Unavailable);
+                }
 
-        if new_alloc.is_null() {
-            handle_alloc_error(new_layout);
+                self.occupy_within_skipblock(start, end, index, state, value);
+            }
         }
 
-        let new_elements = new_alloc as *mut Slot<T, G>;
-        let new_skipfield = new_alloc.add(skipfield_offset) as *mut SkipfieldElement;
-        self.copy_memory(new_elements, new_skipfield, new_cap);
-
-        if old_cap > 0 {
-            debug_assert_ne!(old_layout.size(), 0);
-            dealloc(old_alloc, old_layout);
+        // Reserving may have assigned a new identity
+        if id != G::__sentinel_id() {
+            self.id = id;
         }
 
-        self.elements = NonNull::new_unchecked(new_elements);
-        self.skipfield = NonNull::new_unchecked(new_skipfield);
-        self.capacity = new_cap;
+        self.debug_validate();
+        Ok(())
     }
 
-    // Preconditions:
-    // * new_elements, new_skipfield were allocated from a layout of capacity new_cap
-    // * new_cap >= touched
-    unsafe fn copy_memory(
-        &self,
-        new_elements: *mut Slot<T, G>,
-        new_skipfield: *mut SkipfieldElement,
-        new_cap: usize,
+    /// Inserts every value from an iterator, appending their handles to `handles` in the same order.
+    ///
+    /// This behaves like [`Extend::extend`], except that the handles are kept.
+    /// Empty slots are filled a whole skipblock at a time, which is cheaper than calling [`insert`](Colony::insert)
+    /// for each value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let mut handles = Vec::new();
+    /// colony.extend_with_handles(["foo", "bar", "baz"], &mut handles);
+    ///
+    /// assert_eq!(colony[handles[1]], "bar");
+    /// ```
+    pub fn extend_with_handles<I: IntoIterator<Item = T>>(
+        &mut self,
+        iter: I,
+        handles: &mut Vec<G::Handle>,
     ) {
-        debug_assert!(new_cap >= self.touched);
-        ptr::copy_nonoverlapping(self.elements.as_ptr(), new_elements, self.touched);
-        self.copy_skipfield(new_skipfield, new_cap);
+        let iter = iter.into_iter();
+        handles.reserve(iter.size_hint().0);
+        self.extend_with(iter, |handle| handles.push(handle));
     }
 
-    // Preconditions:
-    // * new_skipfield was allocated from a layout of capacity new_cap
-    // * new_cap >= touched
-    unsafe fn copy_skipfield(&self, new_skipfield: *mut SkipfieldElement, new_cap: usize) {
-        let true_old_skipfield = self.skipfield.as_ptr().sub(1);
-        let true_old_skipfield_len = self.touched + 2;
-        let true_new_skipfield = new_skipfield.sub(1);
-        let true_new_skipfield_len = new_cap + 2;
-        let remaining_skipfield = true_new_skipfield.add(true_old_skipfield_len);
-
-        ptr::copy_nonoverlapping(
-            true_old_skipfield,
-            true_new_skipfield,
-            true_old_skipfield_len,
-        );
-
-        ptr::write_bytes(
-            remaining_skipfield,
-            0,
-            true_new_skipfield_len - true_old_skipfield_len,
-        );
+    /// Inserts every value from an iterator, returning their handles in the same order.
+    ///
+    /// See [`extend_with_handles`](Colony::extend_with_handles) to reuse an existing vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handles = colony.insert_many(0..10);
+    ///
+    /// for (i, handle) in handles.into_iter().enumerate() {
+    ///     assert_eq!(colony[handle], i);
+    /// }
+    /// ```
+    pub fn insert_many<I: IntoIterator<Item = T>>(&mut self, iter: I) -> Vec<G::Handle> {
+        let mut handles = Vec::new();
+        self.extend_with_handles(iter, &mut handles);
+        handles
     }
 
-    fn layout(capacity: usize) -> Result<(Layout, usize), LayoutError> {
-        let layout = Layout::array::<Slot<T, G>>(capacity)?;
-        let (layout, _) = layout.extend(Layout::new::<SkipfieldElement>())?;
-        let (layout, skipfield_offset) =
-            layout.extend(Layout::array::<SkipfieldElement>(capacity)?)?;
-        let (layout, _) = layout.extend(Layout::new::<SkipfieldElement>())?;
+    /// Moves all elements of `other` into `self`, leaving `other` empty and returning the new handle of each element.
+    ///
+    /// Handles to `other` are invalidated as if it had been [cleared](Colony::clear),
+    /// and can be translated with the [`HandleRemap`] returned.
+    /// When `self` has no empty slots to fill, the elements are copied to the end in bulk.
+    ///
+    /// # Panics
+    ///
+    /// See [`reserve`](Self::reserve). If this panics, neither colony is modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// colony.insert("foo");
+    ///
+    /// let mut other = Colony::new();
+    /// let bar = other.insert("bar");
+    ///
+    /// let remap = colony.append(&mut other);
+    /// assert!(other.is_empty());
+    /// assert_eq!(colony.len(), 2);
+    /// assert_eq!(colony[remap.get(bar).unwrap()], "bar");
+    /// ```
+    pub fn append(&mut self, other: &mut Self) -> HandleRemap<G> {
+        let mut pairs = Vec::with_capacity(other.len);
+
+        unsafe {
+            // Nothing can panic once enough space is reserved
+            if other.len > self.capacity - self.touched {
+                self.do_reserve(other.len);
+            }
+
+            if self.first_free().is_none() {
+                for (start, run) in other.runs() {
+                    let to = self.touched;
+                    other.slots.copy_range_to(start, &self.slots, to, run.len());
+                    self.touched += run.len();
+                    self.len += run.len();
+
+                    for offset in 0..run.len() {
+                        let old =
+                            G::__new_handle(other.guard(start + offset), start + offset, other.id);
+                        let new = G::__new_handle(self.guard(to + offset), to + offset, self.id);
+                        pairs.push((old, new));
+                    }
+                }
+
+                self.debug_validate();
+            } else {
+                let mut olds = Vec::with_capacity(other.len);
+                let values = other.iter().map(|(old, value)| {
+                    olds.push(old);
+                    ptr::read(value)
+                });
+
+                let mut news = Vec::with_capacity(other.len);
+                self.extend_with(values, |new| news.push(new));
+                pairs.extend(olds.into_iter().zip(news));
+            }
+        }
 
-        Ok((layout, skipfield_offset))
+        other.forget_all();
+        other.debug_validate();
+        HandleRemap::new(pairs)
     }
 
-    /// Creates an iterator over the values in the colony and their handles.
+    /// Moves the elements with an index of at least `at` into a new colony,
+    /// returning it along with the new handle of each element moved.
     ///
-    /// If you want an iterator over only the values (and not the handles) then call [`values`](Colony::values).
+    /// The elements left in `self` keep their handles.
+    /// The new colony only allocates space for the elements moved, and uses the same [`ReusePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// See [`reserve`](Self::reserve). If this panics, `self` is not modified.
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
-    /// let foo = colony.insert("foo");
-    /// let bar = colony.insert("bar");
+    /// let handles = colony.insert_many(0..10);
     ///
-    /// let expected = [(foo, &"foo"), (bar, &"bar")].into_iter();
-    /// assert!(Iterator::eq(colony.iter(), expected));
+    /// let (tail, remap) = colony.split_off(6);
+    /// assert_eq!(colony.len(), 6);
+    /// assert_eq!(colony[handles[5]], 5);
+    /// assert_eq!(tail[remap.get(handles[6]).unwrap()], 6);
+    /// assert_eq!(colony.get(handles[6]), None);
     /// ```
-    pub fn iter(&self) -> Iter<T, G> {
-        Iter::new(self)
+    pub fn split_off(&mut self, at: usize) -> (Self, HandleRemap<G>) {
+        let mut indices = Vec::new();
+
+        for (start, run) in self.runs() {
+            let end = start + run.len();
+
+            if end > at {
+                indices.extend(usize::max(start, at)..end);
+            }
+        }
+
+        unsafe { self.move_sorted(&indices) }
     }
 
-    /// Creates an iterator over just the values of the colony.
+    /// Moves the elements for which `f` returns `true` into a new colony,
+    /// returning it along with the new handle of each element moved.
     ///
-    /// If you want to iterate over the handle for each value too, call [`iter`](Colony::iter).
+    /// The elements left in `self` keep their handles.
+    /// Elements are visited in the same order as [`iter`](Colony::iter).
+    /// The new colony only allocates space for the elements moved, and uses the same [`ReusePolicy`].
+    ///
+    /// # Panics
+    ///
+    /// See [`reserve`](Self::reserve). If this or `f` panics, `self` is not modified.
     ///
     /// # Examples
     ///
     /// ```
     /// # use colony::Colony;
     /// let mut colony = Colony::new();
-    /// colony.insert("foo");
-    /// colony.insert("bar");
+    /// let handles = colony.insert_many(0..10);
     ///
-    /// let expected = ["foo", "bar"].iter();
-    /// assert!(Iterator::eq(colony.values(), expected));
+    /// let (odd, remap) = colony.partition(|_, &x| x % 2 == 1);
+    /// assert!(colony.values().all(|&x| x % 2 == 0));
+    /// assert!(odd.values().all(|&x| x % 2 == 1));
+    /// assert_eq!(colony[handles[4]], 4);
+    /// assert_eq!(odd[remap.get(handles[3]).unwrap()], 3);
     /// ```
-    pub fn values(&self) -> Values<T, G> {
-        Values::new(self)
+    pub fn partition<F: FnMut(G::Handle, &T) -> bool>(
+        &mut self,
+        mut f: F,
+    ) -> (Self, HandleRemap<G>) {
+        let mut indices = Vec::new();
+
+        for (handle, value) in self.iter() {
+            let index = G::__extract_index(&handle);
+
+            if f(handle, value) {
+                indices.push(index);
+            }
+        }
+
+        unsafe { self.move_sorted(&indices) }
+    }
+
+    // Moves the elements at the given indices into a new colony, copying adjacent elements together
+    // Preconditions:
+    // * indices is sorted and contains no duplicates
+    // * every index is occupied
+    unsafe fn move_sorted(&mut self, indices: &[usize]) -> (Self, HandleRemap<G>) {
+        let mut moved = Self::default();
+        moved.set_reuse_policy(self.reuse);
+        moved.reserve(indices.len());
+
+        let mut pairs = Vec::with_capacity(indices.len());
+
+        for run in indices.chunk_by(|&a, &b| a + 1 == b) {
+            let to = moved.touched;
+            self.slots
+                .copy_range_to(run[0], &moved.slots, to, run.len());
+            moved.touched += run.len();
+            moved.len += run.len();
+
+            for (offset, &index) in run.iter().enumerate() {
+                let old = G::__new_handle(self.guard(index), index, self.id);
+                let new = G::__new_handle(moved.guard(to + offset), to + offset, moved.id);
+                pairs.push((old, new));
+            }
+        }
+
+        self.remove_sorted(indices, false);
+        moved.debug_validate();
+
+        (moved, HandleRemap::new(pairs))
+    }
+
+    /// Computes the changes that turn `old` into `new`, matching elements by their index and generation.
+    ///
+    /// An element with the same index and generation in both colonies is reported as changed if its value differs.
+    /// Every other element of `old` is reported as removed, and every other element of `new` as inserted.
+    /// If `new` is not derived from `old` (for example if either was [cleared](Colony::clear) in between),
+    /// every element is reported as removed and inserted.
+    /// This is an `O(n)` operation, where `n` is the number of slots used by either colony.
+    ///
+    /// The delta is intended to be applied to a replica of `old`, see [`apply_delta`](Colony::apply_delta).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut sender = Colony::new();
+    /// let mut receiver = Colony::new();
+    /// // The sender's record of what the receiver has
+    /// let mut snapshot = Colony::new();
+    ///
+    /// let foo = sender.insert("foo");
+    /// let delta = Colony::diff(&snapshot, &sender);
+    /// snapshot.apply_delta(delta.clone()).unwrap();
+    /// receiver.apply_delta(delta).unwrap();
+    /// assert_eq!(receiver[foo], "foo");
+    ///
+    /// sender[foo] = "bar";
+    /// let baz = sender.insert("baz");
+    ///
+    /// let delta = Colony::diff(&snapshot, &sender);
+    /// assert_eq!(delta.changed().collect::<Vec<_>>(), [(foo, &"bar")]);
+    /// assert_eq!(delta.inserted().collect::<Vec<_>>(), [(baz, &"baz")]);
+    ///
+    /// receiver.apply_delta(delta).unwrap();
+    /// assert_eq!(receiver, sender);
+    /// ```
+    pub fn diff(old: &Self, new: &Self) -> ColonyDelta<T, G>
+    where
+        G: CheckedGuard,
+        T: PartialEq + Clone,
+    {
+        let mut delta = ColonyDelta {
+            old_id: old.id,
+            new_id: new.id,
+            reset: old.id != new.id,
+            removed: Vec::new(),
+            changed: Vec::new(),
+            inserted: Vec::new(),
+        };
+
+        let entry = |colony: &Self, handle: &G::Handle| {
+            let index = G::__extract_index(handle);
+            let state = unsafe { colony.guard(index).__state() };
+            (index, state)
+        };
+
+        if delta.reset {
+            for (handle, _) in old {
+                let (index, state) = entry(old, &handle);
+                delta.removed.push(Entry {
+                    index,
+                    state,
+                    value: (),
+                });
+            }
+
+            for (handle, value) in new {
+                let (index, state) = entry(new, &handle);
+                let value = value.clone();
+                delta.inserted.push(Entry {
+                    index,
+                    state,
+                    value,
+                });
+            }
+
+            return delta;
+        }
+
+        let mut old_iter = old.iter().peekable();
+        let mut new_iter = new.iter().peekable();
+
+        loop {
+            let old_next = old_iter.peek().map(|(handle, _)| entry(old, handle));
+            let new_next = new_iter.peek().map(|(handle, _)| entry(new, handle));
+
+            match (old_next, new_next) {
+                (None, None) => break,
+                (Some((index, state)), new_next)
+                    if new_next.is_none_or(|(other, _)| index < other) =>
+                {
+                    old_iter.next();
+                    delta.removed.push(Entry {
+                        index,
+                        state,
+                        value: (),
+                    });
+                }
+                (old_next, Some((index, state)))
+                    if old_next.is_none_or(|(other, _)| index < other) =>
+                {
+                    let (_, value) = new_iter.next().unwrap();
+                    let value = value.clone();
+                    delta.inserted.push(Entry {
+                        index,
+                        state,
+                        value,
+                    });
+                }
+                (Some((index, old_state)), Some((_, state))) => {
+                    let (_, old_value) = old_iter.next().unwrap();
+                    let (_, value) = new_iter.next().unwrap();
+
+                    if old_state != state {
+                        delta.removed.push(Entry {
+                            index,
+                            state: old_state,
+                            value: (),
+                        });
+                        delta.inserted.push(Entry {
+                            index,
+                            state,
+                            value: value.clone(),
+                        });
+                    } else if old_value != value {
+                        delta.changed.push(Entry {
+                            index,
+                            state,
+                            value: value.clone(),
+                        });
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        delta
+    }
+
+    /// Applies the changes computed by [`diff`](Colony::diff), turning a replica of the older colony into a replica of the newer one.
+    ///
+    /// Elements are placed at the same index and with the same generation as in the newer colony,
+    /// and the colony takes on the identity of the newer colony,
+    /// so handles to elements of the newer colony are valid for this colony and vice versa.
+    /// Any handles created by this colony under a different identity are no longer valid,
+    /// and [`id`](Colony::id) returns the newer colony's identity from then on.
+    /// If the newer colony is not derived from the older one, this colony is cleared before inserting its elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error without modifying the colony if the delta does not match,
+    /// meaning the colony was not a replica of the older colony.
+    /// Unless the delta replaces every element, this includes a colony with a different identity to the older colony.
+    /// A [registered](crate::ColonyRegistry::register) colony keeps its identity, so also rejects deltas which replace every element.
+    ///
+    /// # Panics
+    ///
+    /// See [`reserve`](Self::reserve).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{ApplyDeltaError, Colony};
+    /// let mut sender = Colony::new();
+    /// let mut receiver = Colony::new();
+    ///
+    /// let foo = sender.insert("foo");
+    /// let snapshot = Colony::diff(&Colony::new(), &sender);
+    /// receiver.apply_delta(snapshot).unwrap();
+    /// assert_eq!(receiver[foo], "foo");
+    ///
+    /// // The receiver no longer has the element the sender changes
+    /// let mut changed = Colony::new();
+    /// changed.apply_delta(Colony::diff(&Colony::new(), &sender)).unwrap();
+    /// changed[foo] = "bar";
+    /// receiver.remove(foo);
+    ///
+    /// let result = receiver.apply_delta(Colony::diff(&sender, &changed));
+    /// assert_eq!(result, Err(ApplyDeltaError::Missing { index: 0 }));
+    /// ```
+    pub fn apply_delta(&mut self, delta: ColonyDelta<T, G>) -> Result<(), ApplyDeltaError>
+    where
+        G: CheckedGuard,
+    {
+        self.check_delta(&delta)?;
+
+        // Filling arbitrary slots is cheaper without maintaining the heap, which is rebuilt afterwards
+        let policy = self.reuse;
+        if policy == ReusePolicy::LowestFirst {
+            self.set_reuse_policy(ReusePolicy::Lifo);
+        }
+
+        unsafe {
+            if delta.reset {
+                self.clear();
+            } else {
+                let removed: Vec<_> = delta.removed.iter().map(|entry| entry.index).collect();
+                self.remove_sorted(&removed, true);
+            }
+
+            for entry in delta.changed {
+                *self.occupied_mut(entry.index) = entry.value;
+            }
+
+            for entry in delta.inserted {
+                if entry.index >= self.touched {
+                    let guard = G::__with_state(entry.state).unwrap_unchecked();
+                    self.occupy_past_end(entry.index, guard, entry.value);
+                } else {
+                    let (start, end) = self.skipblock_around(entry.index);
+                    self.occupy_within_skipblock(start, end, entry.index, entry.state, entry.value);
+                }
+            }
+        }
+
+        if delta.new_id != G::__sentinel_id() {
+            self.id = delta.new_id;
+        }
+
+        self.set_reuse_policy(policy);
+        self.debug_validate();
+        Ok(())
+    }
+
+    fn check_delta(&self, delta: &ColonyDelta<T, G>) -> Result<(), ApplyDeltaError> {
+        // A reset replaces every element, so only needs the slots it inserts into to be available
+        if !delta.reset && self.id != delta.old_id {
+            return Err(ApplyDeltaError::ForeignDelta);
+        }
+
+        // Replacing every element would give a pinned colony the newer colony's identity
+        if delta.reset && self.pinned {
+            return Err(ApplyDeltaError::ForeignDelta);
+        }
+
+        let occupied_with = |index: usize, state: u32| unsafe {
+            index < self.touched
+                && self.guard(index).__occupied() == Some(true)
+                && self.guard(index).__state() == state
+        };
+
+        for entry in delta.removed.iter() {
+            if !occupied_with(entry.index, entry.state) {
+                return Err(ApplyDeltaError::Missing { index: entry.index });
+            }
+        }
+
+        let removed = |index: usize| {
+            delta
+                .removed
+                .binary_search_by_key(&index, |entry| entry.index)
+                .is_ok()
+        };
+
+        for entry in delta.changed.iter() {
+            if !occupied_with(entry.index, entry.state) || removed(entry.index) {
+                return Err(ApplyDeltaError::Missing { index: entry.index });
+            }
+        }
+
+        for entry in delta.inserted.iter() {
+            let index = entry.index;
+
+            let available = if index >= self.touched || delta.reset {
+                G::__with_state(entry.state).is_some()
+            } else {
+                let guard = unsafe { self.guard(index) };
+                let empty = guard.__occupied() == Some(false) || removed(index);
+                empty && guard.__can_fill_with(entry.state)
+            };
+
+            if !available {
+                return Err(ApplyDeltaError::Unavailable { index });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn extend_with<I: Iterator<Item = T>>(
+        &mut self,
+        mut iter: I,
+        mut on_insert: impl FnMut(G::Handle),
+    ) {
+        unsafe {
+            while let Some(free) = self.first_free() {
+                if !self.insert_into_skipblock(free, &mut iter, &mut on_insert) {
+                    self.debug_validate();
+                    return;
+                }
+            }
+
+            while let Some(value) = iter.next() {
+                if self.touched == self.capacity {
+                    let (lower, _) = iter.size_hint();
+                    self.do_reserve(lower.saturating_add(1));
+                }
+
+                on_insert(self.insert_at_end_unchecked(value));
+            }
+        }
+
+        self.debug_validate();
+    }
+
+    // Fills the skipblock starting at free with values from iter, updating the skipfield and freelist only once
+    // Returns false if iter ran out of values
+    // Preconditions:
+    // * free was returned by first_free
+    unsafe fn insert_into_skipblock(
+        &mut self,
+        free: usize,
+        iter: &mut impl Iterator<Item = T>,
+        on_insert: &mut impl FnMut(G::Handle),
+    ) -> bool {
+        // Finishes filling even if the iterator or callback panic
+        struct Filling<'a, T, G: Guard, S: Storage> {
+            colony: &'a mut Colony<T, G, S>,
+            free: usize,
+            filled: usize,
+            prev: IndexOpt,
+            next: IndexOpt,
+        }
+
+        impl<T, G: Guard, S: Storage> Drop for Filling<'_, T, G, S> {
+            fn drop(&mut self) {
+                if self.filled == 0 {
+                    return;
+                }
+
+                unsafe {
+                    let colony = &mut *self.colony;
+                    colony.skipfield().unskip_leading(self.free, self.filled);
+
+                    match self.prev.as_opt() {
+                        Some(prev) => colony.unoccupied_mut(prev).next = self.next,
+                        None => colony.next_free = self.next,
+                    }
+
+                    if let Some(next) = self.next.as_opt() {
+                        colony.unoccupied_mut(next).prev = self.prev;
+                    }
+
+                    colony.len += self.filled;
+                }
+            }
+        }
+
+        let (start, end) = self.free_skipblock(free);
+
+        if start != free {
+            let Some(value) = iter.next() else {
+                return false;
+            };
+
+            self.unskip_free(start, end, free);
+            self.len += 1;
+            self.slots.fill(free, value);
+            on_insert(G::__new_handle(self.guard(free), free, self.id));
+            return true;
+        }
+
+        let size = end - start + 1;
+
+        let mut filling = Filling {
+            prev: self.unoccupied(free).prev,
+            next: IndexOpt::none(),
+            colony: self,
+            free,
+            filled: 0,
+        };
+
+        while filling.filled < size {
+            let Some(value) = iter.next() else {
+                return false;
+            };
+
+            let colony = &mut *filling.colony;
+            let index = free + filling.filled;
+
+            // The links are overwritten by the value
+            filling.next = colony.unoccupied(index).next;
+
+            colony.take_free(index);
+            colony.slots.fill(index, value);
+            filling.filled += 1;
+
+            on_insert(G::__new_handle(colony.guard(index), index, colony.id));
+
+            // The rest of the skipblock is not in the freelist if its next slot was retired
+            if filling.next.as_opt() != Some(index + 1) {
+                break;
+            }
+        }
+
+        true
+    }
+
+    // Preconditions:
+    // * free was returned by first_free
+    // * len < touched
+    unsafe fn insert_into_free(&mut self, free: usize, value: T) -> G::Handle {
+        debug_assert!(self.len < self.touched);
+
+        let (start, end) = self.free_skipblock(free);
+
+        if start == free {
+            self.skipfield().unskip_leftmost(free);
+            self.remove_skipblock_from_skiplist(free, free);
+            self.take_free(free);
+        } else {
+            self.unskip_free(start, end, free);
+        }
+
+        self.len += 1;
+
+        self.slots.fill(free, value);
+        G::__new_handle(self.guard(free), free, self.id)
+    }
+
+    // Preconditions:
+    // * the freelist is empty
+    unsafe fn insert_at_end(&mut self, value: T) -> G::Handle {
+        // Retired slots mean touched may exceed len
+        if self.touched == self.capacity {
+            self.do_reserve(1);
+        }
+
+        self.insert_at_end_unchecked(value)
+    }
+
+    // Preconditions:
+    // * the freelist is empty
+    // * touched < capacity
+    unsafe fn insert_at_end_unchecked(&mut self, value: T) -> G::Handle {
+        debug_assert!(self.next_free.as_opt().is_none());
+        debug_assert!(self.touched < self.capacity);
+
+        self.slots.write_full(self.touched, value);
+
+        self.touched += 1;
+        self.len += 1;
+
+        G::__new_handle(self.guard(self.touched - 1), self.touched - 1, self.id)
     }
 
-    /// Creates an iterator over the values in the colony and their handles, by mutable reference.
-    ///
-    /// See [`iter`](Colony::iter).
-    pub fn iter_mut(&mut self) -> IterMut<T, G> {
-        IterMut::new(self)
-    }
+    // Places an element at an index past the end, leaving any slots before it empty
+    // Preconditions:
+    // * index >= touched
+    unsafe fn occupy_past_end(&mut self, index: usize, guard: G, value: T) {
+        debug_assert!(index >= self.touched);
+
+        if index >= self.capacity {
+            self.do_reserve(index + 1 - self.touched);
+        }
+
+        let first = self.touched;
+        self.touched = index + 1;
+
+        for gap in first..index {
+            self.slots.guard(gap).write(G::__new_unused());
+        }
+
+        self.slots.guard(index).write(guard);
+        self.slots.value(index).write(value);
+        self.len += 1;
+
+        if first < index {
+            self.skip_emptied(first, index - 1, true);
+        }
+    }
+
+    // Fills an empty slot anywhere within a skipblock, giving its guard the given state
+    // Preconditions:
+    // * start and end are the head and tail of a skipblock, and start <= index <= end
+    // * the guard at index can be filled with state
+    unsafe fn occupy_within_skipblock(
+        &mut self,
+        start: usize,
+        end: usize,
+        index: usize,
+        state: u32,
+        value: T,
+    ) {
+        // A slot which can be filled has not been retired, so is in the freelist
+        self.unskip_free(start, end, index);
+
+        (*self.slots.guard(index)).__fill_with(state);
+        self.slots.value(index).write(value);
+        self.len += 1;
+    }
+
+    // Unskips a slot in the freelist anywhere within a skipblock, and removes it from the freelist
+    // Preconditions:
+    // * start and end are the head and tail of a skipblock, and start <= index <= end
+    // * the slot at index is in the freelist
+    unsafe fn unskip_free(&mut self, start: usize, end: usize, index: usize) {
+        self.skipfield().unskip_within(start, end, index);
+
+        let links = *self.unoccupied(index);
+
+        match links.prev.as_opt() {
+            Some(prev) => self.unoccupied_mut(prev).next = links.next,
+            None => self.next_free = links.next,
+        }
+
+        if let Some(next) = links.next.as_opt() {
+            self.unoccupied_mut(next).prev = links.prev;
+        }
+
+        self.take_free(index);
+    }
+
+    // Returns the skipblock containing a slot returned by first_free
+    // Preconditions:
+    // * free was returned by first_free, or is the head of a run in the freelist
+    unsafe fn free_skipblock(&self, free: usize) -> (usize, usize) {
+        // Retired slots stay skipped, so a slot in the freelist may follow them in its skipblock
+        if free > 0 && self.retired.contains(&(free - 1)) {
+            return self.skipblock_around(free);
+        }
+
+        let size = self.skipfield().read::<RIGHT>(free as isize);
+        (free, free + size - 1)
+    }
+
+    // Returns the skipblock containing the empty slot at index
+    // Walks outwards from index until either end is found, so this is O(1) if index is at either end
+    // Preconditions:
+    // * index < touched
+    // * the slot at index is empty
+    unsafe fn skipblock_around(&self, index: usize) -> (usize, usize) {
+        let mut left = index;
+        let mut right = index;
+
+        loop {
+            if left == 0 || !self.empty_beside(left, left - 1) {
+                let size = self.skipfield().read::<RIGHT>(left as isize);
+                return (left, left + size - 1);
+            }
+
+            if right + 1 == self.touched || !self.empty_beside(right, right + 1) {
+                let size = self.skipfield().read::<LEFT>(right as isize);
+                return (right + 1 - size, right);
+            }
+
+            left -= 1;
+            right += 1;
+        }
+    }
+
+    // Returns whether the slot beside an empty slot is also empty
+    // Preconditions:
+    // * the slot at empty is empty
+    // * beside is either side of empty, and less than touched
+    unsafe fn empty_beside(&self, empty: usize, beside: usize) -> bool {
+        match self.guard(beside).__occupied() {
+            Some(occupied) => !occupied,
+            // Only guards tracking occupancy retire slots, so otherwise adjacent empty slots are linked in the freelist
+            None => {
+                let links = self.unoccupied(empty);
+                links.prev.as_opt() == Some(beside) || links.next.as_opt() == Some(beside)
+            }
+        }
+    }
+
+    // Returns the skipblock containing the slot at index, or None if it is occupied
+    // Searches from the start, so is only needed by guards that cannot tell whether the slot is occupied
+    // Preconditions:
+    // * index < touched
+    unsafe fn skipblock_containing(&self, index: usize) -> Option<(usize, usize)> {
+        let mut from = 0;
+
+        loop {
+            let size = self.skipfield().read::<RIGHT>(from as isize);
+
+            if size == 0 {
+                from += self.skipfield().unskipped_run(from, index + 1 - from);
+
+                if from > index {
+                    return None;
+                }
+            } else {
+                let end = from + size - 1;
+
+                if index <= end {
+                    return Some((from, end));
+                }
+
+                from = end + 1;
+            }
+        }
+    }
+
+    /// Removes the element with the given handle, if it exists.
+    ///
+    /// Some care needs to be taken with respect to aliasing of handle when not using [`GenerationGuard`].
+    /// See [`Colony`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    /// assert_eq!(colony.remove(handle), Some("foo"));
+    /// assert_eq!(colony.remove(handle), None);
+    /// ```
+    pub fn remove(&mut self, handle: G::Handle) -> Option<T>
+    where
+        G: CheckedGuard,
+    {
+        let index = G::__extract_index(&handle);
+
+        if index >= self.touched {
+            return None;
+        }
+
+        unsafe {
+            if !self.guard(index).__check(&handle, self.id) {
+                return None;
+            }
+
+            Some(self.remove_unchecked(index))
+        }
+    }
+
+    /// Removes the element with the given index, assuming it exists.
+    ///
+    /// This is mostly useful with [`UnguardedColony`] where [`remove`](Colony::remove) cannot be used.
+    ///
+    /// # Safety
+    ///
+    /// An element must exist with the index provided.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    ///
+    /// unsafe {
+    ///     let result = colony.remove_unchecked(handle.index);
+    ///     assert_eq!(result, "foo");
+    /// }
+    /// ```
+    pub unsafe fn remove_unchecked(&mut self, index: usize) -> T {
+        unsafe {
+            let (result, reuse) = self.slots.empty(index);
+            self.skip_emptied(index, index, reuse);
+
+            self.len -= 1;
+            self.debug_validate();
+            result
+        }
+    }
+
+    /// Removes the elements with the given handles, returning the handles that were stale.
+    ///
+    /// This is equivalent to calling [`remove`](Colony::remove) for each handle and dropping the results,
+    /// but adjacent elements are removed together, which is cheaper when removing many elements at once.
+    /// A handle is stale if [`remove`](Colony::remove) would have returned `None` for it,
+    /// including when it refers to the same element as an earlier handle.
+    /// Stale handles are returned in no particular order.
+    ///
+    /// This sorts the handles, so it is an `O(k log k)` operation where `k` is the number of handles,
+    /// and allocates temporary buffers of that size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let handles = colony.insert_many(0..10);
+    /// colony.remove(handles[3]);
+    ///
+    /// let stale = colony.remove_many(handles[..5].iter().copied());
+    /// assert_eq!(stale, [handles[3]]);
+    ///
+    /// assert!(colony.values().copied().eq(5..10));
+    /// ```
+    pub fn remove_many<I: IntoIterator<Item = G::Handle>>(&mut self, handles: I) -> Vec<G::Handle>
+    where
+        G: CheckedGuard,
+    {
+        let mut stale = Vec::new();
+        let mut valid = Vec::new();
+
+        for handle in handles {
+            let index = G::__extract_index(&handle);
+
+            if index < self.touched && unsafe { self.guard(index).__check(&handle, self.id) } {
+                valid.push((index, handle));
+            } else {
+                stale.push(handle);
+            }
+        }
+
+        valid.sort_unstable_by_key(|&(index, _)| index);
+
+        let mut indices = Vec::with_capacity(valid.len());
+
+        for (index, handle) in valid {
+            if indices.last() == Some(&index) {
+                stale.push(handle);
+            } else {
+                indices.push(index);
+            }
+        }
+
+        unsafe {
+            self.remove_sorted(&indices, true);
+        }
+
+        stale
+    }
+
+    /// Removes the elements with the given indices, assuming they exist.
+    ///
+    /// This is equivalent to calling [`remove_unchecked`](Colony::remove_unchecked) for each index and dropping the results,
+    /// but adjacent elements are removed together, which is cheaper when removing many elements at once.
+    ///
+    /// This sorts the indices, so it is an `O(k log k)` operation where `k` is the number of indices,
+    /// and allocates a temporary buffer of that size.
+    ///
+    /// # Safety
+    ///
+    /// An element must exist with each index provided, and the indices must be distinct.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::unguarded();
+    /// colony.extend(0..10);
+    ///
+    /// unsafe {
+    ///     colony.remove_many_unchecked([7, 2, 3, 8]);
+    /// }
+    ///
+    /// assert!(colony.values().copied().eq([0, 1, 4, 5, 6, 9]));
+    /// ```
+    pub unsafe fn remove_many_unchecked<I: IntoIterator<Item = usize>>(&mut self, indices: I) {
+        let mut indices: Vec<_> = indices.into_iter().collect();
+        indices.sort_unstable();
+
+        unsafe {
+            self.remove_sorted(&indices, true);
+        }
+    }
+
+    /// Removes the elements with the handles queued in `queue`, leaving it empty and returning the number of elements removed.
+    ///
+    /// Handles that were queued more than once are ignored, as are invalid handles with [`GenerationGuard`].
+    /// With [`FlagGuard`], a handle to an element that was removed after being queued is only ignored if its slot has not been filled again,
+    /// otherwise the element now in the slot is removed instead.
+    /// Adjacent elements are removed together, as with [`remove_many`](Colony::remove_many).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, RemovalQueue};
+    /// let mut colony = Colony::new();
+    /// let handle = colony.insert("foo");
+    ///
+    /// let queue = RemovalQueue::new();
+    /// queue.defer_remove(handle);
+    /// queue.defer_remove(handle);
+    ///
+    /// assert_eq!(colony.flush_removals(&queue), 1);
+    /// assert!(colony.is_empty() && queue.is_empty());
+    /// ```
+    pub fn flush_removals(&mut self, queue: &RemovalQueue<G>) -> usize
+    where
+        G: CheckedGuard,
+    {
+        let len = self.len;
+        self.remove_many(queue.take());
+        len - self.len
+    }
+
+    // Removes the elements at the given indices, forgetting them instead of dropping them if drop_values is false
+    // Preconditions:
+    // * indices is sorted and contains no duplicates
+    // * every index is occupied
+    // * if drop_values is false, the elements have been moved elsewhere
+    unsafe fn remove_sorted(&mut self, indices: &[usize], drop_values: bool) {
+        let mut values = Vec::new();
+        let mut reuses = Vec::new();
+
+        for run in indices.chunk_by(|&a, &b| a + 1 == b) {
+            let first = run[0];
+            let last = run[run.len() - 1];
+
+            for &index in run {
+                let (value, reuse) = self.slots.empty(index);
+                reuses.push(reuse);
+
+                if drop_values {
+                    values.push(value);
+                } else {
+                    mem::forget(value);
+                }
+            }
+
+            if reuses.iter().all(|&reuse| reuse) {
+                self.skip_emptied(first, last, true);
+            } else {
+                for (&index, &reuse) in run.iter().zip(&reuses) {
+                    self.skip_emptied(index, index, reuse);
+                }
+            }
+
+            self.len -= run.len();
+            reuses.clear();
+
+            // The colony is consistent again, so dropping can safely panic
+            values.clear();
+        }
+
+        self.debug_validate();
+    }
+
+    // Skips the slots from first through last, and adds them to the freelist if they can be reused
+    // Preconditions:
+    // * first <= last
+    // * the slots from first through last were just emptied
+    // * first == last or reuse is true
+    unsafe fn skip_emptied(&mut self, first: usize, last: usize, reuse: bool) {
+        let (start, end) = self.skipfield().skip(first, last);
+
+        if !reuse {
+            self.retired.insert(first);
+            return;
+        }
+
+        // The stitch functions only link first and last to the rest of the freelist
+        for index in first..last {
+            self.unoccupied_mut(index).next = IndexOpt::some(index + 1);
+            self.unoccupied_mut(index + 1).prev = IndexOpt::some(index);
+        }
+
+        // Only the reusable slots either side are joined in the freelist, up to any retired slot
+        let start = self
+            .retired
+            .range(start..first)
+            .next_back()
+            .map_or(start, |&index| index + 1);
+        let end = self
+            .retired
+            .range(last + 1..end + 1)
+            .next()
+            .map_or(end, |&index| index - 1);
+
+        let has_left = start != first;
+        let has_right = end != last;
+
+        if !has_left && !has_right {
+            self.stitch_no_left_no_right(first, last);
+        } else if has_left && !has_right {
+            self.stitch_only_left(first, last);
+        } else if !has_left && has_right {
+            self.stitch_only_right(first, last);
+        } else {
+            self.stitch_left_and_right(first, last, start, end);
+        }
+
+        if self.reuse == ReusePolicy::LowestFirst {
+            self.lowest_free.extend((first..=last).map(Reverse));
+        }
+    }
+
+    unsafe fn stitch_no_left_no_right(&mut self, first: usize, last: usize) {
+        match self.reuse {
+            ReusePolicy::Lifo | ReusePolicy::LowestFirst => {
+                self.add_skipblock_to_skiplist(first, last)
+            }
+            ReusePolicy::FillCurrent => match self.next_free.as_opt() {
+                Some(head) => {
+                    let (_, end) = self.free_skipblock(head);
+                    let tail = self
+                        .retired
+                        .range(head..end + 1)
+                        .next()
+                        .map_or(end, |&index| index - 1);
+                    self.add_skipblock_to_skiplist_after(tail, first, last);
+                }
+                None => self.add_skipblock_to_skiplist(first, last),
+            },
+        }
+    }
+
+    unsafe fn stitch_only_left(&mut self, first: usize, last: usize) {
+        let next = mem::replace(
+            &mut self.unoccupied_mut(first - 1).next,
+            IndexOpt::some(first),
+        );
+
+        if let Some(next) = next.as_opt() {
+            self.unoccupied_mut(next).prev = IndexOpt::some(last);
+        }
+
+        self.unoccupied_mut(first).prev = IndexOpt::some(first - 1);
+        self.unoccupied_mut(last).next = next;
+    }
+
+    unsafe fn stitch_only_right(&mut self, first: usize, last: usize) {
+        let prev = mem::replace(
+            &mut self.unoccupied_mut(last + 1).prev,
+            IndexOpt::some(last),
+        );
+
+        match prev.as_opt() {
+            Some(prev) => self.unoccupied_mut(prev).next = IndexOpt::some(first),
+            None => self.next_free = IndexOpt::some(first),
+        }
+
+        self.unoccupied_mut(first).prev = prev;
+        self.unoccupied_mut(last).next = IndexOpt::some(last + 1);
+    }
+
+    unsafe fn stitch_left_and_right(
+        &mut self,
+        first: usize,
+        last: usize,
+        start: usize,
+        end: usize,
+    ) {
+        // Only the skipblock currently being filled stays first, otherwise the joined skipblock goes first
+        if self.reuse != ReusePolicy::FillCurrent {
+            self.remove_skipblock_from_skiplist(start, first - 1);
+            self.remove_skipblock_from_skiplist(last + 1, end);
+            self.add_skipblock_to_skiplist(start, end);
+        } else {
+            self.remove_skipblock_from_skiplist(last + 1, end);
+            let prev = self.unoccupied(start).prev;
+            self.remove_skipblock_from_skiplist(start, first - 1);
+            self.add_skipblock_to_skiplist_at(start, end, prev);
+        }
+
+        self.unoccupied_mut(first - 1).next = IndexOpt::some(first);
+        self.unoccupied_mut(last + 1).prev = IndexOpt::some(last);
+
+        self.unoccupied_mut(first).prev = IndexOpt::some(first - 1);
+        self.unoccupied_mut(last).next = IndexOpt::some(last + 1);
+    }
+
+    // Preconditions:
+    // * start and end are part of the same skipblock
+    // * start <= end
+    unsafe fn remove_skipblock_from_skiplist(&mut self, start: usize, end: usize) {
+        // Careful not to alias first and last
+        let prev = self.unoccupied(start).prev;
+        let next = self.unoccupied(end).next;
+
+        match prev.as_opt() {
+            Some(prev) => self.unoccupied_mut(prev).next = next,
+            None => self.next_free = next,
+        }
+
+        if let Some(next) = next.as_opt() {
+            self.unoccupied_mut(next).prev = prev;
+        }
+    }
+
+    // Preconditions:
+    // * start <= end
+    // * indices from start through end are all unoccupied, but not in the skiplist
+    unsafe fn add_skipblock_to_skiplist(&mut self, start: usize, end: usize) {
+        self.add_skipblock_to_skiplist_at(start, end, IndexOpt::none());
+    }
+
+    // Preconditions:
+    // * start <= end
+    // * indices from start through end are all unoccupied, but not in the skiplist
+    // * tail is unoccupied and in the skiplist
+    unsafe fn add_skipblock_to_skiplist_after(&mut self, tail: usize, start: usize, end: usize) {
+        self.add_skipblock_to_skiplist_at(start, end, IndexOpt::some(tail));
+    }
+
+    // Preconditions:
+    // * start <= end
+    // * indices from start through end are all unoccupied, but not in the skiplist
+    // * prev is either none or unoccupied and in the skiplist
+    unsafe fn add_skipblock_to_skiplist_at(&mut self, start: usize, end: usize, prev: IndexOpt) {
+        let next = match prev.as_opt() {
+            Some(prev) => mem::replace(&mut self.unoccupied_mut(prev).next, IndexOpt::some(start)),
+            None => mem::replace(&mut self.next_free, IndexOpt::some(start)),
+        };
+
+        self.unoccupied_mut(start).prev = prev;
+        self.unoccupied_mut(end).next = next;
+
+        if let Some(next) = next.as_opt() {
+            self.unoccupied_mut(next).prev = IndexOpt::some(end);
+        }
+    }
+
+    /// Removes all elements from the colony.
+    ///
+    /// This is equivalent to `*self = Colony::default()`, except that the capacity remains unchanged.
+    /// This is an `O(n)` operation even if `T` doesn't implement `Drop`.
+    ///
+    /// The colony is given a new identity, so that handles to the removed elements can never refer to new elements.
+    /// A [registered](crate::ColonyRegistry::register) colony instead keeps its identity,
+    /// and empties its slots as [`remove`](Colony::remove) would, so that their generations move on.
+    ///
+    /// # Panics
+    ///
+    /// When using [`GenerationGuard`], this may panic if all colony IDs have been exhausted.
+    /// See [`Colony`] for more information about the colony ID limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    ///
+    /// let foo = colony.insert("foo");
+    /// colony.clear();
+    /// let bar = colony.insert("bar");
+    ///
+    /// assert_eq!(colony.get(foo), None);
+    /// assert_eq!(colony.get(bar), Some(&"bar"));
+    /// ```
+    pub fn clear(&mut self) {
+        if mem::needs_drop::<(G, T)>() {
+            for value in self.values_mut() {
+                unsafe {
+                    ptr::drop_in_place(value);
+                }
+            }
+        }
+
+        self.forget_all();
+        self.debug_validate();
+    }
+
+    // Empties the colony without dropping its elements
+    fn forget_all(&mut self) {
+        if self.pinned {
+            let occupied: Vec<_> = self
+                .runs()
+                .flat_map(|(start, run)| start..start + run.len())
+                .collect();
+
+            unsafe {
+                self.remove_sorted(&occupied, false);
+            }
+
+            return;
+        }
+
+        unsafe {
+            ptr::write_bytes(self.skipfield.as_ptr(), 0, self.touched);
+        }
+
+        self.id = G::__new_id();
+        self.len = 0;
+        self.touched = 0;
+        self.next_free = IndexOpt::none();
+        self.lowest_free.clear();
+        self.lowest_taken.clear();
+        self.retired.clear();
+    }
+
+    /// Increases the capacity of the colony to at least `self.len() + additional`.
+    ///
+    /// If the colony is already sufficiently large, this is a no-op.
+    /// This can be used as an optimization, or as a way to make sure [`insert`](Colony::insert) won't panic.
+    ///
+    /// # Panics
+    ///
+    /// * If this method allocates, an allocation failure may panic.
+    /// * If the capacity would grow over `isize::MAX`.
+    /// * When using [`GenerationGuard`], this method creates a unique ID for the colony upon the first allocation.
+    ///   This method may panic if all available IDs have been exhausted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::<i32>::new();
+    /// colony.reserve(100);
+    /// assert!(colony.capacity() >= 100);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        if additional > self.capacity - self.len {
+            unsafe {
+                self.do_reserve(additional);
+            }
+        }
+
+        self.debug_validate();
+    }
+
+    // Preconditions:
+    // * touched + additional > capacity
+    #[cold]
+    unsafe fn do_reserve(&mut self, additional: usize) {
+        match self.try_do_reserve(additional) {
+            Ok(()) => {}
+            Err(ReserveError::CapacityOverflow) => panic!("capacity overflow"),
+            Err(ReserveError::Layout) => panic!("could not layout"),
+            Err(ReserveError::Alloc(layout)) => handle_alloc_error(layout),
+        }
+    }
+
+    // Like do_reserve, but returns an error rather than panicking or aborting, leaving the colony unchanged
+    // Preconditions:
+    // * touched + additional > capacity
+    #[cold]
+    unsafe fn try_do_reserve(&mut self, additional: usize) -> Result<(), ReserveError> {
+        let new_cap = self.touched.checked_add(additional);
+        let new_cap = new_cap.filter(|&new_cap| new_cap < MAX_CAPACITY);
+        let new_cap = new_cap.ok_or(ReserveError::CapacityOverflow)?;
+
+        // A colony may have been given an identity before allocating, see ColonyRegistry::register
+        let new_id = if self.capacity == 0 && self.id == G::__sentinel_id() {
+            Some(G::__new_id())
+        } else {
+            None
+        };
+
+        let new_cap = usize::max(new_cap, self.capacity * 2);
+        let new_cap = usize::max(new_cap, Self::MIN_NON_ZERO_CAP);
+
+        self.resize(new_cap)?;
+
+        if let Some(new_id) = new_id {
+            self.id = new_id;
+        }
+
+        Ok(())
+    }
+
+    // Preconditions:
+    // * new_cap >= touched
+    unsafe fn resize(&mut self, new_cap: usize) -> Result<(), ReserveError> {
+        debug_assert!(new_cap >= self.touched);
+        let old_cap = self.capacity;
+
+        let old_layout = Self::layout(old_cap).unwrap_unchecked();
+        let new_layout = Self::layout(new_cap).map_err(|_| ReserveError::Layout)?;
+
+        debug_assert_ne!(new_layout.layout.size(), 0);
+        let new_alloc = alloc(new_layout.layout);
+        let new_alloc = NonNull::new(new_alloc).ok_or(ReserveError::Alloc(new_layout.layout))?;
+
+        let new_slots = Slots::from_alloc(new_alloc, &new_layout);
+        let new_skipfield = new_alloc.as_ptr().add(new_layout.skipfield_offset);
+        self.copy_memory(new_slots, new_skipfield, new_cap);
+
+        if old_cap > 0 {
+            debug_assert_ne!(old_layout.layout.size(), 0);
+            dealloc(self.slots.alloc(&old_layout), old_layout.layout);
+        }
+
+        self.slots = new_slots;
+        self.skipfield = NonNull::new_unchecked(new_skipfield);
+        self.capacity = new_cap;
+        Ok(())
+    }
+
+    // Preconditions:
+    // * new_slots, new_skipfield were allocated from a layout of capacity new_cap
+    // * new_cap >= touched
+    unsafe fn copy_memory(
+        &self,
+        new_slots: Slots<T, G, S>,
+        new_skipfield: *mut SkipfieldElement,
+        new_cap: usize,
+    ) {
+        debug_assert!(new_cap >= self.touched);
+        self.slots.copy_to(&new_slots, self.touched);
+        self.copy_skipfield(new_skipfield, new_cap);
+    }
+
+    // Preconditions:
+    // * new_skipfield was allocated from a layout of capacity new_cap
+    // * new_cap >= touched
+    unsafe fn copy_skipfield(&self, new_skipfield: *mut SkipfieldElement, new_cap: usize) {
+        let true_old_skipfield = self.skipfield.as_ptr().sub(1);
+        let true_old_skipfield_len = self.touched + 2;
+        let true_new_skipfield = new_skipfield.sub(1);
+        let true_new_skipfield_len = new_cap + 2;
+        let remaining_skipfield = true_new_skipfield.add(true_old_skipfield_len);
+
+        ptr::copy_nonoverlapping(
+            true_old_skipfield,
+            true_new_skipfield,
+            true_old_skipfield_len,
+        );
+
+        ptr::write_bytes(
+            remaining_skipfield,
+            0,
+            true_new_skipfield_len - true_old_skipfield_len,
+        );
+    }
+
+    fn layout(capacity: usize) -> Result<SlotsLayout, LayoutError> {
+        Slots::<T, G, S>::layout(capacity)
+    }
+
+    /// Creates an iterator over the values in the colony and their handles.
+    ///
+    /// If you want an iterator over only the values (and not the handles) then call [`values`](Colony::values).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let foo = colony.insert("foo");
+    /// let bar = colony.insert("bar");
+    ///
+    /// let expected = [(foo, &"foo"), (bar, &"bar")].into_iter();
+    /// assert!(Iterator::eq(colony.iter(), expected));
+    /// ```
+    pub fn iter(&self) -> Iter<T, G, S> {
+        Iter::new(self)
+    }
+
+    /// Creates an iterator over just the values of the colony.
+    ///
+    /// If you want to iterate over the handle for each value too, call [`iter`](Colony::iter).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// colony.insert("foo");
+    /// colony.insert("bar");
+    ///
+    /// let expected = ["foo", "bar"].iter();
+    /// assert!(Iterator::eq(colony.values(), expected));
+    /// ```
+    pub fn values(&self) -> Values<T, G, S> {
+        Values::new(self)
+    }
+
+    /// Creates an iterator over the values in the colony and their handles, by mutable reference.
+    ///
+    /// See [`iter`](Colony::iter).
+    pub fn iter_mut(&mut self) -> IterMut<T, G, S> {
+        IterMut::new(self)
+    }
+
+    /// Creates an iterator over just the values in the colony, by mutable reference.
+    ///
+    /// See [`values`](Colony::values).
+    pub fn values_mut(&mut self) -> ValuesMut<T, G, S> {
+        ValuesMut::new(self)
+    }
+
+    /// Creates an iterator over the maximal runs of adjacent elements in the colony.
+    ///
+    /// Each run is given with the index of its first element, and contains every element up to the next removed slot.
+    /// This can be used to process elements in batches rather than one at a time.
+    /// Elements are visited in the same order as [`iter`](Colony::iter).
+    ///
+    /// See [`Run`] for details on when a run can be accessed as a slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::flagged();
+    /// colony.extend(0..5);
+    /// colony.remove(2);
+    ///
+    /// let runs: Vec<_> = colony
+    ///     .runs()
+    ///     .map(|(start, run)| (start, run.iter().copied().collect::<Vec<_>>()))
+    ///     .collect();
+    ///
+    /// assert_eq!(runs, [(0, vec![0, 1]), (3, vec![3, 4])]);
+    /// ```
+    pub fn runs(&self) -> Runs<T, G, S> {
+        Runs::new(self)
+    }
+
+    /// Creates an iterator over the maximal runs of adjacent elements in the colony, by mutable reference.
+    ///
+    /// See [`runs`](Colony::runs).
+    pub fn runs_mut(&mut self) -> RunsMut<T, G, S> {
+        RunsMut::new(self)
+    }
+
+    /// Returns a cursor positioned at the first element, or at the ghost position if the colony is empty.
+    ///
+    /// See [`CursorMut`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// colony.extend(0..10);
+    ///
+    /// let mut cursor = colony.cursor_front_mut();
+    ///
+    /// while let Some(&mut value) = cursor.current() {
+    ///     if value % 2 == 0 {
+    ///         cursor.remove_current();
+    ///     } else {
+    ///         cursor.replace_current(value * 10);
+    ///         cursor.move_next();
+    ///     }
+    /// }
+    ///
+    /// assert!(colony.values().copied().eq([10, 30, 50, 70, 90]));
+    /// ```
+    pub fn cursor_front_mut(&mut self) -> CursorMut<T, G, S> {
+        CursorMut::front(self)
+    }
+
+    /// Returns a cursor positioned at the last element, or at the ghost position if the colony is empty.
+    ///
+    /// See [`CursorMut`] for more information.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// colony.extend(0..3);
+    ///
+    /// let mut cursor = colony.cursor_back_mut();
+    /// assert_eq!(cursor.current(), Some(&mut 2));
+    /// cursor.move_prev();
+    /// assert_eq!(cursor.current(), Some(&mut 1));
+    /// ```
+    pub fn cursor_back_mut(&mut self) -> CursorMut<T, G, S> {
+        CursorMut::back(self)
+    }
+
+    /// Returns whether two colonies contain the same values, ignoring where they are stored.
+    ///
+    /// Values are compared as multisets, so each value must appear the same number of times in both colonies.
+    /// Unlike `==`, which compares the indices of elements as well as their values,
+    /// this considers a colony equal to its [`clone`](Clone::clone) and to colonies using different guards or storages.
+    ///
+    /// This is an `O(n)` operation, and allocates a temporary hash map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut a = Colony::new();
+    /// let b: Colony<_> = ["bar", "foo"].into_iter().collect();
+    ///
+    /// let foo = a.insert("foo");
+    /// a.insert("bar");
+    ///
+    /// assert!(a.values_eq(&b));
+    /// assert_ne!(a, b);
+    ///
+    /// a.remove(foo);
+    /// assert!(!a.values_eq(&b));
+    /// ```
+    pub fn values_eq<G2: Guard, S2: Storage>(&self, other: &Colony<T, G2, S2>) -> bool
+    where
+        T: Eq + Hash,
+    {
+        if self.len != other.len {
+            return false;
+        }
+
+        let mut counts = HashMap::<&T, usize>::with_capacity(self.len);
+
+        for value in self.values() {
+            *counts.entry(value).or_default() += 1;
+        }
+
+        for value in other.values() {
+            match counts.get_mut(value) {
+                Some(0) | None => return false,
+                Some(count) => *count -= 1,
+            }
+        }
+
+        true
+    }
+}
+
+impl<T, G: Guard, S: Storage> Drop for Colony<T, G, S> {
+    fn drop(&mut self) {
+        unsafe {
+            if mem::needs_drop::<T>() {
+                for value in self.values_mut() {
+                    ptr::drop_in_place(value);
+                }
+            }
+
+            if self.capacity > 0 {
+                let layout = Self::layout(self.capacity).unwrap_unchecked();
+                dealloc(self.slots.alloc(&layout), layout.layout);
+            }
+        }
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> Index<G::Handle> for Colony<T, G, S> {
+    type Output = T;
+
+    fn index(&self, index: G::Handle) -> &T {
+        self.get(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> IndexMut<G::Handle> for Colony<T, G, S> {
+    fn index_mut(&mut self, index: G::Handle) -> &mut T {
+        self.get_mut(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T, G: Guard, S: Storage> Extend<T> for Colony<T, G, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.extend_with(iter.into_iter(), |_| {});
+    }
+}
+
+impl<T, G: Guard, S: Storage> FromIterator<T> for Colony<T, G, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::default();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T: Clone, G: Guard, S: Storage> Clone for Colony<T, G, S> {
+    fn clone(&self) -> Self {
+        let mut colony = Self::from_iter(self.values().cloned());
+        colony.set_reuse_policy(self.reuse);
+        colony
+    }
+}
+
+/// Structural equality, where colonies are equal if they have elements with equal values at the same indices.
+///
+/// With [`GenerationGuard`], the generations of the elements must also be equal,
+/// which means that the handles for an element in one colony would refer to the same index and generation in the other.
+/// Handles are still never interchangeable between colonies.
+///
+/// Empty slots, capacity, the [`ReusePolicy`] and the skipblocks are not compared.
+/// Since [`clone`](Clone::clone) does not preserve indices, a colony may not be equal to its clone.
+/// See [`Colony::values_eq`] to compare values regardless of their indices.
+impl<T: PartialEq, G: Guard, S: Storage> PartialEq for Colony<T, G, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && Iterator::zip(self.iter(), other.iter()).all(|((a, a_value), (b, b_value))| {
+                let a = G::__extract_index(&a);
+                let b = G::__extract_index(&b);
+
+                a == b
+                    && unsafe { self.guard(a).__state() == other.guard(b).__state() }
+                    && a_value == b_value
+            })
+    }
+}
+
+impl<T: Eq, G: Guard, S: Storage> Eq for Colony<T, G, S> {}
+
+/// Hashes the same state compared by structural equality, see the [`PartialEq`] implementation.
+impl<T: Hash, G: Guard, S: Storage> Hash for Colony<T, G, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len.hash(state);
+
+        for (handle, value) in self {
+            let index = G::__extract_index(&handle);
+            index.hash(state);
+            unsafe { self.guard(index).__state().hash(state) };
+            value.hash(state);
+        }
+    }
+}
+
+impl<T: Debug, G: Guard, S: Storage> Debug for Colony<T, G, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = self.iter().map(|(_, value)| value);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> IntoIterator for &'a Colony<T, G, S> {
+    type Item = (G::Handle, &'a T);
+    type IntoIter = Iter<'a, T, G, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self)
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> IntoIterator for &'a mut Colony<T, G, S> {
+    type Item = (G::Handle, &'a mut T);
+    type IntoIter = IterMut<'a, T, G, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IterMut::new(self)
+    }
+}
+
+unsafe impl<T, G: Guard, S: Storage> Send for Colony<T, G, S>
+where
+    T: Send,
+    G: Send,
+{
+}
+
+unsafe impl<T, G: Guard, S: Storage> Sync for Colony<T, G, S>
+where
+    T: Sync,
+    G: Sync,
+{
+}
+
+impl<T, G: Guard, S: Storage> UnwindSafe for Colony<T, G, S>
+where
+    T: UnwindSafe,
+    G: UnwindSafe,
+{
+}
+
+impl<T, G: Guard, S: Storage> RefUnwindSafe for Colony<T, G, S>
+where
+    T: RefUnwindSafe,
+    G: RefUnwindSafe,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use std::fmt::{Debug, Formatter};
+    use std::sync::Arc;
+    use std::{fmt, iter, mem, slice};
+
+    use crate::index_opt::IndexOpt;
+    use crate::test_util::{Random, Step};
+
+    use crate::{
+        ApplyDeltaError, CheckedGuard, Colony, CorruptionError, FlagGuard, GenerationGuard, Guard,
+        Handle, InsertAtError, InterleavedStorage, NoGuard, RemovalQueue, ReusePolicy,
+        SeparatedColony, SeparatedStorage, Storage, TrackedColony,
+    };
+
+    #[cfg(not(feature = "debug-validate"))]
+    const N: &[usize] = &[0, 1, 5, 10, 100, 1_000, 10_000, 100_000];
+    // Validating after every modification makes the larger sizes quadratic
+    #[cfg(feature = "debug-validate")]
+    const N: &[usize] = &[0, 1, 5, 10, 100, 1_000];
+
+    #[derive(Clone)]
+    struct Model<T, S: Storage = InterleavedStorage> {
+        slots: Vec<Option<T>>,
+        colony: Colony<T, NoGuard, S>,
+    }
+
+    impl<T> Model<T> {
+        fn new() -> Self {
+            Self::with_storage()
+        }
+    }
+
+    impl<T, S: Storage> Model<T, S> {
+        fn with_storage() -> Self {
+            Self {
+                slots: Vec::new(),
+                colony: Colony::default(),
+            }
+        }
+
+        pub fn insert(&mut self, value: T) -> usize
+        where
+            T: Clone,
+        {
+            let index = self.colony.insert(value.clone());
+
+            match index.cmp(&self.slots.len()) {
+                Ordering::Equal => self.slots.push(Some(value)),
+                Ordering::Less => {
+                    assert!(self.slots[index].is_none());
+                    self.slots[index] = Some(value);
+                }
+                Ordering::Greater => panic!("out of bounds index"),
+            }
+
+            index
+        }
+
+        pub fn remove(&mut self, index: usize)
+        where
+            T: Eq + Debug,
+        {
+            assert!(index < self.slots.len());
+            let Some(expected) = self.slots[index].take() else {
+                panic!("not occupied");
+            };
+
+            let actual = unsafe { self.colony.remove_unchecked(index) };
+
+            assert_eq!(actual, expected);
+        }
+
+        pub fn check(&self)
+        where
+            T: Eq,
+        {
+            let expected = self.slots.iter().filter_map(|slot| slot.as_ref());
+            let actual = self.colony.iter().map(|(_, value)| value);
+            assert!(Iterator::eq(actual, expected));
+            assert_eq!(self.colony.validate(), Ok(()));
+        }
+    }
+
+    impl<T: Debug, S: Storage> Debug for Model<T, S> {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            #[derive(Debug)]
+            #[allow(unused)]
+            enum Slot<'a, T> {
+                Occupied(&'a T),
+                Unoccupied {
+                    prev: Option<usize>,
+                    next: Option<usize>,
+                },
+            }
+
+            let mut slots = Vec::new();
+
+            for (i, slot) in self.slots.iter().enumerate() {
+                let slot = match slot {
+                    Some(value) => Slot::Occupied(value),
+                    None => unsafe {
+                        let super::Unoccupied { prev, next } = self.colony.unoccupied(i);
+
+                        Slot::Unoccupied {
+                            prev: prev.as_opt(),
+                            next: next.as_opt(),
+                        }
+                    },
+                };
+
+                slots.push(slot);
+            }
+
+            let skipfield = unsafe {
+                slice::from_raw_parts(self.colony.skipfield.as_ptr(), self.colony.touched)
+            };
+
+            f.debug_struct("Model")
+                .field("len", &self.colony.len)
+                .field("touched", &self.colony.touched)
+                .field("capacity", &self.colony.capacity)
+                .field("next_free", &self.colony.next_free.as_opt())
+                .field("slots", &slots)
+                .field("skipfield", &skipfield)
+                .finish()
+        }
+    }
+
+    #[test]
+    fn drops() {
+        for &size in N {
+            let arc = Arc::new(());
+            let mut colony = Colony::new();
+
+            for _ in 0..size {
+                colony.insert(arc.clone());
+            }
+
+            assert_eq!(Arc::strong_count(&arc), size + 1);
+            drop(colony);
+            assert_eq!(Arc::strong_count(&arc), 1);
+        }
+    }
+
+    #[test]
+    fn different_colonies_dont_alias() {
+        let mut colony_1 = Colony::new();
+        let handle_1 = colony_1.insert(1);
+
+        let mut colony_2 = Colony::new();
+        let handle_2 = colony_2.insert(1);
+
+        assert_ne!(handle_1, handle_2);
+        assert!(colony_1.get(handle_2).is_none());
+        assert!(colony_2.get(handle_1).is_none());
+    }
+
+    #[test]
+    fn clear() {
+        let mut colony = Colony::new();
+        let handle = colony.insert(42);
+        colony.clear();
+        assert!(colony.get(handle).is_none());
+    }
+
+    #[test]
+    fn insert_after_clear_doesnt_alias() {
+        let mut colony = Colony::new();
+
+        let handle_1 = colony.insert(1);
+        colony.clear();
+        let handle_2 = colony.insert(2);
+
+        assert_eq!(handle_1.index, handle_2.index);
+        assert_ne!(handle_1, handle_2);
+        assert!(colony.get(handle_1).is_none());
+    }
+
+    #[test]
+    fn handle_is_null_pointer_optimized() {
+        assert_eq!(mem::size_of::<Handle>(), 16);
+        assert_eq!(mem::size_of::<Option<Handle>>(), 16);
+    }
+
+    #[test]
+    fn get() {
+        let mut colony = Colony::new();
+        let handle = colony.insert(42);
+        assert_eq!(colony.get(handle), Some(&42));
+    }
+
+    #[test]
+    fn get_after_remove_generation() {
+        let mut colony = Colony::new();
+
+        let handle = colony.insert(42);
+        colony.remove(handle);
+
+        assert_eq!(colony.get(handle), None);
+    }
+
+    #[test]
+    fn get_after_remove_flag() {
+        let mut colony = Colony::flagged();
+
+        let handle = colony.insert(42);
+        colony.remove(handle);
+
+        assert_eq!(colony.get(handle), None);
+    }
+
+    #[test]
+    fn get_after_readd() {
+        let mut colony = Colony::new();
+
+        let handle_1 = colony.insert(42);
+        colony.remove(handle_1);
+        let handle_2 = colony.insert(42);
+
+        assert_ne!(handle_1, handle_2);
+        assert_eq!(colony.get(handle_1), None);
+    }
+
+    #[test]
+    fn reserve() {
+        fn test<T>(size: usize) {
+            let mut colony = Colony::<T>::new();
+            colony.reserve(size);
+        }
+
+        for &size in N {
+            test::<()>(size);
+            test::<u8>(size);
+            test::<u32>(size);
+            test::<[u32; 32]>(size);
+        }
+    }
+
+    #[test]
+    fn insert() {
+        fn test<I>(values: I)
+        where
+            I: Iterator,
+            I::Item: Eq + Clone,
+        {
+            let mut model = Model::new();
+
+            for (i, value) in values.enumerate() {
+                let index = model.insert(value);
+                assert_eq!(index, i);
+            }
+
+            model.check();
+        }
+
+        for &size in N {
+            test(iter::repeat(()).take(size));
+            test(iter::repeat(42u8).take(size));
+            test(iter::repeat(42u32).take(size));
+            test(iter::repeat([42u32; 32]).take(size));
+        }
+    }
+
+    #[test]
+    fn remove_all_forward() {
+        for &size in N {
+            let mut model = Model::new();
+
+            for i in 0..size {
+                model.insert(i);
+            }
+
+            for i in 0..size {
+                model.remove(i);
+            }
+
+            model.check();
+        }
+    }
+
+    #[test]
+    fn remove_all_backward() {
+        for &size in N {
+            let mut model = Model::new();
+
+            for i in 0..size {
+                model.insert(i);
+            }
+
+            for i in (0..size).rev() {
+                model.remove(i);
+            }
+
+            model.check();
+        }
+    }
+
+    #[test]
+    fn reuse_slot() {
+        for &size in N {
+            if size == 0 {
+                continue;
+            }
+
+            let mut model = Model::new();
+
+            for i in 0..size {
+                model.insert(i);
+            }
+
+            for i in 0..size {
+                model.remove(i);
+
+                let index = model.insert(i);
+                assert_eq!(index, i);
+            }
+
+            model.check();
+        }
+    }
+
+    #[test]
+    fn reuse_policies() {
+        let policies = [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ];
+
+        for policy in policies {
+            let mut model = Model::new();
+            model.colony.set_reuse_policy(policy);
+
+            let mut state = 1_u64;
+
+            for i in 0..10_000 {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let index = (state >> 33) as usize % 256;
+
+                if model.slots.get(index).is_some_and(|slot| slot.is_some()) {
+                    model.remove(index);
+                } else {
+                    let lowest = model.slots.iter().position(|slot| slot.is_none());
+                    let inserted = model.insert(i);
+
+                    if policy == ReusePolicy::LowestFirst {
+                        assert_eq!(inserted, lowest.unwrap_or(inserted));
+                    }
+                }
+
+                if i % 100 == 0 {
+                    model.check();
+                }
+            }
+
+            model.check();
+        }
+    }
+
+    #[test]
+    fn reuse_lowest_first_after_switching() {
+        let mut model = Model::new();
+
+        for i in 0..10 {
+            model.insert(i);
+        }
+
+        for i in [8, 1, 5, 4, 2] {
+            model.remove(i);
+        }
+
+        model.colony.set_reuse_policy(ReusePolicy::LowestFirst);
+        model.check();
+
+        for expected in [1, 2, 4, 5, 8, 10] {
+            assert_eq!(model.insert(expected), expected);
+        }
+
+        model.check();
+    }
+
+    #[test]
+    fn reuse_fill_current() {
+        let mut model = Model::new();
+        model.colony.set_reuse_policy(ReusePolicy::FillCurrent);
+
+        for i in 0..10 {
+            model.insert(i);
+        }
+
+        for i in [2, 3, 4] {
+            model.remove(i);
+        }
+
+        assert_eq!(model.insert(2), 2);
+        model.remove(7);
+        assert_eq!(model.insert(3), 3);
+        model.remove(0);
+        assert_eq!(model.insert(4), 4);
+        assert_eq!(model.insert(0), 0);
+        assert_eq!(model.insert(7), 7);
+
+        model.check();
+    }
+
+    #[test]
+    fn extend_with_handles() {
+        for policy in [ReusePolicy::Lifo, ReusePolicy::LowestFirst] {
+            let mut model = Model::new();
+            model.colony.set_reuse_policy(policy);
+
+            for i in 0..1_000 {
+                model.insert(i);
+            }
+
+            // Skipblocks of various sizes, including ones large enough to spill
+            for i in (3..10).chain(20..21).chain(100..500).chain(600..999) {
+                model.remove(i);
+            }
+
+            let mut handles = vec![usize::MAX];
+            model.colony.extend_with_handles(1_000..2_000, &mut handles);
+            assert_eq!(handles[0], usize::MAX);
+
+            for (&index, value) in handles[1..].iter().zip(1_000..) {
+                assert!(model.slots.get(index).is_none_or(|slot| slot.is_none()));
+
+                if index < model.slots.len() {
+                    model.slots[index] = Some(value);
+                } else {
+                    assert_eq!(index, model.slots.len());
+                    model.slots.push(Some(value));
+                }
+            }
+
+            model.check();
+        }
+    }
+
+    #[test]
+    fn insert_many_partial_skipblock() {
+        let mut model = Model::new();
+
+        for i in 0..600 {
+            model.insert(i);
+        }
+
+        for i in 10..590 {
+            model.remove(i);
+        }
+
+        let handles = model.colony.insert_many(600..900);
+        assert!(handles.iter().copied().eq(10..310));
+
+        for (index, value) in (10..310).zip(600..) {
+            model.slots[index] = Some(value);
+        }
+
+        model.check();
+
+        for i in [5, 6, 300, 309] {
+            model.remove(i);
+        }
+
+        model.check();
+    }
+
+    #[test]
+    fn extend_with_handles_panic() {
+        let mut colony = Colony::flagged();
+        colony.extend((0..20).map(Arc::new));
+
+        for i in 5..15 {
+            colony.remove(i);
+        }
+
+        let mut handles = Vec::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let values = (100..110).map(|i| {
+                assert!(i < 104);
+                Arc::new(i)
+            });
+
+            colony.extend_with_handles(values, &mut handles);
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(handles, [5, 6, 7, 8]);
+        assert_eq!(colony.len(), 14);
+        assert_eq!(colony.validate(), Ok(()));
+
+        let values: Vec<_> = colony.values().map(|value| **value).collect();
+        let expected: Vec<_> = (0..5).chain(100..104).chain(15..20).collect();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn remove_many_unchecked() {
+        let policies = [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ];
+
+        for policy in policies {
+            let mut model = Model::new();
+            model.colony.set_reuse_policy(policy);
+
+            for i in 0..1_000 {
+                model.insert(i);
+            }
+
+            let mut state = 1_u64;
+
+            for round in 0..20 {
+                let mut indices = Vec::new();
+
+                for (index, slot) in model.slots.iter_mut().enumerate() {
+                    state = state
+                        .wrapping_mul(6_364_136_223_846_793_005)
+                        .wrapping_add(1_442_695_040_888_963_407);
+
+                    // Remove long runs in some rounds and scattered elements in others
+                    let keep = (state >> 33) % 8 < if round % 2 == 0 { 1 } else { 6 };
+
+                    if slot.is_some() && !keep {
+                        indices.push(index);
+                        *slot = None;
+                    }
+                }
+
+                indices.reverse();
+
+                unsafe {
+                    model.colony.remove_many_unchecked(indices);
+                }
+
+                model.check();
+
+                for i in 0..300 {
+                    model.insert(i);
+                }
+
+                model.check();
+            }
+        }
+    }
+
+    #[test]
+    fn remove_many_reports_stale() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..10);
+
+        colony.remove(handles[4]);
+        let readded = colony.insert(100);
+        assert_eq!(readded.index, 4);
+
+        let mut foreign = Colony::new();
+        let foreign = foreign.insert(0);
+
+        let requested = [
+            handles[5], handles[4], handles[2], handles[3], handles[2], readded, foreign,
+        ];
+
+        let mut stale = colony.remove_many(requested);
+        stale.sort_by_key(|handle| handle.index);
+        assert_eq!(stale, [foreign, handles[2], handles[4]]);
+
+        assert!(colony.values().copied().eq([0, 1, 6, 7, 8, 9]));
+        assert_eq!(colony.validate(), Ok(()));
+    }
+
+    #[test]
+    fn remove_many_retired() {
+        let mut colony = Colony::new();
+        let mut handles = colony.insert_many(0..3);
+
+        // Keep the middle slot a generation ahead so it retires first
+        colony.remove(handles[1]);
+        handles[1] = colony.insert(1);
+
+        while handles.iter().all(|handle| handle.index < 3) {
+            let stale = colony.remove_many(handles.iter().copied());
+            assert!(stale.is_empty());
+            assert_eq!(colony.validate(), Ok(()));
+
+            handles = colony.insert_many(0..3);
+        }
+
+        assert_eq!(colony.len(), 3);
+        assert_eq!(colony.validate(), Ok(()));
+        assert!(colony.stats().retired >= 1);
+    }
+
+    #[test]
+    fn remove_many_panic() {
+        struct Panicky(usize);
+
+        impl Drop for Panicky {
+            fn drop(&mut self) {
+                if self.0 == 3 {
+                    panic!();
+                }
+            }
+        }
+
+        let mut colony = Colony::flagged();
+        colony.extend((0..10).map(Panicky));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            colony.remove_many([1, 2, 3, 4, 7, 8]);
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(colony.validate(), Ok(()));
+
+        let values: Vec<_> = colony.values().map(|value| value.0).collect();
+        assert_eq!(values, [0, 5, 6, 7, 8, 9]);
+
+        colony.clear();
+    }
+
+    #[test]
+    fn join_skipblocks() {
+        let mut model = Model::new();
+
+        for i in 0..5 {
+            model.insert(i);
+        }
+
+        model.remove(1);
+        model.remove(3);
+        model.remove(2);
+
+        model.check();
+    }
+
+    #[test]
+    fn remove_and_readd_twice() {
+        let mut model = Model::new();
+
+        assert_eq!(model.insert(1), 0);
+        model.remove(0);
+        assert_eq!(model.insert(2), 0);
+        assert_eq!(model.insert(3), 1);
+
+        model.check();
+    }
+
+    #[test]
+    fn insert_after_skipblock_join() {
+        let mut model = Model::new();
+
+        assert_eq!(model.insert(1), 0);
+        assert_eq!(model.insert(2), 1);
+        assert_eq!(model.insert(3), 2);
+
+        model.remove(0);
+        model.remove(2);
+        model.remove(1);
+
+        assert_eq!(model.insert(5), 0);
+
+        model.check();
+    }
+
+    #[test]
+    fn skipblock_join_and_reinsert_with_other_skipblock() {
+        let mut model = Model::new();
+
+        model.insert(1);
+        model.insert(2);
+        model.insert(3);
+        model.insert(4);
+        model.insert(5);
+
+        model.remove(4);
+        model.remove(0);
+        model.remove(2);
+        model.remove(1);
+
+        model.insert(6);
+        model.insert(7);
+        model.insert(8);
+        model.insert(9);
+        model.insert(10);
+
+        model.check();
+    }
+
+    #[test]
+    fn multiple_skipblocks_with_join() {
+        let mut model = Model::new();
+
+        model.insert(1);
+        model.insert(1);
+        model.insert(1);
+        model.insert(1);
+        model.insert(1);
+        model.insert(1);
+
+        model.remove(2);
+        model.remove(5);
+        model.remove(0);
+        model.remove(1);
+
+        model.insert(1);
+        model.insert(1);
+
+        model.remove(4);
+
+        model.insert(1);
+        model.insert(1);
+        model.insert(1);
+
+        model.check();
+    }
+
+    #[test]
+    fn runs() {
+        for &size in N {
+            let mut model = Model::new();
+
+            for i in 0..size {
+                model.insert(i);
+            }
+
+            for i in (0..size).filter(|i| i % 3 == 0 || i % 7 == 0) {
+                model.remove(i);
+            }
+
+            let mut expected: Vec<(usize, Vec<usize>)> = Vec::new();
+
+            for (i, slot) in model.slots.iter().enumerate() {
+                match (slot, expected.last_mut()) {
+                    (Some(value), Some((start, run))) if *start + run.len() == i => {
+                        run.push(*value)
+                    }
+                    (Some(value), _) => expected.push((i, vec![*value])),
+                    (None, _) => {}
+                }
+            }
+
+            let actual: Vec<_> = model
+                .colony
+                .runs()
+                .map(|(start, run)| (start, run.iter().copied().collect::<Vec<_>>()))
+                .collect();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn runs_over_long_skipblocks() {
+        let mut colony = Colony::flagged();
+        colony.extend(0..1_000);
+
+        for i in (10..500).chain(501..990) {
+            colony.remove(i);
+        }
+
+        let runs: Vec<_> = colony
+            .runs()
+            .map(|(start, run)| (start, run.len()))
+            .collect();
+        assert_eq!(runs, [(0, 10), (500, 1), (990, 10)]);
+    }
+
+    #[test]
+    fn runs_mut() {
+        let mut colony = Colony::flagged();
+        colony.extend(0..10);
+        colony.remove(4);
+
+        for (start, mut run) in colony.runs_mut() {
+            for (i, value) in run.iter_mut().enumerate() {
+                assert_eq!(*value, start + i);
+                *value *= 2;
+            }
+        }
+
+        assert!(Iterator::eq(
+            colony.values().copied(),
+            [0, 2, 4, 6, 10, 12, 14, 16, 18]
+        ));
+    }
+
+    #[test]
+    fn separated_insert_and_remove() {
+        fn test<T: Eq + Clone + Debug>(value: T) {
+            for &size in N {
+                let mut model = Model::<T, SeparatedStorage>::with_storage();
+
+                for _ in 0..size {
+                    model.insert(value.clone());
+                }
+
+                for i in (0..size).filter(|i| i % 2 == 0) {
+                    model.remove(i);
+                }
+
+                for i in (0..size).filter(|i| i % 2 == 1).rev() {
+                    model.remove(i);
+                }
+
+                for i in 0..size {
+                    assert_eq!(model.insert(value.clone()), i);
+                }
+
+                model.check();
+            }
+        }
+
+        // Links are stored separately for small elements, and overlap larger elements
+        test(());
+        test(42u8);
+        test(42u64);
+        test([42u64; 4]);
+    }
+
+    #[test]
+    fn separated_get_after_readd() {
+        let mut colony = Colony::separated();
+
+        let handle_1 = colony.insert(42);
+        colony.remove(handle_1);
+        let handle_2 = colony.insert(43);
+
+        assert_eq!(handle_1.index, handle_2.index);
+        assert_eq!(colony.get(handle_1), None);
+        assert_eq!(colony.get(handle_2), Some(&43));
+    }
+
+    #[test]
+    fn separated_drops() {
+        for &size in N {
+            let arc = Arc::new(());
+            let mut colony = Colony::separated();
+
+            for _ in 0..size {
+                colony.insert(arc.clone());
+            }
+
+            assert_eq!(Arc::strong_count(&arc), size + 1);
+            drop(colony);
+            assert_eq!(Arc::strong_count(&arc), 1);
+        }
+    }
+
+    #[test]
+    fn separated_runs_are_slices() {
+        let mut colony: SeparatedColony<u8, FlagGuard> = Colony::default();
+        colony.extend(0..10);
+        colony.remove(3);
+
+        let runs: Vec<_> = colony
+            .runs()
+            .map(|(start, run)| (start, run.as_slice().unwrap()))
+            .collect();
+
+        assert_eq!(runs, [(0, &[0, 1, 2][..]), (4, &[4, 5, 6, 7, 8, 9][..])]);
+    }
+
+    #[test]
+    fn separated_is_smaller() {
+        fn size<T, S: Storage>() -> usize {
+            let layout = Colony::<T, FlagGuard, S>::layout(1024).unwrap();
+            layout.layout.size() / 1024
+        }
+
+        fn generation_size<T, S: Storage>() -> usize {
+            let layout = Colony::<T, GenerationGuard, S>::layout(1024).unwrap();
+            layout.layout.size() / 1024
+        }
+
+        assert_eq!(size::<u8, InterleavedStorage>(), 25);
+        assert_eq!(size::<u8, SeparatedStorage>(), 19);
+        assert_eq!(size::<[u64; 4], InterleavedStorage>(), 41);
+        assert_eq!(size::<[u64; 4], SeparatedStorage>(), 34);
+
+        // The sizes given in the documentation
+        assert_eq!(generation_size::<[u64; 4], InterleavedStorage>(), 41);
+        assert_eq!(generation_size::<[u64; 4], SeparatedStorage>(), 37);
+        assert_eq!(generation_size::<u32, SeparatedStorage>(), 25);
+    }
+
+    #[test]
+    fn stats_empty() {
+        let stats = Colony::<i32>::new().stats();
+
+        assert_eq!(stats.len, 0);
+        assert_eq!(stats.touched, 0);
+        assert_eq!(stats.capacity, 0);
+        assert_eq!(stats.skipblocks, 0);
+        assert_eq!(stats.largest_skipblock, 0);
+        assert_eq!(stats.freelist_len, 0);
+        assert_eq!(stats.retired, 0);
+        assert_eq!(stats.allocated_bytes, 0);
+        assert_eq!(stats.average_skipblock(), 0.0);
+    }
+
+    #[test]
+    fn stats() {
+        let mut colony = Colony::flagged();
+        colony.extend(0..1_000);
+
+        for i in (0..1_000).filter(|i| i % 10 == 0) {
+            colony.remove(i);
+        }
+
+        for i in 300..700 {
+            colony.remove(i);
+        }
+
+        let stats = colony.stats();
+
+        assert_eq!(stats.len, 1_000 - 100 - 360);
+        assert_eq!(stats.touched, 1_000);
+        assert_eq!(stats.capacity, colony.capacity());
+        assert_eq!(stats.skipblocks, 30 + 1 + 29);
+        assert_eq!(stats.largest_skipblock, 401);
+        assert_eq!(stats.freelist_len, 460);
+        assert_eq!(stats.retired, 0);
+        assert_eq!(stats.empty(), 460);
+        assert!(stats.allocated_bytes >= 1_000 * mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn stats_retired() {
+        let mut colony = Colony::new();
+
+        loop {
+            let handle = colony.insert(());
+
+            if handle.index != 0 {
+                break;
+            }
+
+            colony.remove(handle);
+        }
+
+        let stats = colony.stats();
+
+        assert_eq!(stats.len, 1);
+        assert_eq!(stats.touched, 2);
+        assert_eq!(stats.skipblocks, 1);
+        assert_eq!(stats.freelist_len, 0);
+        assert_eq!(stats.retired, 1);
+    }
+
+    #[test]
+    fn stats_retired_beside_reusable() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..3);
+
+        let mut handle = handles[0];
+        while handle.index == 0 {
+            colony.remove(handle);
+            handle = colony.insert(0);
+        }
+
+        // The emptied slot joins the skipblock of the retired slot, but can still be reused
+        colony.remove(handles[1]);
+
+        let stats = colony.stats();
+        assert_eq!(stats.skipblocks, 1);
+        assert_eq!(stats.freelist_len, 1);
+        assert_eq!(stats.retired, 1);
+        assert_eq!(stats.empty(), 2);
+    }
+
+    #[test]
+    fn lowest_first_after_retirement() {
+        let mut colony = Colony::new();
+        colony.set_reuse_policy(ReusePolicy::LowestFirst);
+        let handles = colony.insert_many(0..3);
+
+        // Reuse the first slot until it retires
+        let mut handle = handles[0];
+        while handle.index == 0 {
+            colony.remove(handle);
+            handle = colony.insert(0);
+        }
+
+        // The lowest empty slot now follows a retired slot in its skipblock
+        colony.remove(handles[1]);
+        assert_eq!(colony.validate(), Ok(()));
+
+        let a = colony.insert(1);
+        assert_eq!(a.index, 1);
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(colony.values().copied().collect::<Vec<_>>(), [1, 2, 0]);
+
+        colony.remove(a);
+        colony.remove(handles[2]);
+        colony.extend([1, 2, 3]);
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(colony.values().copied().collect::<Vec<_>>(), [1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn lowest_first_insert_at() {
+        let mut server = Colony::new();
+        let handles = server.insert_many(0..8);
+
+        let mut client = Colony::new();
+        client.set_reuse_policy(ReusePolicy::LowestFirst);
+        client.insert_at(handles[7], 7).unwrap();
+
+        // Fill slots from the middle of the freelist, then insert around them
+        for &i in &[3, 1, 5] {
+            client.insert_at(handles[i], i).unwrap();
+        }
+
+        for i in [0, 2, 4, 6] {
+            assert_eq!(client.insert(i).index, i);
+        }
+
+        assert_eq!(client.validate(), Ok(()));
+        assert_eq!(client.len(), 8);
+        assert_eq!(client.insert(8).index, 8);
+    }
+
+    #[test]
+    fn validate_after_retirement() {
+        let mut colony = Colony::new();
+
+        loop {
+            let handle = colony.insert(());
+
+            if handle.index != 0 {
+                break;
+            }
+
+            colony.remove(handle);
+        }
 
-    /// Creates an iterator over just the values in the colony, by mutable reference.
-    ///
-    /// See [`values`](Colony::values).
-    pub fn values_mut(&mut self) -> ValuesMut<T, G> {
-        ValuesMut::new(self)
+        assert_eq!(colony.validate(), Ok(()));
     }
-}
 
-impl<T, G: Guard> Drop for Colony<T, G> {
-    fn drop(&mut self) {
-        unsafe {
-            if mem::needs_drop::<T>() {
-                for value in self.values_mut() {
-                    ptr::drop_in_place(value);
-                }
+    #[test]
+    fn reuse_beside_retired() {
+        for policy in [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ] {
+            let mut colony = Colony::new();
+            colony.set_reuse_policy(policy);
+            let handles = colony.insert_many(0..7);
+
+            // Reuse a slot in the middle until it retires
+            let mut handle = handles[3];
+            while handle.index == 3 {
+                colony.remove(handle);
+                handle = colony.insert(3);
             }
 
-            if self.capacity > 0 {
-                let (layout, _) = Self::layout(self.capacity).unwrap_unchecked();
-                dealloc(self.elements.as_ptr() as *mut u8, layout);
+            // Empty slots beside the retired slot, then join them with slots beside those
+            for i in [4, 2, 6, 5, 0, 1] {
+                colony.remove(handles[i]);
+                assert_eq!(colony.validate(), Ok(()));
             }
+
+            let mut indices: Vec<_> = (0..6).map(|i| colony.insert(i).index).collect();
+            indices.sort();
+            assert_eq!(indices, [0, 1, 2, 4, 5, 6]);
+            assert_eq!(colony.validate(), Ok(()));
+            assert_eq!(colony.insert(6).index, 8);
         }
     }
-}
 
-impl<T, G: CheckedGuard> Index<G::Handle> for Colony<T, G> {
-    type Output = T;
+    #[test]
+    fn validate_detects_corruption() {
+        fn corrupted(corrupt: impl FnOnce(&mut Colony<usize, FlagGuard>)) -> CorruptionError {
+            let mut colony = Colony::flagged();
+            colony.extend(0..10);
 
-    fn index(&self, index: G::Handle) -> &T {
-        self.get(index)
-            .expect("no element with that handle exists in this colony")
-    }
-}
+            for i in [2, 5, 6, 7] {
+                colony.remove(i);
+            }
 
-impl<T, G: CheckedGuard> IndexMut<G::Handle> for Colony<T, G> {
-    fn index_mut(&mut self, index: G::Handle) -> &mut T {
-        self.get_mut(index)
-            .expect("no element with that handle exists in this colony")
-    }
-}
+            assert_eq!(colony.validate(), Ok(()));
+            corrupt(&mut colony);
+            let error = colony.validate().unwrap_err();
 
-impl<T, G: Guard> Extend<T> for Colony<T, G> {
-    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        let mut iter = iter.into_iter();
+            // Restore a valid state so the colony can be dropped
+            colony.len = 0;
+            colony.touched = 0;
+            error
+        }
 
-        unsafe {
-            while let Some(free) = self.next_free.as_opt() {
-                let Some(value) = iter.next() else { return };
-                self.insert_into_free(free, value);
+        let error = corrupted(|colony| unsafe { *colony.skipfield.as_ptr().add(10) = 1 });
+        assert_eq!(error, CorruptionError::UnzeroedSkipfield { index: 10 });
+
+        let error = corrupted(|colony| unsafe { *colony.skipfield.as_ptr().add(7) = 1 });
+        assert_eq!(
+            error,
+            CorruptionError::SkipblockMismatch {
+                start: 5,
+                head: 3,
+                tail: 1
             }
+        );
 
-            while let Some(value) = iter.next() {
-                if self.len == self.capacity {
-                    let (lower, _) = iter.size_hint();
-                    self.reserve(lower.saturating_add(1));
-                }
+        let error = corrupted(|colony| unsafe { *colony.skipfield.as_ptr().add(9) = 1 });
+        assert_eq!(
+            error,
+            CorruptionError::GuardMismatch {
+                index: 9,
+                occupied: false
+            }
+        );
 
-                self.insert_at_end_unchecked(value);
+        let error = corrupted(|colony| unsafe {
+            (*colony.slots.guard(3)).__empty();
+        });
+        assert_eq!(
+            error,
+            CorruptionError::GuardMismatch {
+                index: 3,
+                occupied: true
             }
-        }
-    }
-}
+        );
 
-impl<T, G: Guard> FromIterator<T> for Colony<T, G> {
-    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut result = Self::default();
-        result.extend(iter);
-        result
-    }
-}
+        let error = corrupted(|colony| colony.len += 1);
+        assert_eq!(
+            error,
+            CorruptionError::LenMismatch {
+                len: 7,
+                occupied: 6
+            }
+        );
 
-impl<T: Clone, G: Guard> Clone for Colony<T, G> {
-    fn clone(&self) -> Self {
-        Self::from_iter(self.values().cloned())
-    }
-}
+        let error = corrupted(|colony| colony.next_free = unsafe { IndexOpt::some(3) });
+        assert_eq!(error, CorruptionError::InvalidFreelistNode { index: 3 });
 
-impl<T: Debug, G: Guard> Debug for Colony<T, G> {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let iter = self.iter().map(|(_, value)| value);
-        f.debug_list().entries(iter).finish()
-    }
-}
+        let error = corrupted(|colony| colony.next_free = unsafe { IndexOpt::some(6) });
+        assert_eq!(error, CorruptionError::FreelistLinkMismatch { index: 6 });
 
-impl<'a, T, G: Guard> IntoIterator for &'a Colony<T, G> {
-    type Item = (G::Handle, &'a T);
-    type IntoIter = Iter<'a, T, G>;
+        let error = corrupted(|colony| colony.next_free = IndexOpt::none());
+        assert_eq!(error, CorruptionError::MissingFromFreelist { index: 2 });
 
-    fn into_iter(self) -> Self::IntoIter {
-        Iter::new(self)
+        let error = corrupted(|colony| unsafe {
+            colony.unoccupied_mut(7).next = IndexOpt::some(5);
+        });
+        assert_eq!(error, CorruptionError::FreelistCycle { index: 5 });
     }
-}
 
-impl<'a, T, G: Guard> IntoIterator for &'a mut Colony<T, G> {
-    type Item = (G::Handle, &'a mut T);
-    type IntoIter = IterMut<'a, T, G>;
+    fn hash_of<T: std::hash::Hash>(value: &T) -> u64 {
+        use std::hash::{BuildHasher, RandomState};
 
-    fn into_iter(self) -> Self::IntoIter {
-        IterMut::new(self)
+        // A fixed state, so equal values hash equally across calls
+        thread_local!(static STATE: RandomState = RandomState::new());
+        STATE.with(|state| state.hash_one(value))
     }
-}
 
-unsafe impl<T, G: Guard> Send for Colony<T, G>
-where
-    T: Send,
-    G: Send,
-{
-}
+    #[test]
+    fn structural_eq() {
+        let build = || {
+            let mut colony = Colony::new();
+            colony.extend(0..10);
+            colony.reserve(100);
 
-unsafe impl<T, G: Guard> Sync for Colony<T, G>
-where
-    T: Sync,
-    G: Sync,
-{
-}
+            for i in [2, 3, 7] {
+                unsafe {
+                    colony.remove_unchecked(i);
+                }
+            }
 
-impl<T, G: Guard> UnwindSafe for Colony<T, G>
-where
-    T: UnwindSafe,
-    G: UnwindSafe,
-{
-}
+            colony
+        };
 
-impl<T, G: Guard> RefUnwindSafe for Colony<T, G>
-where
-    T: RefUnwindSafe,
-    G: RefUnwindSafe,
-{
-}
+        let a = build();
+        let mut b = build();
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
 
-struct Slot<T, G: Guard> {
-    guard: G,
-    inner: SlotInner<T>,
-}
+        // The generations of empty slots are not compared
+        let handle = b.insert(100);
+        b.remove(handle);
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
 
-union SlotInner<T> {
-    occupied: ManuallyDrop<T>,
-    unoccupied: Unoccupied,
-}
+        let (first, _) = b.iter().next().unwrap();
+        b[first] = 100;
+        assert_ne!(a, b);
+    }
 
-#[derive(Copy, Clone)]
-struct Unoccupied {
-    prev: IndexOpt,
-    next: IndexOpt,
-}
+    #[test]
+    fn structural_eq_generations() {
+        let mut a = Colony::new();
+        let mut b = Colony::new();
+
+        a.insert(0);
+        let handle = b.insert(1);
+        b.remove(handle);
+        b.insert(0);
+
+        // Same value at the same index, but a different generation
+        assert_eq!(
+            a.values().collect::<Vec<_>>(),
+            b.values().collect::<Vec<_>>()
+        );
+        assert_ne!(a, b);
+        assert!(a.values_eq(&b));
 
-impl<T, G: Guard> Slot<T, G> {
-    pub unsafe fn occupied(&self) -> &T {
-        &self.inner.occupied
-    }
+        // Flags have no state beyond occupancy
+        let mut a = Colony::flagged();
+        let mut b = Colony::flagged();
 
-    pub unsafe fn occupied_mut(&mut self) -> &mut T {
-        &mut self.inner.occupied
-    }
+        a.insert(0);
+        b.insert(1);
+        b.remove(0);
+        b.insert(0);
 
-    pub unsafe fn unoccupied(&self) -> &Unoccupied {
-        &self.inner.unoccupied
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
     }
 
-    pub unsafe fn unoccupied_mut(&mut self) -> &mut Unoccupied {
-        &mut self.inner.unoccupied
+    #[test]
+    fn structural_eq_indices() {
+        let mut a = Colony::flagged();
+        a.extend(0..5);
+        a.remove(1);
+
+        let b: Colony<_, FlagGuard> = a.values().copied().collect();
+
+        assert_ne!(a, b);
+        assert_ne!(a, a.clone());
+        assert!(a.values_eq(&b));
+        assert!(a.values_eq(&a.clone()));
     }
 
-    pub unsafe fn new_full(value: T) -> Self {
-        Self {
-            guard: G::__new(),
-            inner: SlotInner {
-                occupied: ManuallyDrop::new(value),
-            },
-        }
+    #[test]
+    fn values_eq() {
+        let a: Colony<_> = [1, 2, 2, 3].into_iter().collect();
+        let b: SeparatedColony<_, FlagGuard> = [2, 3, 1, 2].into_iter().collect();
+        let c: Colony<_, NoGuard> = [1, 2, 3, 3].into_iter().collect();
+        let d: Colony<_> = [1, 2, 3].into_iter().collect();
+
+        assert!(a.values_eq(&b));
+        assert!(b.values_eq(&a));
+        assert!(!a.values_eq(&c));
+        assert!(!c.values_eq(&a));
+        assert!(!a.values_eq(&d));
+        assert!(Colony::<i32>::new().values_eq(&Colony::<i32, FlagGuard>::flagged()));
     }
 
-    pub unsafe fn fill(&mut self, value: T) {
-        self.guard.__fill();
+    #[test]
+    fn handle_at() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..10);
+        colony.remove(handles[4]);
 
-        self.inner = SlotInner {
-            occupied: ManuallyDrop::new(value),
-        };
-    }
+        for (i, &handle) in handles.iter().enumerate() {
+            let expected = (i != 4).then_some(handle);
+            assert_eq!(colony.handle_at(i), expected);
+        }
 
-    pub unsafe fn empty(&mut self) -> (T, bool) {
-        let value = ManuallyDrop::take(&mut self.inner.occupied);
+        assert_eq!(colony.handle_at(10), None);
+        assert_eq!(colony.handle_at(usize::MAX), None);
 
-        self.inner = SlotInner {
-            unoccupied: Unoccupied {
-                prev: IndexOpt::none(),
-                next: IndexOpt::none(),
-            },
-        };
+        let mut colony = Colony::flagged();
+        colony.extend(0..10);
+        colony.remove(4);
 
-        let reuse = self.guard.__empty();
-        (value, reuse)
+        assert_eq!(colony.handle_at(3), Some(3));
+        assert_eq!(colony.handle_at(4), None);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::cmp::Ordering;
-    use std::fmt::{Debug, Formatter};
-    use std::sync::Arc;
-    use std::{fmt, iter, mem, slice};
+    #[test]
+    fn handle_of() {
+        fn check<S: Storage>() {
+            let mut colony = Colony::<_, FlagGuard, S>::default();
+            colony.extend((0..100_u64).map(|i| [i; 3]));
 
-    use crate::{Colony, Handle, UnguardedColony};
+            for i in (0..100).step_by(3) {
+                colony.remove(i);
+            }
 
-    const N: &[usize] = &[0, 1, 5, 10, 100, 1_000, 10_000, 100_000];
+            for (handle, value) in &colony {
+                assert_eq!(colony.handle_of(value), Some(handle));
+            }
 
-    #[derive(Clone)]
-    struct Model<T> {
-        slots: Vec<Option<T>>,
-        colony: UnguardedColony<T>,
-    }
+            assert_eq!(colony.handle_of(&[1; 3]), None);
 
-    impl<T> Model<T> {
-        fn new() -> Self {
-            Self {
-                slots: Vec::new(),
-                colony: Colony::default(),
-            }
+            let other: Colony<_, FlagGuard, S> = colony.values().copied().collect();
+            assert_eq!(colony.handle_of(other.values().next().unwrap()), None);
         }
 
-        pub fn insert(&mut self, value: T) -> usize
-        where
-            T: Clone,
-        {
-            let index = self.colony.insert(value.clone());
+        check::<InterleavedStorage>();
+        check::<SeparatedStorage>();
 
-            match index.cmp(&self.slots.len()) {
-                Ordering::Equal => self.slots.push(Some(value)),
-                Ordering::Less => {
-                    assert!(self.slots[index].is_none());
-                    self.slots[index] = Some(value);
-                }
-                Ordering::Greater => panic!("out of bounds index"),
-            }
+        let mut colony = Colony::new();
+        let handle = colony.insert(());
+        assert_eq!(colony.handle_of(&colony[handle]), None);
+        assert_eq!(Colony::<u8>::new().handle_of(&0), None);
+    }
 
-            index
-        }
+    #[test]
+    fn get_by_index() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..10);
+        colony.remove(handles[2]);
 
-        pub fn remove(&mut self, index: usize)
-        where
-            T: Eq + Debug,
-        {
-            assert!(index < self.slots.len());
-            let Some(expected) = self.slots[index].take() else {
-                panic!("not occupied");
-            };
+        assert_eq!(colony.get_by_index(1), Some((handles[1], &1)));
+        assert_eq!(colony.get_by_index(2), None);
+        assert_eq!(colony.get_by_index(10), None);
 
-            let actual = unsafe { self.colony.remove_unchecked(index) };
+        let (handle, value) = colony.get_by_index_mut(5).unwrap();
+        assert_eq!(handle, handles[5]);
+        *value = 50;
+        assert_eq!(colony[handles[5]], 50);
 
-            assert_eq!(actual, expected);
-        }
+        assert!(colony.get_by_index_mut(2).is_none());
 
-        pub fn check(&self)
-        where
-            T: Eq,
-        {
-            let expected = self.slots.iter().filter_map(|slot| slot.as_ref());
-            let actual = self.colony.iter().map(|(_, value)| value);
-            assert!(Iterator::eq(actual, expected));
-        }
+        let readded = colony.insert(20);
+        assert_eq!(colony.get_by_index(2), Some((readded, &20)));
+        assert_ne!(readded, handles[2]);
     }
 
-    impl<T: Debug> Debug for Model<T> {
-        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-            #[derive(Debug)]
-            #[allow(unused)]
-            enum Slot<'a, T> {
-                Occupied(&'a T),
-                Unoccupied {
-                    prev: Option<usize>,
-                    next: Option<usize>,
-                },
+    fn check_append<S: Storage>(fill_holes: bool) {
+        let mut colony = Colony::<_, GenerationGuard, S>::default();
+        let mut kept = colony.insert_many(0..100);
+
+        if fill_holes {
+            for handle in kept.drain(10..20) {
+                colony.remove(handle);
             }
+        }
 
-            let mut slots = Vec::new();
+        let mut other = Colony::<_, GenerationGuard, S>::default();
+        let handles = other.insert_many(100..400);
+        let removed = handles[49];
 
-            for (i, slot) in self.slots.iter().enumerate() {
-                let slot = match slot {
-                    Some(value) => Slot::Occupied(value),
-                    None => unsafe {
-                        let super::Unoccupied { prev, next } = self.colony.slot(i).unoccupied();
+        for &handle in handles.iter().step_by(7) {
+            other.remove(handle);
+        }
 
-                        Slot::Unoccupied {
-                            prev: prev.as_opt(),
-                            next: next.as_opt(),
-                        }
-                    },
-                };
+        let remap = colony.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(other.validate(), Ok(()));
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(remap.len(), 300 - 43);
+        assert_eq!(colony.len(), kept.len() + remap.len());
+        assert_eq!(remap.get(removed), None);
 
-                slots.push(slot);
-            }
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(other.get(handle), None);
 
-            let skipfield = unsafe {
-                slice::from_raw_parts(self.colony.skipfield.as_ptr(), self.colony.touched)
-            };
+            match remap.get(handle) {
+                Some(new) => assert_eq!(colony[new], 100 + i),
+                None => assert_eq!(i % 7, 0),
+            }
+        }
 
-            f.debug_struct("Model")
-                .field("len", &self.colony.len)
-                .field("touched", &self.colony.touched)
-                .field("capacity", &self.colony.capacity)
-                .field("next_free", &self.colony.next_free.as_opt())
-                .field("slots", &slots)
-                .field("skipfield", &skipfield)
-                .finish()
+        for (i, &handle) in kept.iter().enumerate() {
+            assert_eq!(
+                colony[handle],
+                if fill_holes && i >= 10 { i + 10 } else { i }
+            );
         }
+
+        // The emptied colony remains usable
+        let handle = other.insert(0);
+        assert_eq!(other[handle], 0);
     }
 
     #[test]
-    fn drops() {
-        for &size in N {
-            let arc = Arc::new(());
-            let mut colony = Colony::new();
-
-            for _ in 0..size {
-                colony.insert(arc.clone());
-            }
+    fn append() {
+        check_append::<InterleavedStorage>(false);
+        check_append::<InterleavedStorage>(true);
+        check_append::<SeparatedStorage>(false);
+        check_append::<SeparatedStorage>(true);
+    }
 
-            assert_eq!(Arc::strong_count(&arc), size + 1);
-            drop(colony);
-            assert_eq!(Arc::strong_count(&arc), 1);
-        }
+    #[test]
+    fn append_drops_once() {
+        let mut colony = Colony::new();
+        let mut other = Colony::new();
+        let value = std::rc::Rc::new(());
+
+        colony.insert(value.clone());
+        other.insert_many(std::iter::repeat_n(value.clone(), 10));
+        colony.append(&mut other);
+        assert_eq!(std::rc::Rc::strong_count(&value), 12);
+
+        drop(other);
+        assert_eq!(std::rc::Rc::strong_count(&value), 12);
+        drop(colony);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
     }
 
     #[test]
-    fn different_colonies_dont_alias() {
-        let mut colony_1 = Colony::new();
-        let handle_1 = colony_1.insert(1);
+    fn append_empty() {
+        let mut colony = Colony::<i32>::new();
+        let mut other = Colony::new();
+
+        assert!(colony.append(&mut other).is_empty());
+        assert_eq!(colony.capacity(), 0);
+
+        other.insert(1);
+        let remap = colony.append(&mut other);
+        let (old, new) = remap.into_iter().next().unwrap();
+        assert_eq!(colony.get(old), None);
+        assert_eq!(colony[new], 1);
+    }
 
-        let mut colony_2 = Colony::new();
-        let handle_2 = colony_2.insert(1);
+    fn check_split<S: Storage>(policy: ReusePolicy) {
+        let mut colony = Colony::<_, GenerationGuard, S>::default();
+        colony.set_reuse_policy(policy);
+        let handles = colony.insert_many(0..500);
 
-        assert_ne!(handle_1, handle_2);
-        assert!(colony_1.get(handle_2).is_none());
-        assert!(colony_2.get(handle_1).is_none());
-    }
+        for &handle in handles.iter().step_by(3) {
+            colony.remove(handle);
+        }
 
-    #[test]
-    fn clear() {
-        let mut colony = Colony::new();
-        let handle = colony.insert(42);
-        colony.clear();
-        assert!(colony.get(handle).is_none());
+        let (mut moved, remap) = colony.partition(|_, &x| x % 5 < 2);
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(moved.validate(), Ok(()));
+        assert_eq!(moved.capacity(), moved.len());
+        assert_eq!(moved.reuse_policy(), policy);
+        assert_eq!(remap.len(), moved.len());
+
+        let (tail, tail_remap) = colony.split_off(300);
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(tail.validate(), Ok(()));
+
+        for (i, &handle) in handles.iter().enumerate() {
+            let in_colony = colony.get(handle);
+            let in_moved = remap.get(handle).map(|new| moved[new]);
+            let in_tail = tail_remap.get(handle).map(|new| tail[new]);
+
+            if i % 3 == 0 {
+                assert_eq!((in_colony, in_moved, in_tail), (None, None, None));
+            } else if i % 5 < 2 {
+                assert_eq!((in_colony, in_moved, in_tail), (None, Some(i), None));
+            } else if i >= 300 {
+                assert_eq!((in_colony, in_moved, in_tail), (None, None, Some(i)));
+            } else {
+                assert_eq!((in_colony, in_moved, in_tail), (Some(&i), None, None));
+            }
+        }
+
+        // Both sides remain usable
+        let handle = colony.insert(1_000);
+        assert_eq!(colony[handle], 1_000);
+        let handle = moved.insert(1_000);
+        assert_eq!(moved[handle], 1_000);
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(moved.validate(), Ok(()));
+    }
+
+    #[test]
+    fn split() {
+        for policy in [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ] {
+            check_split::<InterleavedStorage>(policy);
+            check_split::<SeparatedStorage>(policy);
+        }
     }
 
     #[test]
-    fn insert_after_clear_doesnt_alias() {
+    fn split_edges() {
         let mut colony = Colony::new();
+        let handles = colony.insert_many(0..10);
 
-        let handle_1 = colony.insert(1);
-        colony.clear();
-        let handle_2 = colony.insert(2);
+        let (tail, remap) = colony.split_off(10);
+        assert!(tail.is_empty() && remap.is_empty());
 
-        assert_eq!(handle_1.index, handle_2.index);
-        assert_ne!(handle_1, handle_2);
-        assert!(colony.get(handle_1).is_none());
+        let (tail, remap) = colony.split_off(0);
+        assert!(colony.is_empty());
+        assert_eq!(tail.len(), 10);
+        assert_eq!(remap.len(), 10);
+        assert_eq!(colony.validate(), Ok(()));
+
+        let (moved, remap) = colony.partition(|_, _| true);
+        assert!(moved.is_empty() && remap.is_empty());
+        assert_eq!(colony.get(handles[0]), None);
     }
 
     #[test]
-    fn handle_is_null_pointer_optimized() {
-        assert_eq!(mem::size_of::<Handle>(), 16);
-        assert_eq!(mem::size_of::<Option<Handle>>(), 16);
+    fn partition_panic() {
+        let mut colony = Colony::new();
+        colony.extend(0..10);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            colony.partition(|_, &x| if x == 5 { panic!() } else { true })
+        }));
+
+        assert!(result.is_err());
+        assert!(colony.values().copied().eq(0..10));
     }
 
     #[test]
-    fn get() {
+    fn partition_drops_once() {
+        let value = std::rc::Rc::new(());
         let mut colony = Colony::new();
-        let handle = colony.insert(42);
-        assert_eq!(colony.get(handle), Some(&42));
+        colony.insert_many(std::iter::repeat_n(value.clone(), 10));
+
+        let mut count = 0;
+        let (moved, _) = colony.partition(|_, _| {
+            count += 1;
+            count % 2 == 0
+        });
+
+        assert_eq!(std::rc::Rc::strong_count(&value), 11);
+        drop(moved);
+        assert_eq!(std::rc::Rc::strong_count(&value), 6);
+        drop(colony);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
     }
 
     #[test]
-    fn get_after_remove_generation() {
+    fn flush_removals() {
         let mut colony = Colony::new();
+        let handles = colony.insert_many(0..100);
+        let mut other = Colony::new();
+        let foreign = other.insert(0);
 
-        let handle = colony.insert(42);
-        colony.remove(handle);
+        let queue = RemovalQueue::new();
 
-        assert_eq!(colony.get(handle), None);
+        for (handle, &value) in &colony {
+            if value % 3 == 0 {
+                queue.defer_remove(handle);
+                queue.defer_remove(handle);
+            }
+        }
+
+        queue.defer_remove(foreign);
+        colony.remove(handles[3]);
+        assert_eq!(queue.len(), 69);
+
+        assert_eq!(colony.flush_removals(&queue), 33);
+        assert!(queue.is_empty());
+        assert_eq!(colony.flush_removals(&queue), 0);
+        assert_eq!(other.len(), 1);
+        assert!(colony.values().all(|&value| value % 3 != 0));
+        assert_eq!(colony.validate(), Ok(()));
+
+        // A handle queued before its slot is reused must not remove the new element
+        queue.defer_remove(handles[1]);
+        colony.remove(handles[1]);
+        let readded = colony.insert(1);
+        assert_eq!(colony.flush_removals(&queue), 0);
+        assert_eq!(colony[readded], 1);
+
+        queue.defer_remove(readded);
+        queue.clear();
+        assert_eq!(colony.flush_removals(&queue), 0);
     }
 
     #[test]
-    fn get_after_remove_flag() {
+    fn flush_removals_shared() {
         let mut colony = Colony::flagged();
+        colony.extend(0..1_000);
+        let queue = RemovalQueue::new();
 
-        let handle = colony.insert(42);
-        colony.remove(handle);
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let (colony, queue) = (&colony, &queue);
 
-        assert_eq!(colony.get(handle), None);
-    }
+                scope.spawn(move || {
+                    for (handle, &value) in colony {
+                        if value % 4 == thread {
+                            queue.defer_remove(handle);
+                        }
+                    }
+                });
+            }
+        });
 
-    #[test]
-    fn get_after_readd() {
-        let mut colony = Colony::new();
+        assert_eq!(colony.flush_removals(&queue), 1_000);
+        assert!(colony.is_empty());
+        assert_eq!(colony.validate(), Ok(()));
 
-        let handle_1 = colony.insert(42);
-        colony.remove(handle_1);
-        let handle_2 = colony.insert(42);
+        // With FlagGuard a handle cannot tell the element it was queued for from one reusing its slot
+        let handle = colony.insert(0);
+        queue.defer_remove(handle);
+        colony.remove(handle);
+        assert_eq!(colony.insert(1), handle);
+        assert_eq!(colony.flush_removals(&queue), 1);
+        assert!(colony.is_empty());
+    }
 
-        assert_ne!(handle_1, handle_2);
-        assert_eq!(colony.get(handle_1), None);
+    #[test]
+    fn tracked_changes() {
+        let mut colony = TrackedColony::new();
+        let handles: Vec<_> = (0..10).map(|i| colony.insert(i)).collect();
+
+        let changes = colony.drain_changes();
+        assert_eq!(changes.inserted, handles);
+        assert!(changes.modified.is_empty() && changes.removed.is_empty());
+        assert!(colony.drain_changes().is_empty());
+
+        // Modified then removed is only removed
+        *colony.get_mut(handles[1]).unwrap() += 10;
+        colony.remove(handles[1]);
+
+        // Inserted then modified is only inserted
+        let inserted = colony.insert(20);
+        colony[inserted] += 1;
+
+        // Inserted then removed is not reported
+        let transient = colony.insert(30);
+        colony.remove(transient);
+
+        // Modified twice is reported once
+        colony[handles[2]] += 1;
+        assert!(colony.mark_modified(handles[2]));
+        assert!(!colony.mark_modified(handles[1]));
+
+        let changes = colony.drain_changes();
+        assert_eq!(changes.inserted, [inserted]);
+        assert_eq!(changes.modified, [handles[2]]);
+        assert_eq!(changes.removed, [handles[1]]);
+        assert_eq!(colony[inserted], 21);
+        assert!(colony.drain_changes().is_empty());
     }
 
     #[test]
-    fn reserve() {
-        fn test<T>(size: usize) {
-            let mut colony = Colony::<T>::new();
-            colony.reserve(size);
-        }
+    fn tracked_iter_mut_and_clear() {
+        let mut colony = TrackedColony::from(Colony::flagged());
+        let handles: Vec<_> = (0..5).map(|i| colony.insert(i)).collect();
+        colony.drain_changes();
 
-        for &size in N {
-            test::<()>(size);
-            test::<u8>(size);
-            test::<u32>(size);
-            test::<[u32; 32]>(size);
+        for (_, value) in colony.iter_mut().take(2) {
+            *value *= 10;
         }
+
+        let changes = colony.drain_changes();
+        assert_eq!(changes.modified, handles[..2]);
+
+        colony[handles[3]] = 0;
+        let inserted = colony.insert(5);
+        colony.clear();
+
+        let changes = colony.drain_changes();
+        assert!(changes.inserted.is_empty() && changes.modified.is_empty());
+        assert_eq!(changes.removed, [0, 1, 2, 3, 4]);
+        assert_eq!(inserted, 5);
+        assert!(colony.is_empty());
+
+        let readded = colony.insert(0);
+        assert_eq!(colony.drain_changes().inserted, [readded]);
     }
 
-    #[test]
-    fn insert() {
-        fn test<I>(values: I)
-        where
-            I: Iterator,
-            I::Item: Eq + Clone,
-        {
-            let mut model = Model::new();
+    fn check_delta_replication<G: CheckedGuard>(
+        sender_policy: ReusePolicy,
+        receiver_policy: ReusePolicy,
+    ) where
+        G::Handle: Copy + Eq + Debug,
+    {
+        let mut random = Random::new(1);
+
+        let mut sender = Colony::<usize, G>::default();
+        sender.set_reuse_policy(sender_policy);
+        let mut snapshot = Colony::<usize, G>::default();
+        let mut receiver = Colony::<usize, G>::default();
+        receiver.set_reuse_policy(receiver_policy);
+        let mut handles = Vec::new();
+
+        for tick in 0..60 {
+            for _ in 0..random.below(40) {
+                match random.step(&mut handles) {
+                    Step::Insert(value) => handles.push(sender.insert(value)),
+                    Step::Remove(handle) => {
+                        sender.remove(handle);
+                    }
+                    Step::Change(handle, value) => sender[handle] = value,
+                }
+            }
 
-            for (i, value) in values.enumerate() {
-                let index = model.insert(value);
-                assert_eq!(index, i);
+            if tick % 25 == 24 {
+                sender.clear();
+                handles.clear();
             }
 
-            model.check();
-        }
+            let delta = Colony::diff(&snapshot, &sender);
+            snapshot.apply_delta(delta.clone()).unwrap();
+            receiver.apply_delta(delta).unwrap();
 
-        for &size in N {
-            test(iter::repeat(()).take(size));
-            test(iter::repeat(42u8).take(size));
-            test(iter::repeat(42u32).take(size));
-            test(iter::repeat([42u32; 32]).take(size));
+            assert_eq!(receiver.validate(), Ok(()));
+            assert_eq!(snapshot.validate(), Ok(()));
+            assert_eq!(receiver, sender);
+            assert!(Colony::diff(&receiver, &sender).is_empty());
+
+            for &handle in &handles {
+                assert_eq!(receiver.get(handle), sender.get(handle));
+            }
         }
     }
 
     #[test]
-    fn remove_all_forward() {
-        for &size in N {
-            let mut model = Model::new();
-
-            for i in 0..size {
-                model.insert(i);
+    fn delta_replication() {
+        let policies = [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ];
+
+        for sender_policy in policies {
+            for receiver_policy in policies {
+                check_delta_replication::<GenerationGuard>(sender_policy, receiver_policy);
+                check_delta_replication::<FlagGuard>(sender_policy, receiver_policy);
             }
+        }
+    }
 
-            for i in 0..size {
-                model.remove(i);
-            }
+    #[test]
+    fn delta_contents() {
+        let mut old = Colony::new();
+        let handles = old.insert_many(0..5);
+        let mut new = Colony::new();
+        new.apply_delta(Colony::diff(&Colony::new(), &old)).unwrap();
+
+        new.remove(handles[1]);
+        new.remove(handles[2]);
+        let reused = new.insert(20);
+        new[handles[3]] = 30;
+        new[handles[4]] = 4;
+        let appended = new.insert(50);
+
+        let delta = Colony::diff(&old, &new);
+        assert_eq!(
+            delta.removed().collect::<Vec<_>>(),
+            [handles[1], handles[2]]
+        );
+        assert_eq!(delta.changed().collect::<Vec<_>>(), [(handles[3], &30)]);
 
-            model.check();
-        }
+        let mut inserted: Vec<_> = delta.inserted().collect();
+        inserted.sort();
+        let mut expected = vec![(reused, &20), (appended, &50)];
+        expected.sort();
+        assert_eq!(inserted, expected);
+
+        old.apply_delta(delta).unwrap();
+        assert_eq!(old, new);
+        assert_eq!(old[reused], 20);
     }
 
     #[test]
-    fn remove_all_backward() {
-        for &size in N {
-            let mut model = Model::new();
+    fn delta_mismatch() {
+        let mut sender = Colony::new();
+        let handles = sender.insert_many(0..3);
+
+        let mut snapshot = Colony::new();
+        let mut receiver = Colony::new();
+        let initial = Colony::diff(&Colony::new(), &sender);
+        snapshot.apply_delta(initial.clone()).unwrap();
+        receiver.apply_delta(initial.clone()).unwrap();
+
+        // Deltas between unrelated colonies replace everything, so can be applied again
+        receiver.apply_delta(initial).unwrap();
+        assert_eq!(receiver, sender);
+
+        // The receiver no longer has the element the sender changes
+        receiver.remove(handles[1]);
+        let before: Vec<_> = receiver
+            .iter()
+            .map(|(handle, &value)| (handle, value))
+            .collect();
+
+        sender[handles[1]] = 10;
+        assert_eq!(
+            receiver.apply_delta(Colony::diff(&snapshot, &sender)),
+            Err(ApplyDeltaError::Missing { index: 1 })
+        );
+        assert!(receiver
+            .iter()
+            .map(|(handle, &value)| (handle, value))
+            .eq(before));
+
+        // The receiver has used the generation the sender inserts with
+        let mut receiver = Colony::new();
+        receiver
+            .apply_delta(Colony::diff(&Colony::new(), &snapshot))
+            .unwrap();
+        let local = receiver.insert(20);
+        receiver.remove(local);
+
+        let appended = sender.insert(30);
+        assert_eq!(appended.index, local.index);
+        assert_eq!(
+            receiver.apply_delta(Colony::diff(&snapshot, &sender)),
+            Err(ApplyDeltaError::Unavailable { index: 3 })
+        );
 
-            for i in 0..size {
-                model.insert(i);
-            }
+        assert_eq!(receiver.validate(), Ok(()));
+        assert_eq!(receiver.len(), 3);
+        assert_eq!(receiver.get(appended), None);
 
-            for i in (0..size).rev() {
-                model.remove(i);
+        // A colony with the same layout but a different identity is not a replica
+        let mut foreign = Colony::new();
+        foreign.extend(0..3);
+        assert_eq!(
+            foreign.apply_delta(Colony::diff(&snapshot, &sender)),
+            Err(ApplyDeltaError::ForeignDelta)
+        );
+        assert_eq!(foreign.values().copied().collect::<Vec<_>>(), [0, 1, 2]);
+
+        // Unless the delta replaces every element, taking on the newer identity
+        let id = foreign.id();
+        foreign
+            .apply_delta(Colony::diff(&Colony::new(), &sender))
+            .unwrap();
+        assert_ne!(foreign.id(), id);
+        assert_eq!(foreign.id(), sender.id());
+        assert_eq!(foreign, sender);
+    }
+
+    fn check_insert_at<G: CheckedGuard>(policy: ReusePolicy)
+    where
+        G::Handle: Copy + Eq + Debug,
+    {
+        let mut random = Random::new(7);
+
+        let mut server = Colony::<usize, G>::default();
+        let mut handles = Vec::new();
+
+        for _ in 0..2_000 {
+            match random.step(&mut handles) {
+                Step::Insert(value) => handles.push(server.insert(value)),
+                Step::Remove(handle) => {
+                    server.remove(handle);
+                }
+                Step::Change(handle, value) => server[handle] = value,
             }
+        }
 
-            model.check();
+        let mut client = Colony::<usize, G>::default();
+        client.set_reuse_policy(policy);
+
+        // Insert in an arbitrary order, so slots are taken from the middle of skipblocks
+        let mut order = handles.clone();
+        random.shuffle(&mut order);
+
+        for &handle in &order {
+            assert_eq!(client.insert_at(handle, server[handle]), Ok(()));
+        }
+
+        assert_eq!(client.validate(), Ok(()));
+        assert_eq!(client, server);
+
+        for &handle in &handles {
+            assert_eq!(client.get(handle), server.get(handle));
         }
+
+        // The empty slots left behind are reused as usual
+        let free = client.stats().touched - client.len();
+        for i in 0..free + 10 {
+            client.insert(i);
+        }
+
+        assert_eq!(client.validate(), Ok(()));
+        assert_eq!(client.len(), handles.len() + free + 10);
     }
 
     #[test]
-    fn reuse_slot() {
-        for &size in N {
-            if size == 0 {
-                continue;
-            }
+    fn insert_at() {
+        for policy in [
+            ReusePolicy::Lifo,
+            ReusePolicy::LowestFirst,
+            ReusePolicy::FillCurrent,
+        ] {
+            check_insert_at::<GenerationGuard>(policy);
+            check_insert_at::<FlagGuard>(policy);
+        }
+    }
 
-            let mut model = Model::new();
+    #[test]
+    fn insert_at_errors() {
+        let mut colony = Colony::new();
+        let foo = colony.insert("foo");
+        let bar = colony.insert("bar");
 
-            for i in 0..size {
-                model.insert(i);
-            }
+        assert_eq!(colony.insert_at(foo, "baz"), Err(InsertAtError::Occupied));
 
-            for i in 0..size {
-                model.remove(i);
+        // The slot has moved past the generation of the removed handle
+        colony.remove(foo);
+        assert_eq!(
+            colony.insert_at(foo, "baz"),
+            Err(InsertAtError::Unavailable)
+        );
 
-                let index = model.insert(i);
-                assert_eq!(index, i);
-            }
+        let mut other = Colony::new();
+        other.extend(["a", "b"]);
+        let qux = other.insert("qux");
+        assert_eq!(
+            colony.insert_at(qux, "qux"),
+            Err(InsertAtError::ForeignHandle)
+        );
 
-            model.check();
+        assert_eq!(colony.validate(), Ok(()));
+        assert_eq!(colony.len(), 1);
+        assert_eq!(colony[bar], "bar");
+
+        // A colony keeps its identity once it has allocated, even when empty
+        colony.remove(bar);
+        assert_eq!(
+            colony.insert_at(qux, "qux"),
+            Err(InsertAtError::ForeignHandle)
+        );
+
+        // A cleared colony has a new identity rather than none
+        colony.clear();
+        assert_eq!(
+            colony.insert_at(qux, "qux"),
+            Err(InsertAtError::ForeignHandle)
+        );
+
+        // A colony that has never allocated adopts the identity of the handle's colony
+        let mut fresh = Colony::new();
+        assert_eq!(fresh.insert_at(qux, "qux"), Ok(()));
+        assert_eq!(fresh[qux], "qux");
+        assert_eq!(fresh.id(), other.id());
+        assert_eq!(fresh.validate(), Ok(()));
+
+        // Indexes which can't be reserved are rejected without panicking or aborting
+        for index in [usize::MAX, usize::MAX / 2, usize::MAX / 64] {
+            let far = Handle { index, ..qux };
+            assert_eq!(
+                fresh.insert_at(far, "far"),
+                Err(InsertAtError::CannotReserve)
+            );
+
+            let mut flagged = Colony::flagged();
+            assert_eq!(
+                flagged.insert_at(index, "far"),
+                Err(InsertAtError::CannotReserve)
+            );
+            assert_eq!(flagged.capacity(), 0);
         }
+
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh.validate(), Ok(()));
     }
 
     #[test]
-    fn join_skipblocks() {
-        let mut model = Model::new();
+    fn insert_at_unguarded() {
+        let mut colony = Colony::unguarded();
+        colony.extend(0..10);
 
-        for i in 0..5 {
-            model.insert(i);
+        unsafe {
+            colony.remove_many_unchecked([2, 3, 4, 7]);
         }
 
-        model.remove(1);
-        model.remove(3);
-        model.remove(2);
+        // Without a guard, occupied slots are found by searching the skipfield
+        assert_eq!(colony.insert_at(5, 5), Err(InsertAtError::Occupied));
+        assert_eq!(colony.insert_at(3, 30), Ok(()));
+        assert_eq!(colony.insert_at(7, 70), Ok(()));
+        assert_eq!(colony.insert_at(3, 3), Err(InsertAtError::Occupied));
+        assert_eq!(colony.validate(), Ok(()));
 
-        model.check();
+        let values: Vec<_> = colony.values().copied().collect();
+        assert_eq!(values, [0, 1, 30, 5, 6, 70, 8, 9]);
     }
 
     #[test]
-    fn remove_and_readd_twice() {
-        let mut model = Model::new();
+    fn swap_and_replace() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..5);
+        let removed = handles[4];
+        colony.remove(removed);
 
-        assert_eq!(model.insert(1), 0);
-        model.remove(0);
-        assert_eq!(model.insert(2), 0);
-        assert_eq!(model.insert(3), 1);
+        assert!(colony.swap(handles[0], handles[3]));
+        assert_eq!(colony[handles[0]], 3);
+        assert_eq!(colony[handles[3]], 0);
 
-        model.check();
+        assert!(colony.swap(handles[1], handles[1]));
+        assert_eq!(colony[handles[1]], 1);
+
+        assert!(!colony.swap(handles[1], removed));
+        assert_eq!(colony[handles[1]], 1);
+
+        assert_eq!(colony.replace(handles[2], 20), Some(2));
+        assert_eq!(colony[handles[2]], 20);
+        assert_eq!(colony.replace(removed, 40), None);
+        assert_eq!(colony.validate(), Ok(()));
     }
 
     #[test]
-    fn insert_after_skipblock_join() {
-        let mut model = Model::new();
+    fn swap_identities() {
+        let mut colony = Colony::new();
+        let a = colony.insert(String::from("a"));
+        let b = colony.insert(String::from("b"));
+
+        let (new_a, new_b) = colony.swap_identities(a, b).unwrap();
+        assert_eq!(colony[new_a], "a");
+        assert_eq!(colony[new_b], "b");
+        assert_eq!(new_a.index, b.index);
+        assert_eq!(new_b.index, a.index);
+        assert_eq!(colony.get(a), None);
+        assert_eq!(colony.get(b), None);
+        assert_eq!(colony.swap_identities(a, new_b), None);
+
+        assert_eq!(colony.swap_identities(new_a, new_a), Some((new_a, new_a)));
+        assert_eq!(colony.len(), 2);
+        assert_eq!(colony.validate(), Ok(()));
+
+        let mut colony = Colony::<_, FlagGuard>::default();
+        let a = colony.insert("a");
+        let b = colony.insert("b");
+
+        assert_eq!(colony.swap_identities(a, b), Some((b, a)));
+        assert_eq!(colony[b], "a");
+        assert_eq!(colony[a], "b");
+    }
 
-        assert_eq!(model.insert(1), 0);
-        assert_eq!(model.insert(2), 1);
-        assert_eq!(model.insert(3), 2);
+    #[test]
+    fn take_with() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many((0..3).map(|i| vec![i]));
 
-        model.remove(0);
-        model.remove(2);
-        model.remove(1);
+        assert!(colony.take_with(handles[1], |mut values| {
+            values.push(10);
+            values
+        }));
+        assert_eq!(colony[handles[1]], [1, 10]);
 
-        assert_eq!(model.insert(5), 0);
+        colony.remove(handles[0]);
+        assert!(!colony.take_with(handles[0], |_| unreachable!()));
 
-        model.check();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            colony.take_with(handles[2], |_| panic!());
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(colony.len(), 1);
+        assert_eq!(colony.get(handles[2]), None);
+        assert_eq!(colony.validate(), Ok(()));
     }
 
     #[test]
-    fn skipblock_join_and_reinsert_with_other_skipblock() {
+    fn cursor_traversal() {
         let mut model = Model::new();
 
-        model.insert(1);
-        model.insert(2);
-        model.insert(3);
-        model.insert(4);
-        model.insert(5);
+        for i in 0..1_000 {
+            model.insert(i);
+        }
 
-        model.remove(4);
-        model.remove(0);
-        model.remove(2);
-        model.remove(1);
+        for i in (0..10).chain(100..500).chain(501..600).chain(990..1_000) {
+            model.remove(i);
+        }
 
-        model.insert(6);
-        model.insert(7);
-        model.insert(8);
-        model.insert(9);
-        model.insert(10);
+        let expected: Vec<_> = model
+            .colony
+            .iter()
+            .map(|(index, &value)| (index, value))
+            .collect();
+        let mut cursor = model.colony.cursor_front_mut();
+        let mut forward = Vec::new();
+
+        while let Some(index) = cursor.index() {
+            forward.push((index, *cursor.current().unwrap()));
+            cursor.move_next();
+        }
 
-        model.check();
+        assert_eq!(forward, expected);
+
+        // Wraps around from the ghost position
+        cursor.move_prev();
+        let mut backward = Vec::new();
+
+        while let Some(index) = cursor.index() {
+            backward.push((index, *cursor.current().unwrap()));
+            cursor.move_prev();
+        }
+
+        backward.reverse();
+        assert_eq!(backward, expected);
+
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(10));
     }
 
     #[test]
-    fn multiple_skipblocks_with_join() {
+    fn cursor_remove() {
         let mut model = Model::new();
 
-        model.insert(1);
-        model.insert(1);
-        model.insert(1);
-        model.insert(1);
-        model.insert(1);
-        model.insert(1);
+        for i in 0..100 {
+            model.insert(i);
+        }
 
-        model.remove(2);
-        model.remove(5);
-        model.remove(0);
-        model.remove(1);
+        for i in [10, 11, 50, 99] {
+            model.remove(i);
+        }
 
-        model.insert(1);
-        model.insert(1);
+        let mut cursor = model.colony.cursor_front_mut();
+        let mut removed = Vec::new();
 
-        model.remove(4);
+        while let Some(&mut value) = cursor.current() {
+            if value % 3 == 0 || (40..60).contains(&value) {
+                removed.push(cursor.remove_current().unwrap());
+                assert_eq!(cursor.as_colony().validate(), Ok(()));
+            } else {
+                cursor.move_next();
+            }
+        }
 
-        model.insert(1);
-        model.insert(1);
-        model.insert(1);
+        assert_eq!(cursor.remove_current(), None);
+
+        for value in removed {
+            model.slots[value] = None;
+        }
+
+        model.check();
+
+        let mut cursor = model.colony.cursor_back_mut();
+
+        while cursor.index().is_some() {
+            let value = *cursor.current().unwrap();
+            assert_eq!(cursor.replace_current(value * 2), Some(value));
+            cursor.move_prev();
+        }
+
+        for value in model.slots.iter_mut().flatten() {
+            *value *= 2;
+        }
 
         model.check();
     }
+
+    #[test]
+    fn cursor_move_to_and_insert() {
+        let mut colony = Colony::new();
+        let handles = colony.insert_many(0..10);
+        colony.remove(handles[5]);
+
+        let mut cursor = colony.cursor_front_mut();
+        assert!(cursor.move_to(handles[7]));
+        assert_eq!(cursor.handle(), Some(handles[7]));
+
+        assert!(!cursor.move_to(handles[5]));
+        assert_eq!(cursor.index(), Some(7));
+
+        let inserted = cursor.insert(100);
+        assert_eq!(inserted.index, 5);
+        assert_eq!(cursor.current(), Some(&mut 7));
+
+        cursor.move_prev();
+        cursor.move_prev();
+        assert_eq!(cursor.handle(), Some(inserted));
+
+        assert_eq!(cursor.replace_current(50), Some(100));
+        assert_eq!(colony[inserted], 50);
+
+        let mut empty = Colony::<i32>::new();
+        let mut cursor = empty.cursor_front_mut();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.replace_current(0), None);
+        cursor.move_prev();
+        assert_eq!(cursor.index(), None);
+    }
 }
@@ -0,0 +1,392 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+use std::iter::FusedIterator;
+
+use crate::{Handle, OrderedColony, OrderedIter};
+
+/// A least recently used cache whose entries are stored in a colony and identified by [`Handle`]s.
+///
+/// Entries are kept in an [`OrderedColony`] from most to least recently used, and a hash map finds them by key.
+/// When an insertion takes the cache over its capacity, the least recently used entry is evicted.
+///
+/// Entries can be looked up by key or by handle. Since handles are those of [`GenerationGuard`](crate::GenerationGuard),
+/// a handle held elsewhere becomes stale once its entry is evicted or removed,
+/// and is never valid for another entry, even one with the same key.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::LruColony;
+/// let mut cache = LruColony::new(2);
+/// let (foo, _) = cache.insert("foo", 1);
+/// cache.insert("bar", 2);
+///
+/// // Using foo makes bar the least recently used
+/// assert_eq!(cache.get("foo"), Some(&1));
+///
+/// let (_, evicted) = cache.insert("baz", 3);
+/// assert_eq!(evicted.unwrap().key, "bar");
+/// assert_eq!(cache.get_by_handle(foo), Some(&1));
+/// ```
+pub struct LruColony<K, V> {
+    entries: OrderedColony<Entry<K, V>>,
+    keys: HashMap<K, Handle>,
+    capacity: usize,
+}
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+}
+
+/// An entry removed from a [`LruColony`] to make room for another.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Evicted<K, V> {
+    /// The handle the entry had, which is now stale.
+    pub handle: Handle,
+    /// The key of the entry.
+    pub key: K,
+    /// The value of the entry.
+    pub value: V,
+}
+
+impl<K: Hash + Eq + Clone, V> LruColony<K, V> {
+    /// Constructs an empty cache which holds at most `capacity` entries.
+    ///
+    /// Does not allocate.
+    ///
+    /// # Panics
+    ///
+    /// If `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "an LRU colony must have a non-zero capacity");
+
+        Self {
+            entries: OrderedColony::new(),
+            keys: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the number of entries in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the maximum number of entries in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Inserts an entry as the most recently used, returning its handle.
+    ///
+    /// If the key is already present, the old entry is replaced and returned.
+    /// Otherwise, if the cache is full, the least recently used entry is evicted and returned.
+    /// Either way the handle of the returned entry becomes stale.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::LruColony;
+    /// let mut cache = LruColony::new(10);
+    /// let (old, _) = cache.insert("foo", 1);
+    /// let (new, replaced) = cache.insert("foo", 2);
+    ///
+    /// assert_eq!(replaced.unwrap().value, 1);
+    /// assert_eq!(cache.get_by_handle(old), None);
+    /// assert_eq!(cache.get_by_handle(new), Some(&2));
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> (Handle, Option<Evicted<K, V>>) {
+        let evicted = match self.keys.get(&key) {
+            Some(&handle) => self.remove_entry(handle),
+            None if self.len() == self.capacity => self.pop_lru(),
+            None => None,
+        };
+
+        let handle = self.entries.insert(Entry {
+            key: key.clone(),
+            value,
+        });
+        self.entries.move_to_front(handle);
+        self.keys.insert(key, handle);
+
+        (handle, evicted)
+    }
+
+    /// Returns the value of the entry with the given key, marking it as the most recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the given key,
+    /// marking it as the most recently used.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = *self.keys.get(key)?;
+        self.get_by_handle_mut(handle)
+    }
+
+    /// Returns the value of the entry with the given handle, marking it as the most recently used.
+    pub fn get_by_handle(&mut self, handle: Handle) -> Option<&V> {
+        self.get_by_handle_mut(handle).map(|value| &*value)
+    }
+
+    /// Returns a mutable reference to the value of the entry with the given handle,
+    /// marking it as the most recently used.
+    pub fn get_by_handle_mut(&mut self, handle: Handle) -> Option<&mut V> {
+        if !self.entries.move_to_front(handle) {
+            return None;
+        }
+
+        Some(&mut self.entries[handle].value)
+    }
+
+    /// Returns the value of the entry with the given key, without marking it as used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = *self.keys.get(key)?;
+        Some(&self.entries[handle].value)
+    }
+
+    /// Returns the value of the entry with the given handle, without marking it as used.
+    pub fn peek_by_handle(&self, handle: Handle) -> Option<&V> {
+        Some(&self.entries.get(handle)?.value)
+    }
+
+    /// Returns the handle of the entry with the given key, without marking it as used.
+    pub fn handle<Q>(&self, key: &Q) -> Option<Handle>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.get(key).copied()
+    }
+
+    /// Returns `true` if the cache contains an entry with the given key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.contains_key(key)
+    }
+
+    /// Returns the handle, key and value of the least recently used entry, which is the next to be evicted.
+    pub fn peek_lru(&self) -> Option<(Handle, &K, &V)> {
+        let (handle, entry) = self.entries.back()?;
+        Some((handle, &entry.key, &entry.value))
+    }
+
+    /// Removes the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<Evicted<K, V>> {
+        let (handle, Entry { key, value }) = self.entries.pop_back()?;
+        self.keys.remove(&key);
+
+        Some(Evicted { handle, key, value })
+    }
+
+    /// Removes the entry with the given key, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let handle = self.keys.remove(key)?;
+        Some(self.entries.remove(handle)?.value)
+    }
+
+    /// Removes the entry with the given handle, returning its key and value.
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<(K, V)> {
+        let Evicted { key, value, .. } = self.remove_entry(handle)?;
+        Some((key, value))
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.keys.clear();
+    }
+
+    /// Creates an iterator over the handles, keys and values of the entries, from most to least recently used.
+    ///
+    /// This does not mark any entry as used.
+    pub fn iter(&self) -> LruIter<'_, K, V> {
+        LruIter {
+            inner: self.entries.iter_ordered(),
+        }
+    }
+
+    fn remove_entry(&mut self, handle: Handle) -> Option<Evicted<K, V>> {
+        let Entry { key, value } = self.entries.remove(handle)?;
+        self.keys.remove(&key);
+
+        Some(Evicted { handle, key, value })
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for LruColony<K, V> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = self
+            .entries
+            .iter_ordered()
+            .map(|(_, entry)| (&entry.key, &entry.value));
+
+        f.debug_map().entries(iter).finish()
+    }
+}
+
+impl<'a, K: Hash + Eq + Clone, V> IntoIterator for &'a LruColony<K, V> {
+    type Item = (Handle, &'a K, &'a V);
+    type IntoIter = LruIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The iterator returned by [`LruColony::iter`].
+pub struct LruIter<'a, K, V> {
+    inner: OrderedIter<'a, Entry<K, V>>,
+}
+
+impl<'a, K, V> Iterator for LruIter<'a, K, V> {
+    type Item = (Handle, &'a K, &'a V);
+
+    fn next(&mut self) -> Option<(Handle, &'a K, &'a V)> {
+        let (handle, entry) = self.inner.next()?;
+        Some((handle, &entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for LruIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<(Handle, &'a K, &'a V)> {
+        let (handle, entry) = self.inner.next_back()?;
+        Some((handle, &entry.key, &entry.value))
+    }
+}
+
+impl<'a, K, V> FusedIterator for LruIter<'a, K, V> {}
+
+impl<'a, K, V> ExactSizeIterator for LruIter<'a, K, V> {}
+
+impl<'a, K, V> Clone for LruIter<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, K: Debug, V: Debug> Debug for LruIter<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::LruColony;
+    use crate::test_util::Random;
+
+    #[test]
+    fn matches_model() {
+        let mut random = Random::new(11);
+
+        let mut cache = LruColony::new(16);
+        // Keys and values from most to least recently used
+        let mut model: VecDeque<(usize, usize)> = VecDeque::new();
+        let mut stale = Vec::new();
+
+        for _ in 0..10_000 {
+            let key = random.below(32);
+            let position = model.iter().position(|&(k, _)| k == key);
+
+            match random.below(4) {
+                0 | 1 => {
+                    let value = random.below(1_000);
+                    let (_, evicted) = cache.insert(key, value);
+
+                    let expected = match position {
+                        Some(position) => model.remove(position),
+                        None if model.len() == 16 => model.pop_back(),
+                        None => None,
+                    };
+
+                    assert_eq!(evicted.as_ref().map(|e| (e.key, e.value)), expected);
+                    stale.extend(evicted.map(|evicted| evicted.handle));
+                    model.push_front((key, value));
+                }
+                2 => {
+                    let expected = position.map(|position| {
+                        let entry = model.remove(position).unwrap();
+                        model.push_front(entry);
+                        entry.1
+                    });
+
+                    assert_eq!(cache.get(&key).copied(), expected);
+                }
+                _ => {
+                    let handle = cache.handle(&key);
+                    let expected = position.map(|position| model.remove(position).unwrap());
+
+                    assert_eq!(
+                        handle.and_then(|handle| cache.remove_by_handle(handle)),
+                        expected
+                    );
+                    stale.extend(handle);
+                }
+            }
+
+            assert_eq!(cache.len(), model.len());
+        }
+
+        let entries: Vec<_> = cache.iter().map(|(_, &k, &v)| (k, v)).collect();
+        assert!(entries.iter().eq(model.iter()));
+
+        for handle in stale {
+            assert_eq!(cache.peek_by_handle(handle), None);
+        }
+    }
+
+    #[test]
+    fn peek_does_not_touch() {
+        let mut cache = LruColony::new(2);
+        let (foo, _) = cache.insert("foo", 1);
+        cache.insert("bar", 2);
+
+        assert_eq!(cache.peek("foo"), Some(&1));
+        assert_eq!(cache.peek_by_handle(foo), Some(&1));
+        assert_eq!(cache.peek_lru().map(|(_, &key, _)| key), Some("foo"));
+
+        let (_, evicted) = cache.insert("baz", 3);
+        let evicted = evicted.unwrap();
+        assert_eq!(evicted.handle, foo);
+        assert_eq!(cache.get_by_handle(foo), None);
+        assert!(!cache.contains_key("foo"));
+    }
+}
@@ -0,0 +1,586 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::{Index, IndexMut};
+
+use crate::guard::{CheckedGuard, Guard};
+use crate::index_opt::IndexOpt;
+use crate::{Colony, GenerationGuard, InterleavedStorage, Storage};
+
+/// A colony which remembers the order its elements were inserted in.
+///
+/// Iterating a [`Colony`] visits elements by index, and so in an unpredictable order once empty slots are reused.
+/// This colony also threads a doubly linked list through its elements,
+/// so that [`iter_ordered`](OrderedColony::iter_ordered) visits them in the order they were inserted.
+/// Elements can be moved within the order with [`move_to_front`](OrderedColony::move_to_front)
+/// and [`move_to_back`](OrderedColony::move_to_back), which makes this suitable for least recently used tracking.
+///
+/// Inserting, removing and moving elements are all `O(1)`.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::OrderedColony;
+/// let mut colony = OrderedColony::new();
+/// let foo = colony.insert("foo");
+/// colony.insert("bar");
+///
+/// // The slot freed by foo is reused, but baz is still last
+/// colony.remove(foo);
+/// colony.insert("baz");
+///
+/// let values: Vec<_> = colony.iter_ordered().map(|(_, &value)| value).collect();
+/// assert_eq!(values, ["bar", "baz"]);
+/// ```
+pub struct OrderedColony<T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: Colony<Node<T>, G, S>,
+    head: IndexOpt,
+    tail: IndexOpt,
+}
+
+struct Node<T> {
+    value: T,
+    prev: IndexOpt,
+    next: IndexOpt,
+}
+
+impl<T> OrderedColony<T> {
+    /// Constructs an empty ordered colony using [`GenerationGuard`].
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, G: Guard, S: Storage> OrderedColony<T, G, S> {
+    /// Returns the number of elements in the colony.
+    pub fn len(&self) -> usize {
+        self.colony.len()
+    }
+
+    /// Returns `true` if the colony contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.colony.is_empty()
+    }
+
+    /// Inserts an element at the back of the order, at an unspecified index.
+    ///
+    /// See [`Colony::insert`].
+    pub fn insert(&mut self, value: T) -> G::Handle {
+        let handle = self.colony.insert(Node {
+            value,
+            prev: IndexOpt::none(),
+            next: IndexOpt::none(),
+        });
+
+        unsafe {
+            self.link_back(G::__extract_index(&handle));
+        }
+
+        handle
+    }
+
+    /// Returns the handle and a reference to the first element in the order.
+    pub fn front(&self) -> Option<(G::Handle, &T)> {
+        unsafe { Some(self.entry(self.head.as_opt()?)) }
+    }
+
+    /// Returns the handle and a reference to the last element in the order.
+    pub fn back(&self) -> Option<(G::Handle, &T)> {
+        unsafe { Some(self.entry(self.tail.as_opt()?)) }
+    }
+
+    /// Removes the first element in the order, returning its handle and value.
+    pub fn pop_front(&mut self) -> Option<(G::Handle, T)> {
+        unsafe { Some(self.remove_index(self.head.as_opt()?)) }
+    }
+
+    /// Removes the last element in the order, returning its handle and value.
+    pub fn pop_back(&mut self) -> Option<(G::Handle, T)> {
+        unsafe { Some(self.remove_index(self.tail.as_opt()?)) }
+    }
+
+    /// Removes all elements from the colony.
+    ///
+    /// See [`Colony::clear`].
+    pub fn clear(&mut self) {
+        self.colony.clear();
+        self.head = IndexOpt::none();
+        self.tail = IndexOpt::none();
+    }
+
+    /// Creates an iterator over the handles and elements of the colony by reference, in order.
+    pub fn iter_ordered(&self) -> OrderedIter<'_, T, G, S> {
+        OrderedIter {
+            colony: &self.colony,
+            front: self.head,
+            back: self.tail,
+            remaining: self.len(),
+        }
+    }
+
+    /// Creates an iterator over the handles and elements of the colony by mutable reference, in order.
+    pub fn iter_ordered_mut(&mut self) -> OrderedIterMut<'_, T, G, S> {
+        OrderedIterMut {
+            front: self.head,
+            back: self.tail,
+            remaining: self.len(),
+            colony: &mut self.colony,
+        }
+    }
+
+    // Preconditions:
+    // * the slot at index is occupied
+    unsafe fn entry(&self, index: usize) -> (G::Handle, &T) {
+        let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+        (handle, &self.colony.occupied(index).value)
+    }
+
+    // Preconditions:
+    // * the slot at index is occupied and its node is not linked
+    unsafe fn link_back(&mut self, index: usize) {
+        let node = self.colony.occupied_mut(index);
+        node.prev = self.tail;
+        node.next = IndexOpt::none();
+
+        match self.tail.as_opt() {
+            Some(tail) => self.colony.occupied_mut(tail).next = IndexOpt::some(index),
+            None => self.head = IndexOpt::some(index),
+        }
+
+        self.tail = IndexOpt::some(index);
+    }
+
+    // Preconditions:
+    // * the slot at index is occupied and its node is not linked
+    unsafe fn link_front(&mut self, index: usize) {
+        let node = self.colony.occupied_mut(index);
+        node.prev = IndexOpt::none();
+        node.next = self.head;
+
+        match self.head.as_opt() {
+            Some(head) => self.colony.occupied_mut(head).prev = IndexOpt::some(index),
+            None => self.tail = IndexOpt::some(index),
+        }
+
+        self.head = IndexOpt::some(index);
+    }
+
+    // Preconditions:
+    // * the slot at index is occupied and its node is linked
+    unsafe fn unlink(&mut self, index: usize) {
+        let Node { prev, next, .. } = *self.colony.occupied(index);
+
+        match prev.as_opt() {
+            Some(prev) => self.colony.occupied_mut(prev).next = next,
+            None => self.head = next,
+        }
+
+        match next.as_opt() {
+            Some(next) => self.colony.occupied_mut(next).prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    // Preconditions:
+    // * the slot at index is occupied and its node is linked
+    unsafe fn remove_index(&mut self, index: usize) -> (G::Handle, T) {
+        let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+        self.unlink(index);
+        (handle, self.colony.remove_unchecked(index).value)
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> OrderedColony<T, G, S> {
+    /// Returns `true` if the colony contains an element with the given handle.
+    pub fn contains(&self, handle: G::Handle) -> bool {
+        self.colony.get(handle).is_some()
+    }
+
+    /// Returns a reference to the element with the given handle if it exists.
+    ///
+    /// See [`Colony::get`].
+    pub fn get(&self, handle: G::Handle) -> Option<&T> {
+        Some(&self.colony.get(handle)?.value)
+    }
+
+    /// Returns a mutable reference to the element with the given handle if it exists.
+    ///
+    /// See [`Colony::get_mut`].
+    pub fn get_mut(&mut self, handle: G::Handle) -> Option<&mut T> {
+        Some(&mut self.colony.get_mut(handle)?.value)
+    }
+
+    /// Removes the element with the given handle if it exists.
+    ///
+    /// See [`Colony::remove`].
+    pub fn remove(&mut self, handle: G::Handle) -> Option<T> {
+        let index = self.colony.checked_index(&handle)?;
+
+        unsafe {
+            self.unlink(index);
+            Some(self.colony.remove_unchecked(index).value)
+        }
+    }
+
+    /// Moves the element with the given handle to the front of the order, returning whether it exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::OrderedColony;
+    /// let mut colony = OrderedColony::new();
+    /// colony.insert("foo");
+    /// let bar = colony.insert("bar");
+    ///
+    /// assert!(colony.move_to_front(bar));
+    /// assert_eq!(colony.front(), Some((bar, &"bar")));
+    /// ```
+    pub fn move_to_front(&mut self, handle: G::Handle) -> bool {
+        let Some(index) = self.colony.checked_index(&handle) else {
+            return false;
+        };
+
+        unsafe {
+            self.unlink(index);
+            self.link_front(index);
+        }
+
+        true
+    }
+
+    /// Moves the element with the given handle to the back of the order, returning whether it exists.
+    ///
+    /// See [`move_to_front`](OrderedColony::move_to_front).
+    pub fn move_to_back(&mut self, handle: G::Handle) -> bool {
+        let Some(index) = self.colony.checked_index(&handle) else {
+            return false;
+        };
+
+        unsafe {
+            self.unlink(index);
+            self.link_back(index);
+        }
+
+        true
+    }
+}
+
+impl<T, G: Guard, S: Storage> Default for OrderedColony<T, G, S> {
+    fn default() -> Self {
+        Self {
+            colony: Colony::default(),
+            head: IndexOpt::none(),
+            tail: IndexOpt::none(),
+        }
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> Index<G::Handle> for OrderedColony<T, G, S> {
+    type Output = T;
+
+    fn index(&self, index: G::Handle) -> &T {
+        self.get(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> IndexMut<G::Handle> for OrderedColony<T, G, S> {
+    fn index_mut(&mut self, index: G::Handle) -> &mut T {
+        self.get_mut(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T, G: Guard, S: Storage> Extend<T> for OrderedColony<T, G, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T, G: Guard, S: Storage> FromIterator<T> for OrderedColony<T, G, S> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::default();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T: Debug, G: Guard, S: Storage> Debug for OrderedColony<T, G, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = self.iter_ordered().map(|(_, value)| value);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> IntoIterator for &'a OrderedColony<T, G, S> {
+    type Item = (G::Handle, &'a T);
+    type IntoIter = OrderedIter<'a, T, G, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_ordered()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> IntoIterator for &'a mut OrderedColony<T, G, S> {
+    type Item = (G::Handle, &'a mut T);
+    type IntoIter = OrderedIterMut<'a, T, G, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_ordered_mut()
+    }
+}
+
+/// The iterator returned by [`OrderedColony::iter_ordered`].
+pub struct OrderedIter<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: &'a Colony<Node<T>, G, S>,
+    front: IndexOpt,
+    back: IndexOpt,
+    remaining: usize,
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for OrderedIter<'a, T, G, S> {
+    type Item = (G::Handle, &'a T);
+
+    fn next(&mut self) -> Option<(G::Handle, &'a T)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        unsafe {
+            // The list only links occupied slots
+            let index = self.front.as_opt().unwrap_unchecked();
+            let node = self.colony.occupied(index);
+            self.front = node.next;
+
+            let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+            Some((handle, &node.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> DoubleEndedIterator for OrderedIter<'a, T, G, S> {
+    fn next_back(&mut self) -> Option<(G::Handle, &'a T)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        unsafe {
+            let index = self.back.as_opt().unwrap_unchecked();
+            let node = self.colony.occupied(index);
+            self.back = node.prev;
+
+            let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+            Some((handle, &node.value))
+        }
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> FusedIterator for OrderedIter<'a, T, G, S> {}
+
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for OrderedIter<'a, T, G, S> {}
+
+impl<'a, T, G: Guard, S: Storage> Clone for OrderedIter<'a, T, G, S> {
+    fn clone(&self) -> Self {
+        Self {
+            colony: self.colony,
+            front: self.front,
+            back: self.back,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for OrderedIter<'a, T, G, S>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// The iterator returned by [`OrderedColony::iter_ordered_mut`].
+pub struct OrderedIterMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: &'a mut Colony<Node<T>, G, S>,
+    front: IndexOpt,
+    back: IndexOpt,
+    remaining: usize,
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for OrderedIterMut<'a, T, G, S> {
+    type Item = (G::Handle, &'a mut T);
+
+    fn next(&mut self) -> Option<(G::Handle, &'a mut T)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        unsafe {
+            let index = self.front.as_opt().unwrap_unchecked();
+            let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+
+            // Each node is yielded at most once, so the references never alias
+            let node = &mut *(self.colony.occupied_mut(index) as *mut Node<T>);
+            self.front = node.next;
+
+            Some((handle, &mut node.value))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> DoubleEndedIterator for OrderedIterMut<'a, T, G, S> {
+    fn next_back(&mut self) -> Option<(G::Handle, &'a mut T)> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        unsafe {
+            let index = self.back.as_opt().unwrap_unchecked();
+            let handle = G::__new_handle(self.colony.guard(index), index, self.colony.id);
+
+            let node = &mut *(self.colony.occupied_mut(index) as *mut Node<T>);
+            self.back = node.prev;
+
+            Some((handle, &mut node.value))
+        }
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> FusedIterator for OrderedIterMut<'a, T, G, S> {}
+
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for OrderedIterMut<'a, T, G, S> {}
+
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for OrderedIterMut<'a, T, G, S>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = OrderedIter {
+            colony: &*self.colony,
+            front: self.front,
+            back: self.back,
+            remaining: self.remaining,
+        };
+
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use super::OrderedColony;
+    use crate::test_util::Random;
+    use crate::{FlagGuard, Handle};
+
+    #[test]
+    fn matches_model() {
+        let mut random = Random::new(5);
+
+        let mut colony = OrderedColony::new();
+        let mut model: VecDeque<(Handle, usize)> = VecDeque::new();
+
+        for _ in 0..5_000 {
+            let position = (!model.is_empty()).then(|| random.below(model.len()));
+
+            match (random.below(6), position) {
+                (0 | 1, _) | (_, None) => {
+                    let value = random.below(1_000);
+                    model.push_back((colony.insert(value), value));
+                }
+                (2, Some(position)) => {
+                    let (handle, value) = model.remove(position).unwrap();
+                    assert_eq!(colony.remove(handle), Some(value));
+                    assert_eq!(colony.remove(handle), None);
+                    assert!(!colony.move_to_front(handle));
+                }
+                (3, Some(position)) => {
+                    let entry = model.remove(position).unwrap();
+                    assert!(colony.move_to_front(entry.0));
+                    model.push_front(entry);
+                }
+                (4, Some(position)) => {
+                    let entry = model.remove(position).unwrap();
+                    assert!(colony.move_to_back(entry.0));
+                    model.push_back(entry);
+                }
+                (_, Some(_)) => {
+                    if random.below(2) == 0 {
+                        assert_eq!(colony.pop_front(), model.pop_front());
+                    } else {
+                        assert_eq!(colony.pop_back(), model.pop_back());
+                    }
+                }
+            }
+
+            assert_eq!(colony.len(), model.len());
+            assert_eq!(
+                colony.front(),
+                model.front().map(|(handle, value)| (*handle, value))
+            );
+            assert_eq!(
+                colony.back(),
+                model.back().map(|(handle, value)| (*handle, value))
+            );
+        }
+
+        let forwards: Vec<_> = colony
+            .iter_ordered()
+            .map(|(handle, &value)| (handle, value))
+            .collect();
+        assert_eq!(forwards, Vec::from(model.clone()));
+
+        let backwards: Vec<_> = colony
+            .iter_ordered()
+            .rev()
+            .map(|(handle, &value)| (handle, value))
+            .collect();
+        assert!(backwards.iter().eq(model.iter().rev()));
+
+        for (_, value) in colony.iter_ordered_mut() {
+            *value += 1;
+        }
+
+        for (handle, value) in &model {
+            assert_eq!(colony[*handle], value + 1);
+        }
+
+        colony.clear();
+        assert_eq!(colony.iter_ordered().len(), 0);
+        assert_eq!(colony.front(), None);
+    }
+
+    #[test]
+    fn meeting_in_the_middle() {
+        let mut colony: OrderedColony<_, FlagGuard> = (0..5).collect();
+        let mut iter = colony.iter_ordered_mut();
+
+        assert_eq!(iter.next().map(|(_, value)| *value), Some(0));
+        assert_eq!(iter.next_back().map(|(_, value)| *value), Some(4));
+        assert_eq!(iter.len(), 3);
+
+        let rest: Vec<_> = iter.map(|(_, value)| *value).collect();
+        assert_eq!(rest, [1, 2, 3]);
+    }
+}
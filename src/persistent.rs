@@ -0,0 +1,787 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::{Index, IndexMut};
+use std::slice;
+use std::sync::Arc;
+
+use crate::guard::{CheckedGuard, Guard};
+#[cfg(doc)]
+use crate::Colony;
+use crate::{GenerationGuard, Handle};
+
+const PAGE_BITS: u32 = 6;
+const PAGE_SIZE: usize = 1 << PAGE_BITS;
+
+const FANOUT_BITS: u32 = 6;
+const FANOUT: usize = 1 << FANOUT_BITS;
+
+/// A colony whose snapshots share storage, for keeping many versions of a colony cheaply (such as for undo history).
+///
+/// The slots are stored in fixed-size pages, which are the leaves of a tree of [`Arc`]s.
+/// Taking a [`snapshot`](PersistentColony::snapshot) only shares the root of the tree, which is `O(1)`.
+/// A node is copied when it is first modified while shared, so modifying an element copies at most one page
+/// and the nodes on the path to it, which is `O(log n)`, and unchanged pages are never copied.
+///
+/// Unlike [`Colony`], this does not use a skipfield, and always uses [`GenerationGuard`].
+/// Instead, each node of the tree counts its elements, so iteration skips pages and branches without any.
+///
+/// Handles are [`Handle`]s, and unlike with [`Colony::clone`] they are kept by snapshots:
+/// a handle works with every snapshot in which its element is alive.
+/// Each snapshot is given a new identity for the elements inserted into it,
+/// so snapshots which are modified independently never give the same handle to different elements.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::PersistentColony;
+/// let mut colony = PersistentColony::new();
+/// let foo = colony.insert("foo");
+///
+/// let snapshot = colony.snapshot();
+/// colony[foo] = "bar";
+/// let baz = colony.insert("baz");
+///
+/// assert_eq!(snapshot[foo], "foo");
+/// assert_eq!(snapshot.get(baz), None);
+/// assert_eq!(colony[foo], "bar");
+/// ```
+pub struct PersistentColony<T> {
+    // None until the first insertion
+    root: Option<Arc<Node<T>>>,
+    // The number of levels of branches above the pages
+    depth: u32,
+    // Given to the slots filled by this version, or the sentinel until one is
+    id: u64,
+    len: usize,
+    touched: usize,
+    // The head of a linked list of empty slots which can be reused
+    next_free: Option<usize>,
+}
+
+#[derive(Clone)]
+struct Node<T> {
+    // The number of occupied slots below the node
+    len: usize,
+    kind: NodeKind<T>,
+}
+
+#[derive(Clone)]
+enum NodeKind<T> {
+    // Up to FANOUT children, each a branch one level down or a page if this is the lowest level
+    Branch(Vec<Arc<Node<T>>>),
+    // Up to PAGE_SIZE slots
+    Page(Vec<Slot<T>>),
+}
+
+#[derive(Clone)]
+struct Slot<T> {
+    guard: GenerationGuard,
+    // The identity of the version which last filled the slot, shared by the handles to its element
+    id: u64,
+    value: Value<T>,
+}
+
+#[derive(Clone)]
+enum Value<T> {
+    Occupied(T),
+    Empty { next_free: Option<usize> },
+}
+
+impl<T> PersistentColony<T> {
+    /// Constructs an empty persistent colony.
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            root: None,
+            depth: 0,
+            id: GenerationGuard::__sentinel_id(),
+            len: 0,
+            touched: 0,
+            next_free: None,
+        }
+    }
+
+    /// Returns a snapshot of the colony, sharing all of its storage.
+    ///
+    /// This is an `O(1)` operation. It is equivalent to [`clone`](Clone::clone).
+    /// Elements inserted into the snapshot are given handles of a new identity, distinct from those of the colony.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::PersistentColony;
+    /// let mut colony = PersistentColony::new();
+    /// let mut history = Vec::new();
+    ///
+    /// for i in 0..3 {
+    ///     history.push(colony.snapshot());
+    ///     colony.insert(i);
+    /// }
+    ///
+    /// let lengths: Vec<_> = history.iter().map(PersistentColony::len).collect();
+    /// assert_eq!(lengths, [0, 1, 2]);
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Returns the number of elements in the colony.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the colony contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the colony contains an element with the given handle.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Returns a reference to the element with the given handle if it exists.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        if handle.index >= self.touched {
+            return None;
+        }
+
+        let slot = self.slot(handle.index);
+
+        match &slot.value {
+            Value::Occupied(value) if slot.guard.__check(&handle, slot.id) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Creates an iterator over the handles and elements of the colony by reference, in index order.
+    ///
+    /// Pages and branches of the tree without any elements are skipped.
+    pub fn iter(&self) -> PersistentIter<'_, T> {
+        let mut iter = PersistentIter {
+            branches: Vec::new(),
+            slots: [].iter(),
+            depth: self.depth,
+            index: 0,
+            remaining: self.len,
+        };
+
+        if let Some(root) = &self.root {
+            match &root.kind {
+                NodeKind::Branch(children) => iter.branches.push(children.iter()),
+                NodeKind::Page(slots) => iter.slots = slots.iter(),
+            }
+        }
+
+        iter
+    }
+
+    /// Removes all elements from the colony, leaving any snapshots untouched.
+    ///
+    /// Handles to elements of the colony become invalid, as with [`Colony::clear`].
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // Preconditions:
+    // * index < touched
+    fn slot(&self, index: usize) -> &Slot<T> {
+        let mut node = self.root.as_deref().unwrap();
+
+        for level in (0..self.depth).rev() {
+            let NodeKind::Branch(children) = &node.kind else {
+                unreachable!("page above the lowest level");
+            };
+
+            node = &children[child_index(index, level)];
+        }
+
+        let NodeKind::Page(slots) = &node.kind else {
+            unreachable!("branch at the lowest level");
+        };
+
+        &slots[index % PAGE_SIZE]
+    }
+
+    fn new_handle(&self, index: usize) -> Handle {
+        // Only called for occupied slots, which have been given an identity when filled
+        let slot = self.slot(index);
+        unsafe { slot.guard.__new_handle(index, slot.id) }
+    }
+}
+
+impl<T: Clone> PersistentColony<T> {
+    /// Inserts an element into the colony at an unspecified index.
+    ///
+    /// This copies the page the element is placed in if it is shared with a snapshot.
+    ///
+    /// # Panics
+    ///
+    /// This method creates a unique ID for the colony upon the first insertion after it is created, cleared
+    /// or snapshotted, and may panic if all available IDs have been exhausted.
+    pub fn insert(&mut self, value: T) -> Handle {
+        if self.id == GenerationGuard::__sentinel_id() {
+            self.id = GenerationGuard::__new_id();
+        }
+
+        let id = self.id;
+
+        let index = if let Some(index) = self.next_free {
+            let slot = self.slot_mut(index, 1);
+            let Value::Empty { next_free } = mem::replace(&mut slot.value, Value::Occupied(value))
+            else {
+                unreachable!("occupied slot in the freelist");
+            };
+
+            // Only empty slots are in the freelist
+            unsafe {
+                slot.guard.__fill();
+            }
+
+            slot.id = id;
+            self.next_free = next_free;
+            index
+        } else {
+            let index = self.touched;
+
+            // Add a level above the root once the tree is full
+            let capacity = PAGE_SIZE << (self.depth * FANOUT_BITS);
+            if index == capacity {
+                let root = self.root.take().unwrap();
+                self.root = Some(Arc::new(Node {
+                    len: root.len,
+                    kind: NodeKind::Branch(vec![root]),
+                }));
+                self.depth += 1;
+            }
+
+            let mut node = self.root.get_or_insert_with(|| Arc::new(Node::page()));
+
+            for level in (0..self.depth).rev() {
+                let node_mut = Arc::make_mut(node);
+                node_mut.len += 1;
+
+                let NodeKind::Branch(children) = &mut node_mut.kind else {
+                    unreachable!("page above the lowest level");
+                };
+
+                // The new slot is always the last, so any missing node is the next child
+                if child_index(index, level) == children.len() {
+                    children.push(Arc::new(if level == 0 {
+                        Node::page()
+                    } else {
+                        Node::branch()
+                    }));
+                }
+
+                node = children.last_mut().unwrap();
+            }
+
+            let node = Arc::make_mut(node);
+            node.len += 1;
+
+            let NodeKind::Page(slots) = &mut node.kind else {
+                unreachable!("branch at the lowest level");
+            };
+
+            slots.push(Slot {
+                guard: GenerationGuard::__new(),
+                id,
+                value: Value::Occupied(value),
+            });
+
+            self.touched += 1;
+            index
+        };
+
+        self.len += 1;
+        self.new_handle(index)
+    }
+
+    /// Returns a mutable reference to the element with the given handle if it exists.
+    ///
+    /// This copies the page the element is in if it is shared with a snapshot.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        if !self.contains(handle) {
+            return None;
+        }
+
+        match &mut self.slot_mut(handle.index, 0).value {
+            Value::Occupied(value) => Some(value),
+            Value::Empty { .. } => None,
+        }
+    }
+
+    /// Removes the element with the given handle if it exists.
+    ///
+    /// This copies the page the element is in if it is shared with a snapshot,
+    /// in which case the element returned is a clone.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+
+        let next_free = self.next_free;
+        let slot = self.slot_mut(handle.index, -1);
+
+        // The slot is occupied, since the handle is valid
+        let reuse = unsafe { slot.guard.__empty() };
+
+        // A slot which has run out of generations is never reused
+        let value = mem::replace(
+            &mut slot.value,
+            Value::Empty {
+                next_free: next_free.filter(|_| reuse),
+            },
+        );
+
+        if reuse {
+            self.next_free = Some(handle.index);
+        }
+
+        self.len -= 1;
+
+        match value {
+            Value::Occupied(value) => Some(value),
+            Value::Empty { .. } => unreachable!("removed an empty slot"),
+        }
+    }
+
+    // Copies the nodes on the path to the slot which are shared with a snapshot,
+    // adding len_change to the number of elements counted by each of them
+    // Preconditions:
+    // * index < touched
+    fn slot_mut(&mut self, index: usize, len_change: isize) -> &mut Slot<T> {
+        let mut node = Arc::make_mut(self.root.as_mut().unwrap());
+
+        for level in (0..self.depth).rev() {
+            node.len = node.len.wrapping_add_signed(len_change);
+
+            let NodeKind::Branch(children) = &mut node.kind else {
+                unreachable!("page above the lowest level");
+            };
+
+            node = Arc::make_mut(&mut children[child_index(index, level)]);
+        }
+
+        node.len = node.len.wrapping_add_signed(len_change);
+
+        let NodeKind::Page(slots) = &mut node.kind else {
+            unreachable!("branch at the lowest level");
+        };
+
+        &mut slots[index % PAGE_SIZE]
+    }
+}
+
+impl<T> Node<T> {
+    fn page() -> Self {
+        Self {
+            len: 0,
+            kind: NodeKind::Page(Vec::with_capacity(PAGE_SIZE)),
+        }
+    }
+
+    fn branch() -> Self {
+        Self {
+            len: 0,
+            kind: NodeKind::Branch(Vec::with_capacity(FANOUT)),
+        }
+    }
+}
+
+// The index of the child containing a slot, within a branch the given number of levels above the lowest
+fn child_index(index: usize, level: u32) -> usize {
+    (index >> (PAGE_BITS + level * FANOUT_BITS)) % FANOUT
+}
+
+impl<T> Clone for PersistentColony<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            depth: self.depth,
+            // Given a new identity on its first insertion, so the versions never share handles to new elements
+            id: GenerationGuard::__sentinel_id(),
+            len: self.len,
+            touched: self.touched,
+            next_free: self.next_free,
+        }
+    }
+}
+
+impl<T> Default for PersistentColony<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Handle> for PersistentColony<T> {
+    type Output = T;
+
+    fn index(&self, index: Handle) -> &T {
+        self.get(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T: Clone> IndexMut<Handle> for PersistentColony<T> {
+    fn index_mut(&mut self, index: Handle) -> &mut T {
+        self.get_mut(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T: Clone> Extend<T> for PersistentColony<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentColony<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut result = Self::new();
+        result.extend(iter);
+        result
+    }
+}
+
+impl<T: Debug> Debug for PersistentColony<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = self.iter().map(|(_, value)| value);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a PersistentColony<T> {
+    type Item = (Handle, &'a T);
+    type IntoIter = PersistentIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The iterator returned by [`PersistentColony::iter`].
+pub struct PersistentIter<'a, T> {
+    // The children left to visit of each branch on the path to the current page, from the root down
+    branches: Vec<slice::Iter<'a, Arc<Node<T>>>>,
+    // The slots left to visit in the current page
+    slots: slice::Iter<'a, Slot<T>>,
+    depth: u32,
+    // The index of the next slot
+    index: usize,
+    remaining: usize,
+}
+
+impl<'a, T> PersistentIter<'a, T> {
+    // Moves on to the next page with any elements
+    // Preconditions:
+    // * remaining > 0, and the current page has no elements left
+    fn next_page(&mut self) {
+        loop {
+            // The number of slots spanned by each child of the lowest branch
+            let levels = self.depth - self.branches.len() as u32;
+            let span = PAGE_SIZE << (levels * FANOUT_BITS);
+
+            let Some(child) = self.branches.last_mut().unwrap().next() else {
+                self.branches.pop();
+                continue;
+            };
+
+            if child.len == 0 {
+                self.index += span;
+                continue;
+            }
+
+            match &child.kind {
+                NodeKind::Branch(children) => self.branches.push(children.iter()),
+                NodeKind::Page(slots) => {
+                    self.slots = slots.iter();
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for PersistentIter<'a, T> {
+    type Item = (Handle, &'a T);
+
+    fn next(&mut self) -> Option<(Handle, &'a T)> {
+        while self.remaining > 0 {
+            let Some(slot) = self.slots.next() else {
+                self.next_page();
+                continue;
+            };
+
+            let index = self.index;
+            self.index += 1;
+
+            if let Value::Occupied(value) = &slot.value {
+                self.remaining -= 1;
+
+                // Occupied slots have been given an identity when filled
+                let handle = unsafe { slot.guard.__new_handle(index, slot.id) };
+                return Some((handle, value));
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> FusedIterator for PersistentIter<'a, T> {}
+
+impl<'a, T> ExactSizeIterator for PersistentIter<'a, T> {}
+
+impl<'a, T> Clone for PersistentIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            branches: self.branches.clone(),
+            slots: self.slots.clone(),
+            depth: self.depth,
+            index: self.index,
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T: Debug> Debug for PersistentIter<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{Node, NodeKind, PersistentColony, FANOUT, PAGE_SIZE};
+    use crate::test_util::{Random, Step};
+
+    // Returns every node of the tree, in depth-first order
+    fn nodes<T>(colony: &PersistentColony<T>) -> Vec<&Arc<Node<T>>> {
+        fn visit<'a, T>(node: &'a Arc<Node<T>>, nodes: &mut Vec<&'a Arc<Node<T>>>) {
+            nodes.push(node);
+
+            if let NodeKind::Branch(children) = &node.kind {
+                for child in children {
+                    visit(child, nodes);
+                }
+            }
+        }
+
+        let mut nodes = Vec::new();
+        visit(colony.root.as_ref().unwrap(), &mut nodes);
+        nodes
+    }
+
+    fn pages<T>(colony: &PersistentColony<T>) -> Vec<&Arc<Node<T>>> {
+        let mut pages = nodes(colony);
+        pages.retain(|node| matches!(node.kind, NodeKind::Page(_)));
+        pages
+    }
+
+    fn shared_pages<T>(a: &PersistentColony<T>, b: &PersistentColony<T>) -> usize {
+        let (a, b) = (pages(a), pages(b));
+        a.iter().zip(b).filter(|(a, b)| Arc::ptr_eq(a, b)).count()
+    }
+
+    // Counts the nodes of a which are not also nodes of b
+    fn copied_nodes<T>(a: &PersistentColony<T>, b: &PersistentColony<T>) -> usize {
+        let b = nodes(b);
+        let shared = |node: &&Arc<Node<T>>| b.iter().any(|other| Arc::ptr_eq(node, other));
+        nodes(a).iter().filter(|node| !shared(node)).count()
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut random = Random::new(3);
+
+        let mut colony = PersistentColony::new();
+        let mut handles = Vec::new();
+        let mut history = Vec::new();
+
+        for step in 0..5_000 {
+            match random.step(&mut handles) {
+                Step::Insert(value) => handles.push(colony.insert(value)),
+                Step::Remove(handle) => {
+                    assert!(colony.remove(handle).is_some());
+                    assert_eq!(colony.remove(handle), None);
+                }
+                Step::Change(handle, value) => colony[handle] = value,
+            }
+
+            if step % 100 == 0 {
+                let contents: Vec<_> = colony
+                    .iter()
+                    .map(|(handle, &value)| (handle, value))
+                    .collect();
+                assert_eq!(contents.len(), handles.len());
+                history.push((colony.snapshot(), contents));
+            }
+        }
+
+        // Later modifications never show through earlier snapshots
+        for (snapshot, contents) in &history {
+            assert_eq!(snapshot.len(), contents.len());
+
+            let actual: Vec<_> = snapshot
+                .iter()
+                .map(|(handle, &value)| (handle, value))
+                .collect();
+            assert_eq!(&actual, contents);
+
+            for &(handle, value) in contents {
+                assert_eq!(snapshot.get(handle), Some(&value));
+            }
+        }
+    }
+
+    #[test]
+    fn copy_on_write() {
+        let mut colony: PersistentColony<_> = (0..PAGE_SIZE * 4).collect();
+        let handles: Vec<_> = colony.iter().map(|(handle, _)| handle).collect();
+
+        let snapshot = colony.snapshot();
+        assert_eq!(copied_nodes(&colony, &snapshot), 0);
+
+        colony[handles[PAGE_SIZE + 1]] = 0;
+        assert_eq!(shared_pages(&colony, &snapshot), 3);
+
+        colony.remove(handles[PAGE_SIZE + 2]);
+        colony[handles[2 * PAGE_SIZE]] = 0;
+        assert_eq!(shared_pages(&colony, &snapshot), 2);
+
+        // The freed slot is reused in the copied page
+        let handle = colony.insert(1);
+        assert_eq!(handle.index, PAGE_SIZE + 2);
+        assert_eq!(shared_pages(&colony, &snapshot), 2);
+
+        assert_eq!(snapshot[handles[PAGE_SIZE + 1]], PAGE_SIZE + 1);
+        assert_eq!(snapshot[handles[PAGE_SIZE + 2]], PAGE_SIZE + 2);
+        assert_eq!(snapshot.get(handle), None);
+        assert_eq!(colony.get(handles[PAGE_SIZE + 2]), None);
+    }
+
+    #[test]
+    fn bounded_copying() {
+        // Large enough for three levels of branches
+        let len = PAGE_SIZE * FANOUT * FANOUT + 1;
+        let mut colony: PersistentColony<_> = (0..len).collect();
+        let handles: Vec<_> = colony.iter().map(|(handle, _)| handle).collect();
+        assert_eq!(colony.depth, 3);
+
+        // Each write after a snapshot copies one page and the branches above it, however large the colony
+        for &index in &[0, PAGE_SIZE * FANOUT + 5, len - 1] {
+            let snapshot = colony.snapshot();
+            colony[handles[index]] += 1;
+            assert_eq!(copied_nodes(&colony, &snapshot), colony.depth as usize + 1);
+
+            // Writing to the same page again copies nothing more
+            colony[handles[index - index % PAGE_SIZE]] += 1;
+            assert_eq!(copied_nodes(&colony, &snapshot), colony.depth as usize + 1);
+            assert_eq!(snapshot[handles[index]], index);
+        }
+
+        // As does growing the tree
+        let snapshot = colony.snapshot();
+        colony.insert(0);
+        assert_eq!(copied_nodes(&colony, &snapshot), colony.depth as usize + 1);
+    }
+
+    #[test]
+    fn diverging_snapshots() {
+        let mut colony = PersistentColony::new();
+        let foo = colony.insert("foo");
+        let bar = colony.insert("bar");
+        colony.remove(bar);
+
+        let mut first = colony.snapshot();
+        let mut second = colony.snapshot();
+
+        // Each version reuses the same slot for a different element
+        let handles = [
+            colony.insert("baz"),
+            first.insert("qux"),
+            second.insert("quux"),
+        ];
+
+        assert!(handles.iter().all(|handle| handle.index == bar.index));
+        assert_ne!(handles[0], handles[1]);
+        assert_ne!(handles[0], handles[2]);
+        assert_ne!(handles[1], handles[2]);
+
+        assert_eq!(first.get(handles[0]), None);
+        assert_eq!(first.get(handles[2]), None);
+        assert_eq!(second.get(handles[1]), None);
+        assert_eq!(colony.get(handles[1]), None);
+
+        // Elements from before the snapshots keep their handles in every version
+        for version in [&colony, &first, &second] {
+            assert_eq!(version[foo], "foo");
+            assert_eq!(version.iter().next(), Some((foo, &"foo")));
+        }
+    }
+
+    #[test]
+    fn iter_skips_empty_pages() {
+        let len = PAGE_SIZE * FANOUT * 2;
+        let mut colony: PersistentColony<_> = (0..len).collect();
+        let handles: Vec<_> = colony.iter().map(|(handle, _)| handle).collect();
+
+        // Empty all but a few slots, leaving whole pages and branches without elements
+        let kept = [3, PAGE_SIZE * 5 + 1, PAGE_SIZE * FANOUT + 7, len - 1];
+        let snapshot = colony.snapshot();
+
+        for (index, &handle) in handles.iter().enumerate() {
+            if !kept.contains(&index) {
+                colony.remove(handle);
+            }
+        }
+
+        assert_eq!(colony.root.as_ref().unwrap().len, kept.len());
+        let actual: Vec<_> = colony
+            .iter()
+            .map(|(handle, &value)| (handle, value))
+            .collect();
+        let expected: Vec<_> = kept.iter().map(|&index| (handles[index], index)).collect();
+        assert_eq!(actual, expected);
+        assert_eq!(colony.iter().len(), kept.len());
+
+        // The snapshot still counts every element
+        assert_eq!(snapshot.root.as_ref().unwrap().len, len);
+        assert!(snapshot.iter().map(|(_, &value)| value).eq(0..len));
+
+        // Emptied pages are filled again from the freelist
+        colony.extend(0..10);
+        assert_eq!(colony.iter().len(), kept.len() + 10);
+        assert_eq!(colony.root.as_ref().unwrap().len, kept.len() + 10);
+    }
+
+    #[test]
+    fn clear() {
+        let mut colony = PersistentColony::new();
+        let foo = colony.insert("foo");
+        let snapshot = colony.snapshot();
+
+        colony.clear();
+        assert!(colony.is_empty());
+        assert_eq!(colony.get(foo), None);
+
+        let bar = colony.insert("bar");
+        assert_eq!(bar.index, foo.index);
+        assert_eq!(colony.get(foo), None);
+        assert_eq!(snapshot.get(bar), None);
+        assert_eq!(snapshot[foo], "foo");
+    }
+}
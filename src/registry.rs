@@ -0,0 +1,295 @@
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+
+use crate::{Colony, ColonyId, GenerationGuard, Handle, Storage};
+
+/// A map from colony identities to values, for routing handles from many colonies to the right place.
+///
+/// The values are usually the colonies themselves, in which case elements can be looked up directly by handle
+/// (see [`register`](ColonyRegistry::register)), but they can be anything identifying a colony,
+/// such as an index into a list of colonies stored elsewhere.
+///
+/// A colony registered with [`register`](ColonyRegistry::register) keeps its identity from then on:
+/// [clearing](Colony::clear) it keeps its identity, and [`apply_delta`](Colony::apply_delta) rejects deltas which would change it.
+/// The registry does not notice when a colony is replaced by another, such as by assigning through [`get_mut`](ColonyRegistry::get_mut),
+/// and keeps the new colony under the old identity.
+/// Handles to its elements are then not routed to it until it is registered again (see [`reregister`](ColonyRegistry::reregister)).
+///
+/// # Examples
+///
+/// ```
+/// # use colony::{Colony, ColonyRegistry};
+/// let mut registry = ColonyRegistry::new();
+/// let foo = registry.register(Colony::new());
+/// let bar = registry.register(Colony::new());
+///
+/// let a = registry.get_mut(foo).unwrap().insert("a");
+/// let b = registry.get_mut(bar).unwrap().insert("b");
+///
+/// // Each handle is routed to the colony that created it
+/// assert_eq!(registry.element(a), Some(&"a"));
+/// assert_eq!(registry.element(b), Some(&"b"));
+/// ```
+pub struct ColonyRegistry<V> {
+    entries: HashMap<ColonyId, V>,
+}
+
+impl<V> ColonyRegistry<V> {
+    /// Constructs an empty registry.
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of colonies registered.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no colonies are registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Associates a value with a colony identity, returning the value previously associated with it.
+    pub fn insert(&mut self, id: ColonyId, value: V) -> Option<V> {
+        self.entries.insert(id, value)
+    }
+
+    /// Removes the value associated with a colony identity, returning it.
+    pub fn remove(&mut self, id: ColonyId) -> Option<V> {
+        self.entries.remove(&id)
+    }
+
+    /// Returns a reference to the value associated with a colony identity.
+    pub fn get(&self, id: ColonyId) -> Option<&V> {
+        self.entries.get(&id)
+    }
+
+    /// Returns a mutable reference to the value associated with a colony identity.
+    pub fn get_mut(&mut self, id: ColonyId) -> Option<&mut V> {
+        self.entries.get_mut(&id)
+    }
+
+    /// Returns a reference to the value associated with the colony that created a handle.
+    pub fn route(&self, handle: Handle) -> Option<&V> {
+        self.get(handle.colony_id())
+    }
+
+    /// Returns a mutable reference to the value associated with the colony that created a handle.
+    pub fn route_mut(&mut self, handle: Handle) -> Option<&mut V> {
+        self.get_mut(handle.colony_id())
+    }
+
+    /// Creates an iterator over the colony identities and their values, in an unspecified order.
+    pub fn iter(&self) -> hash_map::Iter<'_, ColonyId, V> {
+        self.entries.iter()
+    }
+}
+
+impl<T, S: Storage> ColonyRegistry<Colony<T, GenerationGuard, S>> {
+    /// Registers a colony under its identity, returning the identity.
+    ///
+    /// A colony that has not allocated yet has no identity, so it is given one now.
+    /// The colony keeps its identity from then on, even if it is removed from the registry.
+    /// Clearing it empties its slots as [`remove`](Colony::remove) would, rather than giving it a new identity,
+    /// and [`apply_delta`](Colony::apply_delta) rejects deltas which replace every element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, ColonyRegistry};
+    /// let mut registry = ColonyRegistry::new();
+    /// let id = registry.register(Colony::new());
+    ///
+    /// let foo = registry.get_mut(id).unwrap().insert("foo");
+    /// registry.get_mut(id).unwrap().clear();
+    /// let bar = registry.get_mut(id).unwrap().insert("bar");
+    ///
+    /// assert_eq!(bar.colony_id(), id);
+    /// assert_eq!(registry.element(foo), None);
+    /// assert_eq!(registry.element(bar), Some(&"bar"));
+    /// ```
+    pub fn register(&mut self, mut colony: Colony<T, GenerationGuard, S>) -> ColonyId {
+        let id = colony.pin_id();
+        self.entries.insert(id, colony);
+        id
+    }
+
+    /// Registers the colony under `id` again, under its current identity, returning the identity.
+    ///
+    /// This should be called after the colony registered under `id` is replaced by another, see [`ColonyRegistry`].
+    /// Returns `None` if no colony is registered under `id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::{Colony, ColonyRegistry};
+    /// let mut registry = ColonyRegistry::new();
+    /// let id = registry.register(Colony::new());
+    ///
+    /// *registry.get_mut(id).unwrap() = Colony::new();
+    /// let handle = registry.get_mut(id).unwrap().insert("foo");
+    /// assert_eq!(registry.element(handle), None);
+    ///
+    /// let id = registry.reregister(id).unwrap();
+    /// assert_eq!(handle.colony_id(), id);
+    /// assert_eq!(registry.element(handle), Some(&"foo"));
+    /// ```
+    pub fn reregister(&mut self, id: ColonyId) -> Option<ColonyId> {
+        let colony = self.entries.remove(&id)?;
+        Some(self.register(colony))
+    }
+
+    /// Returns a reference to the element with the given handle, in whichever registered colony created it.
+    pub fn element(&self, handle: Handle) -> Option<&T> {
+        self.route(handle)?.get(handle)
+    }
+
+    /// Returns a mutable reference to the element with the given handle, in whichever registered colony created it.
+    pub fn element_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.route_mut(handle)?.get_mut(handle)
+    }
+
+    /// Removes the element with the given handle from whichever registered colony created it.
+    pub fn remove_element(&mut self, handle: Handle) -> Option<T> {
+        self.route_mut(handle)?.remove(handle)
+    }
+}
+
+impl<V> Default for ColonyRegistry<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Debug> Debug for ColonyRegistry<V> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.entries.iter()).finish()
+    }
+}
+
+impl<'a, V> IntoIterator for &'a ColonyRegistry<V> {
+    type Item = (&'a ColonyId, &'a V);
+    type IntoIter = hash_map::Iter<'a, ColonyId, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColonyRegistry;
+    use crate::{ApplyDeltaError, Colony};
+
+    #[test]
+    fn routing() {
+        let mut colonies: Vec<Colony<u32>> = (0..4).map(|_| Colony::new()).collect();
+        let mut handles = Vec::new();
+
+        for (i, colony) in colonies.iter_mut().enumerate() {
+            for j in 0..10 {
+                handles.push((colony.insert((i * 100 + j) as u32), i));
+            }
+        }
+
+        // Route to indices of colonies stored elsewhere
+        let mut registry = ColonyRegistry::new();
+        for (i, colony) in colonies.iter().enumerate() {
+            assert_eq!(registry.insert(colony.id().unwrap(), i), None);
+        }
+
+        for &(handle, i) in &handles {
+            assert_eq!(registry.route(handle), Some(&i));
+            assert_eq!(colonies[i][handle] / 100, i as u32);
+        }
+
+        // Handles from cleared colonies are no longer routed
+        colonies[0].clear();
+        let stale = handles[0].0;
+        let fresh = colonies[0].insert(0);
+        assert_eq!(fresh.index, stale.index);
+        assert_eq!(registry.route(fresh), None);
+        assert_eq!(registry.route(stale), Some(&0));
+        assert_eq!(colonies[0].get(stale), None);
+    }
+
+    #[test]
+    fn owned_colonies() {
+        let mut registry = ColonyRegistry::new();
+        let empty = registry.register(Colony::new());
+        let full = registry.register(Colony::from_iter([1, 2, 3]));
+        assert_ne!(empty, full);
+
+        // The identity given before the first allocation is kept
+        let handle = registry.get_mut(empty).unwrap().insert(4);
+        assert_eq!(handle.colony_id(), empty);
+        assert_eq!(registry.get(empty).unwrap().id(), Some(empty));
+
+        assert_eq!(registry.element(handle), Some(&4));
+        *registry.element_mut(handle).unwrap() += 1;
+        assert_eq!(registry.remove_element(handle), Some(5));
+        assert_eq!(registry.element(handle), None);
+
+        let colony = registry.remove(full).unwrap();
+        let (first, _) = colony.iter().next().unwrap();
+        assert_eq!(registry.element(first), None);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn cleared_colonies() {
+        let mut registry: ColonyRegistry<Colony<u32>> = ColonyRegistry::new();
+        let id = registry.register(Colony::from_iter([1, 2, 3]));
+        let old: Vec<_> = registry
+            .get(id)
+            .unwrap()
+            .iter()
+            .map(|(handle, _)| handle)
+            .collect();
+
+        // Clearing keeps the identity, and moves the generations on rather than reusing old handles
+        registry.get_mut(id).unwrap().clear();
+        let new = registry.get_mut(id).unwrap().insert(4);
+        assert_eq!(new.colony_id(), id);
+        assert!(old.iter().any(|handle| handle.index == new.index));
+        assert!(old.iter().all(|&handle| registry.element(handle).is_none()));
+        assert_eq!(registry.element(new), Some(&4));
+        assert_eq!(registry.get(id).unwrap().validate(), Ok(()));
+
+        // Deltas which would give the colony another identity are rejected
+        let mut other = Colony::new();
+        other.insert(5);
+        let delta = Colony::diff(&Colony::new(), &other);
+        let colony = registry.get_mut(id).unwrap();
+        assert_eq!(
+            colony.apply_delta(delta),
+            Err(ApplyDeltaError::ForeignDelta)
+        );
+        assert_eq!(colony.id(), Some(id));
+        assert_eq!(registry.element(new), Some(&4));
+
+        // A colony removed from the registry stays pinned
+        let mut colony = registry.remove(id).unwrap();
+        colony.clear();
+        assert_eq!(colony.insert(6).colony_id(), id);
+        assert_eq!(registry.register(colony), id);
+
+        // A replaced colony needs to be registered again
+        *registry.get_mut(id).unwrap() = Colony::new();
+        let newer = registry.get_mut(id).unwrap().insert(7);
+        assert_eq!(registry.element(newer), None);
+
+        let newer_id = registry.reregister(id).unwrap();
+        assert_eq!(newer_id, newer.colony_id());
+        assert_eq!(registry.element(newer), Some(&7));
+        assert_eq!(registry.reregister(id), None);
+        assert_eq!(registry.len(), 1);
+    }
+}
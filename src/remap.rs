@@ -0,0 +1,98 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::slice;
+use std::vec;
+
+use crate::guard::Guard;
+#[cfg(doc)]
+use crate::Colony;
+use crate::GenerationGuard;
+
+/// A mapping from the old handles of elements moved between colonies to their new handles,
+/// as returned by [`Colony::append`], [`Colony::split_off`] and [`Colony::partition`].
+///
+/// The pairs are ordered by the index of the old handle.
+pub struct HandleRemap<G: Guard = GenerationGuard> {
+    pairs: Vec<(G::Handle, G::Handle)>,
+}
+
+impl<G: Guard> HandleRemap<G> {
+    pub(crate) fn new(pairs: Vec<(G::Handle, G::Handle)>) -> Self {
+        debug_assert!(pairs
+            .windows(2)
+            .all(|pair| G::__extract_index(&pair[0].0) < G::__extract_index(&pair[1].0)));
+
+        Self { pairs }
+    }
+
+    /// Returns the new handle of the element with the given old handle, if it was moved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::new();
+    /// let mut other = Colony::new();
+    /// let handle = other.insert("foo");
+    ///
+    /// let remap = colony.append(&mut other);
+    /// assert_eq!(colony[remap.get(handle).unwrap()], "foo");
+    /// ```
+    pub fn get(&self, old: G::Handle) -> Option<G::Handle>
+    where
+        G::Handle: PartialEq + Clone,
+    {
+        let index = G::__extract_index(&old);
+        let position = self
+            .pairs
+            .binary_search_by_key(&index, |(old, _)| G::__extract_index(old))
+            .ok()?;
+
+        let (candidate, new) = &self.pairs[position];
+        (*candidate == old).then(|| new.clone())
+    }
+
+    /// Returns the number of elements moved.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if no elements were moved.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Creates an iterator over the pairs of old and new handles, ordered by the index of the old handle.
+    pub fn iter(&self) -> slice::Iter<'_, (G::Handle, G::Handle)> {
+        self.pairs.iter()
+    }
+}
+
+impl<G: Guard> IntoIterator for HandleRemap<G> {
+    type Item = (G::Handle, G::Handle);
+    type IntoIter = vec::IntoIter<(G::Handle, G::Handle)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+impl<'a, G: Guard> IntoIterator for &'a HandleRemap<G> {
+    type Item = &'a (G::Handle, G::Handle);
+    type IntoIter = slice::Iter<'a, (G::Handle, G::Handle)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<G: Guard> Debug for HandleRemap<G>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.pairs.iter().map(|(old, new)| (old, new)))
+            .finish()
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Mutex, PoisonError};
+
+use crate::guard::Guard;
+#[cfg(doc)]
+use crate::Colony;
+use crate::GenerationGuard;
+
+/// A queue of handles to remove from a colony later, see [`Colony::flush_removals`].
+///
+/// Handles can be queued through a shared reference with [`defer_remove`](RemovalQueue::defer_remove),
+/// so code that only has shared access to a colony (such as while iterating it) can still request removals.
+/// The queue can be shared between threads.
+///
+/// The handles are checked when the queue is flushed,
+/// so it is fine to queue a handle more than once.
+/// With [`GenerationGuard`], it is also fine to queue a handle that has since become invalid,
+/// but with [`FlagGuard`](crate::FlagGuard) this removes whichever element has since been inserted in its place.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::{Colony, RemovalQueue};
+/// let mut colony = Colony::new();
+/// colony.extend(0..10);
+///
+/// let queue = RemovalQueue::new();
+///
+/// for (handle, &value) in &colony {
+///     if value % 2 == 0 {
+///         queue.defer_remove(handle);
+///     }
+/// }
+///
+/// assert_eq!(colony.flush_removals(&queue), 5);
+/// assert!(colony.values().all(|&value| value % 2 == 1));
+/// ```
+pub struct RemovalQueue<G: Guard = GenerationGuard> {
+    handles: Mutex<Vec<G::Handle>>,
+}
+
+impl<G: Guard> RemovalQueue<G> {
+    /// Creates an empty queue.
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queues the element with the given handle for removal.
+    pub fn defer_remove(&self, handle: G::Handle) {
+        self.handles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(handle);
+    }
+
+    /// Returns the number of handles queued, including duplicates and invalid handles.
+    pub fn len(&self) -> usize {
+        self.handles
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns `true` if no handles are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all handles from the queue without removing their elements.
+    pub fn clear(&self) {
+        self.take();
+    }
+
+    pub(crate) fn take(&self) -> Vec<G::Handle> {
+        let mut handles = self.handles.lock().unwrap_or_else(PoisonError::into_inner);
+        std::mem::take(&mut *handles)
+    }
+}
+
+impl<G: Guard> Default for RemovalQueue<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G: Guard> Debug for RemovalQueue<G>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let handles = self.handles.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_list().entries(handles.iter()).finish()
+    }
+}
@@ -0,0 +1,28 @@
+#[cfg(doc)]
+use crate::Colony;
+
+/// Dictates which empty slot a colony fills when inserting, see [`Colony::set_reuse_policy`].
+///
+/// A *skipblock* is a maximal run of adjacent empty slots.
+/// Whatever the policy, the slots of a skipblock are always filled from lowest to highest index,
+/// policies only differ in which skipblock is used next.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum ReusePolicy {
+    /// Fill the most recently created skipblock first.
+    ///
+    /// This is the default, and is the cheapest policy to maintain,
+    /// but tends to leave empty slots scattered across the colony.
+    #[default]
+    Lifo,
+    /// Fill the lowest empty slot first.
+    ///
+    /// This keeps elements dense at the start of the colony, which helps iteration under heavy churn.
+    /// The empty slots are kept in a separate heap, making insertion and removal `O(log m)` operations
+    /// (where `m` is the number of empty slots), and using an extra `usize` per empty slot.
+    LowestFirst,
+    /// Fill the skipblock currently in use before moving on to the most recently created one.
+    ///
+    /// This avoids jumping back and forth between skipblocks when insertions and removals are interleaved.
+    FillCurrent,
+}
@@ -0,0 +1,476 @@
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+use std::ptr::NonNull;
+use std::{fmt, mem, slice};
+
+use crate::guard::Guard;
+use crate::skipfield::RIGHT;
+use crate::slots::Slots;
+use crate::{Colony, GenerationGuard, InterleavedStorage, Storage};
+
+struct RawRuns<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: &'a Colony<T, G, S>,
+    current_index: usize,
+    len: usize,
+}
+
+impl<'a, T, G: Guard, S: Storage> RawRuns<'a, T, G, S> {
+    fn new(colony: &'a Colony<T, G, S>) -> Self {
+        Self {
+            colony,
+            current_index: 0,
+            len: colony.len,
+        }
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for RawRuns<'a, T, G, S> {
+    type Item = (usize, NonNull<T>, usize);
+
+    fn next(&mut self) -> Option<(usize, NonNull<T>, usize)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        unsafe {
+            let skipfield = self.colony.skipfield();
+            let offset = skipfield.read::<RIGHT>(self.current_index as isize);
+            let start = self.current_index + offset;
+
+            let run_len = skipfield.unskipped_run(start, self.len);
+
+            let elem = NonNull::new_unchecked(self.colony.slots.value(start));
+
+            self.current_index = start + run_len;
+            self.len -= run_len;
+
+            Some((start, elem, run_len))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::from(self.len > 0), Some(self.len))
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> Clone for RawRuns<'a, T, G, S> {
+    fn clone(&self) -> Self {
+        Self {
+            colony: self.colony,
+            current_index: self.current_index,
+            len: self.len,
+        }
+    }
+}
+
+/// The iterator returned by [`Colony::runs`].
+pub struct Runs<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    raw: RawRuns<'a, T, G, S>,
+}
+
+impl<'a, T, G: Guard, S: Storage> Runs<'a, T, G, S> {
+    pub(super) fn new(colony: &'a Colony<T, G, S>) -> Self {
+        Self {
+            raw: RawRuns::new(colony),
+        }
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for Runs<'a, T, G, S> {
+    type Item = (usize, Run<'a, T>);
+
+    fn next(&mut self) -> Option<(usize, Run<'a, T>)> {
+        let (start, ptr, len) = self.raw.next()?;
+        let stride = Slots::<T, G, S>::VALUE_STRIDE;
+
+        let run = Run {
+            ptr,
+            len,
+            stride,
+            _marker: PhantomData,
+        };
+
+        Some((start, run))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> FusedIterator for Runs<'a, T, G, S> {}
+
+impl<'a, T, G: Guard, S: Storage> Clone for Runs<'a, T, G, S> {
+    fn clone(&self) -> Self {
+        Self {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for Runs<'a, T, G, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// The iterator returned by [`Colony::runs_mut`].
+pub struct RunsMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    raw: RawRuns<'a, T, G, S>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, G: Guard, S: Storage> RunsMut<'a, T, G, S> {
+    pub(super) fn new(colony: &'a mut Colony<T, G, S>) -> Self {
+        Self {
+            raw: RawRuns::new(colony),
+            _marker: PhantomData,
+        }
+    }
+
+    fn reborrow(&self) -> Runs<'_, T, G, S> {
+        Runs {
+            raw: self.raw.clone(),
+        }
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for RunsMut<'a, T, G, S> {
+    type Item = (usize, RunMut<'a, T>);
+
+    fn next(&mut self) -> Option<(usize, RunMut<'a, T>)> {
+        let (start, ptr, len) = self.raw.next()?;
+        let stride = Slots::<T, G, S>::VALUE_STRIDE;
+
+        let run = RunMut {
+            ptr,
+            len,
+            stride,
+            _marker: PhantomData,
+        };
+
+        Some((start, run))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> FusedIterator for RunsMut<'a, T, G, S> {}
+
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for RunsMut<'a, T, G, S> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.reborrow()).finish()
+    }
+}
+
+/// A view of a maximal run of adjacent elements in a colony, as returned by [`Colony::runs`].
+///
+/// Depending on the layout of the colony the elements may not be contiguous in memory,
+/// in which case the elements are spaced apart by a fixed stride.
+/// See [`as_slice`](Run::as_slice) to access the run as a slice when possible.
+#[allow(clippy::len_without_is_empty)]
+pub struct Run<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    stride: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> Run<'a, T> {
+    /// Returns the number of elements in the run, which is never zero.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns a reference to the element at `index` within the run, if it is in bounds.
+    ///
+    /// The index is relative to the start of the run, not the start of the colony.
+    pub fn get(&self, index: usize) -> Option<&'a T> {
+        if index < self.len {
+            unsafe { Some(&*element_ptr(self.ptr, self.stride, index)) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the run as a slice, if its elements are contiguous in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::unguarded();
+    /// colony.extend([[1usize; 4], [2; 4]]);
+    ///
+    /// for (_, run) in colony.runs() {
+    ///     // Unguarded elements at least as large as the freelist links are contiguous
+    ///     assert_eq!(run.as_slice(), Some(&[[1; 4], [2; 4]][..]));
+    /// }
+    /// ```
+    pub fn as_slice(&self) -> Option<&'a [T]> {
+        if self.stride == mem::size_of::<T>() {
+            unsafe { Some(slice::from_raw_parts(self.ptr.as_ptr(), self.len)) }
+        } else {
+            None
+        }
+    }
+
+    /// Creates an iterator over the elements in the run.
+    pub fn iter(&self) -> RunIter<'a, T> {
+        RunIter {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Copy for Run<'a, T> {}
+
+impl<'a, T> Clone for Run<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Index<usize> for Run<'a, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds for run")
+    }
+}
+
+impl<'a, T> IntoIterator for Run<'a, T> {
+    type Item = &'a T;
+    type IntoIter = RunIter<'a, T>;
+
+    fn into_iter(self) -> RunIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T: Debug> Debug for Run<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A mutable view of a maximal run of adjacent elements in a colony, as returned by [`Colony::runs_mut`].
+///
+/// See [`Run`] for more information.
+#[allow(clippy::len_without_is_empty)]
+pub struct RunMut<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    stride: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> RunMut<'a, T> {
+    /// Returns the number of elements in the run, which is never zero.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns a reference to the element at `index` within the run, if it is in bounds.
+    ///
+    /// See [`Run::get`].
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.reborrow().get(index)
+    }
+
+    /// Returns a mutable reference to the element at `index` within the run, if it is in bounds.
+    ///
+    /// See [`Run::get`].
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len {
+            unsafe { Some(&mut *element_ptr(self.ptr, self.stride, index)) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns the run as a slice, if its elements are contiguous in memory.
+    ///
+    /// See [`Run::as_slice`].
+    pub fn as_slice(&self) -> Option<&[T]> {
+        self.reborrow().as_slice()
+    }
+
+    /// Returns the run as a mutable slice, if its elements are contiguous in memory.
+    ///
+    /// See [`Run::as_slice`].
+    pub fn as_mut_slice(&mut self) -> Option<&mut [T]> {
+        if self.stride == mem::size_of::<T>() {
+            unsafe { Some(slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len)) }
+        } else {
+            None
+        }
+    }
+
+    /// Creates an iterator over the elements in the run.
+    pub fn iter(&self) -> RunIter<'_, T> {
+        self.reborrow().iter()
+    }
+
+    /// Creates an iterator over the elements in the run, by mutable reference.
+    pub fn iter_mut(&mut self) -> RunIterMut<'_, T> {
+        RunIterMut {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+
+    fn reborrow(&self) -> Run<'_, T> {
+        Run {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Index<usize> for RunMut<'a, T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index out of bounds for run")
+    }
+}
+
+impl<'a, T> IndexMut<usize> for RunMut<'a, T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index out of bounds for run")
+    }
+}
+
+impl<'a, T> IntoIterator for RunMut<'a, T> {
+    type Item = &'a mut T;
+    type IntoIter = RunIterMut<'a, T>;
+
+    fn into_iter(self) -> RunIterMut<'a, T> {
+        RunIterMut {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Debug> Debug for RunMut<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The iterator returned by [`Run::iter`].
+pub struct RunIter<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    stride: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T> Iterator for RunIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        unsafe {
+            let result = &*self.ptr.as_ptr();
+            self.ptr = NonNull::new_unchecked(element_ptr(self.ptr, self.stride, 1));
+            self.len -= 1;
+            Some(result)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> FusedIterator for RunIter<'a, T> {}
+
+impl<'a, T> ExactSizeIterator for RunIter<'a, T> {}
+
+impl<'a, T> Clone for RunIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Debug> Debug for RunIter<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// The iterator returned by [`RunMut::iter_mut`].
+pub struct RunIterMut<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    stride: usize,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> Iterator for RunIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        unsafe {
+            let result = &mut *self.ptr.as_ptr();
+            self.ptr = NonNull::new_unchecked(element_ptr(self.ptr, self.stride, 1));
+            self.len -= 1;
+            Some(result)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> FusedIterator for RunIterMut<'a, T> {}
+
+impl<'a, T> ExactSizeIterator for RunIterMut<'a, T> {}
+
+impl<'a, T: Debug> Debug for RunIterMut<'a, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = RunIter {
+            ptr: self.ptr,
+            len: self.len,
+            stride: self.stride,
+            _marker: PhantomData,
+        };
+
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+// Preconditions:
+// * ptr offset by index * stride bytes is in bounds of the allocation (or one past the end)
+unsafe fn element_ptr<T>(ptr: NonNull<T>, stride: usize, index: usize) -> *mut T {
+    (ptr.as_ptr() as *mut u8).add(index * stride) as *mut T
+}
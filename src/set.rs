@@ -0,0 +1,314 @@
+use std::any::{Any, TypeId};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::set::sealed::Sealed;
+use crate::{Colony, Handle};
+
+/// A collection of colonies, one for each type of element.
+///
+/// This is useful for keeping many kinds of entities together, rather than passing each colony around separately.
+/// Elements are identified by [`TypedHandle`]s, which carry the type of the element,
+/// so a handle can only be used to look up an element in the colony for its type.
+/// Since every colony has a unique identity, a handle can't be used with a different set either.
+///
+/// Colonies are created when an element of their type is first inserted, or when they are first borrowed mutably.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::ColonySet;
+/// struct Player(&'static str);
+/// struct Monster(u32);
+///
+/// let mut world = ColonySet::new();
+/// let player = world.insert(Player("foo"));
+/// let monster = world.insert(Monster(10));
+///
+/// let (players, monsters) = world.colonies_mut::<(Player, Monster)>();
+/// players[player.handle()].0 = "bar";
+/// monsters[monster.handle()].0 -= 1;
+///
+/// assert_eq!(world.get(player).unwrap().0, "bar");
+/// assert_eq!(world.get(monster).unwrap().0, 9);
+/// ```
+pub struct ColonySet {
+    colonies: HashMap<TypeId, Box<dyn Any>>,
+}
+
+/// A [`Handle`] to an element of type `T` in a [`ColonySet`].
+pub struct TypedHandle<T> {
+    handle: Handle,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedHandle<T> {
+    /// Returns the untyped handle, for use with the colony of `T` directly.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+}
+
+impl ColonySet {
+    /// Constructs an empty set of colonies.
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            colonies: HashMap::new(),
+        }
+    }
+
+    /// Inserts an element into the colony for its type, returning its handle.
+    ///
+    /// See [`Colony::insert`].
+    pub fn insert<T: 'static>(&mut self, value: T) -> TypedHandle<T> {
+        TypedHandle {
+            handle: self.colony_mut::<T>().insert(value),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns `true` if the set contains an element with the given handle.
+    pub fn contains<T: 'static>(&self, handle: TypedHandle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Returns a reference to the element with the given handle if it exists.
+    ///
+    /// See [`Colony::get`].
+    pub fn get<T: 'static>(&self, handle: TypedHandle<T>) -> Option<&T> {
+        self.colony::<T>()?.get(handle.handle)
+    }
+
+    /// Returns a mutable reference to the element with the given handle if it exists.
+    ///
+    /// See [`Colony::get_mut`].
+    pub fn get_mut<T: 'static>(&mut self, handle: TypedHandle<T>) -> Option<&mut T> {
+        self.colonies
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<Colony<T>>()?
+            .get_mut(handle.handle)
+    }
+
+    /// Removes the element with the given handle if it exists.
+    ///
+    /// See [`Colony::remove`].
+    pub fn remove<T: 'static>(&mut self, handle: TypedHandle<T>) -> Option<T> {
+        self.colonies
+            .get_mut(&TypeId::of::<T>())?
+            .downcast_mut::<Colony<T>>()?
+            .remove(handle.handle)
+    }
+
+    /// Returns a reference to the colony for `T`, if it has been created.
+    pub fn colony<T: 'static>(&self) -> Option<&Colony<T>> {
+        self.colonies.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the colony for `T`, creating it if necessary.
+    pub fn colony_mut<T: 'static>(&mut self) -> &mut Colony<T> {
+        self.colonies
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Colony::<T>::new()))
+            .downcast_mut()
+            .expect("colony stored under the wrong type")
+    }
+
+    /// Returns mutable references to the colonies for several types at once, creating them if necessary.
+    ///
+    /// The types are given as a tuple of up to six types, and the references are returned as a tuple in the same order.
+    ///
+    /// # Panics
+    ///
+    /// If the same type is given more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::ColonySet;
+    /// let mut world = ColonySet::new();
+    /// let (numbers, strings) = world.colonies_mut::<(u32, String)>();
+    ///
+    /// for (_, string) in strings.iter() {
+    ///     numbers.insert(string.len() as u32);
+    /// }
+    /// ```
+    pub fn colonies_mut<C: ColonyTypes>(&mut self) -> C::Mut<'_> {
+        C::__colonies_mut(self)
+    }
+
+    /// Removes the colony for `T` from the set, returning it if it had been created.
+    pub fn remove_colony<T: 'static>(&mut self) -> Option<Colony<T>> {
+        let colony = self.colonies.remove(&TypeId::of::<T>())?;
+        Some(
+            *colony
+                .downcast()
+                .expect("colony stored under the wrong type"),
+        )
+    }
+}
+
+impl Default for ColonySet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for ColonySet {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ColonySet")
+            .field("colonies", &self.colonies.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A tuple of element types whose colonies can be borrowed at once with [`ColonySet::colonies_mut`].
+///
+/// This is implemented for tuples of up to six types.
+pub trait ColonyTypes: Sealed {
+    /// The tuple of mutable references to the colonies.
+    type Mut<'a>;
+
+    #[doc(hidden)]
+    fn __colonies_mut(set: &mut ColonySet) -> Self::Mut<'_>;
+}
+
+macro_rules! impl_colony_types {
+    ($($T:ident),+) => {
+        impl<$($T: 'static),+> Sealed for ($($T,)+) {}
+
+        impl<$($T: 'static),+> ColonyTypes for ($($T,)+) {
+            type Mut<'a> = ($(&'a mut Colony<$T>,)+);
+
+            #[allow(non_snake_case)]
+            fn __colonies_mut(set: &mut ColonySet) -> Self::Mut<'_> {
+                $(set.colony_mut::<$T>();)+
+
+                // Panics if any of the types are the same
+                let [$($T),+] = set.colonies.get_disjoint_mut([$(&TypeId::of::<$T>()),+]);
+
+                ($($T
+                    .and_then(|colony| colony.downcast_mut::<Colony<$T>>())
+                    .expect("colony stored under the wrong type"),)+)
+            }
+        }
+    };
+}
+
+impl_colony_types!(A);
+impl_colony_types!(A, B);
+impl_colony_types!(A, B, C);
+impl_colony_types!(A, B, C, D);
+impl_colony_types!(A, B, C, D, E);
+impl_colony_types!(A, B, C, D, E, F);
+
+impl<T> From<TypedHandle<T>> for Handle {
+    fn from(handle: TypedHandle<T>) -> Handle {
+        handle.handle
+    }
+}
+
+impl<T> Copy for TypedHandle<T> {}
+
+impl<T> Clone for TypedHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for TypedHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl<T> Eq for TypedHandle<T> {}
+
+impl<T> PartialOrd for TypedHandle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for TypedHandle<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.handle.cmp(&other.handle)
+    }
+}
+
+impl<T> Hash for TypedHandle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.handle.hash(state);
+    }
+}
+
+impl<T> Debug for TypedHandle<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("TypedHandle").field(&self.handle).finish()
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::ColonySet;
+
+    #[test]
+    fn separate_colonies() {
+        let mut set = ColonySet::new();
+        let a = set.insert(1u32);
+        let b = set.insert("foo");
+        let c = set.insert(2u32);
+
+        assert_eq!(set.get(a), Some(&1));
+        assert_eq!(set.get(b), Some(&"foo"));
+        assert_eq!(set.colony::<u32>().unwrap().len(), 2);
+        assert!(set.colony::<u64>().is_none());
+
+        // Both colonies start at index zero, but the handles can't be confused
+        assert_eq!(a.handle().index, b.handle().index);
+        assert_eq!(set.colony::<&str>().unwrap().get(a.handle()), None);
+
+        assert_eq!(set.remove(a), Some(1));
+        assert!(!set.contains(a));
+        assert_eq!(set.get(c), Some(&2));
+
+        // Handles from another set are rejected by the colony identity
+        let mut other = ColonySet::new();
+        let d = other.insert(3u32);
+        assert_eq!(set.get(d), None);
+
+        let colony = set.remove_colony::<u32>().unwrap();
+        assert_eq!(colony[c.handle()], 2);
+        assert_eq!(set.get(c), None);
+    }
+
+    #[test]
+    fn colonies_mut() {
+        let mut set = ColonySet::new();
+        let a = set.insert(1u8);
+
+        let (bytes, words, strings) = set.colonies_mut::<(u8, u16, String)>();
+        let value = bytes[a.handle()];
+        words.insert(value as u16 + 1);
+        strings.insert(value.to_string());
+
+        assert_eq!(set.colony::<u16>().unwrap().len(), 1);
+        assert_eq!(set.colony::<String>().unwrap().len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn colonies_mut_duplicate() {
+        let mut set = ColonySet::new();
+        set.colonies_mut::<(u8, u16, u8)>();
+    }
+}
@@ -1,5 +1,5 @@
-use std::mem;
 use std::ptr::NonNull;
+use std::{mem, ptr};
 
 pub type SkipfieldElement = u8;
 
@@ -21,20 +21,22 @@ impl SkipfieldPtr {
     }
 
     // Preconditions:
-    // * index in bounds and unskipped
-    pub unsafe fn skip(&self, index: usize) -> (usize, usize) {
-        let left = self.read::<LEFT>(index as isize - 1);
-        let right = self.read::<RIGHT>(index as isize + 1);
+    // * first <= last, and both are in bounds
+    // * elements from first through last are unskipped
+    pub unsafe fn skip(&self, first: usize, last: usize) -> (usize, usize) {
+        let left = self.read::<LEFT>(first as isize - 1);
+        let right = self.read::<RIGHT>(last as isize + 1);
+        let count = last - first + 1;
 
         debug_assert!(left
             .checked_add(right)
-            .and_then(|n| n.checked_add(1))
+            .and_then(|n| n.checked_add(count))
             .is_some());
 
-        let size = left + right + 1;
+        let size = left + right + count;
 
-        let start = index - left;
-        let end = index + right;
+        let start = first - left;
+        let end = last + right;
 
         self.write::<RIGHT>(start as isize, size);
         self.write::<LEFT>(end as isize, size);
@@ -58,6 +60,64 @@ impl SkipfieldPtr {
         }
     }
 
+    // Unskips the first count elements of a skipblock at once
+    // Preconditions:
+    // * index is the head of a skipblock
+    // * 0 < count <= size of the skipblock
+    pub unsafe fn unskip_leading(&self, index: usize, count: usize) {
+        let old_size = self.read::<RIGHT>(index as isize);
+        debug_assert!(0 < count && count <= old_size);
+
+        // Unskipped elements are always zero, whatever was left there by previous skipblocks
+        ptr::write_bytes(self.ptr.as_ptr().add(index), 0, count);
+
+        let new_size = old_size - count;
+
+        if new_size > 0 {
+            self.write::<RIGHT>((index + count) as isize, new_size);
+            self.write::<LEFT>((index + old_size - 1) as isize, new_size);
+        }
+    }
+
+    // Unskips a single element anywhere within a skipblock, splitting it in two
+    // Preconditions:
+    // * start and end are the head and tail of a skipblock
+    // * start <= index <= end
+    pub unsafe fn unskip_within(&self, start: usize, end: usize, index: usize) {
+        debug_assert!(start <= index && index <= end);
+        debug_assert_eq!(self.read::<RIGHT>(start as isize), end - start + 1);
+
+        *self.ptr.as_ptr().add(index) = 0;
+
+        if index > start {
+            let size = index - start;
+            self.write::<RIGHT>(start as isize, size);
+            self.write::<LEFT>((index - 1) as isize, size);
+        }
+
+        if index < end {
+            let size = end - index;
+            self.write::<RIGHT>((index + 1) as isize, size);
+            self.write::<LEFT>(end as isize, size);
+        }
+    }
+
+    // Preconditions:
+    // * index is in bounds and unskipped
+    // * 0 < max and index + max <= len
+    pub unsafe fn unskipped_run(&self, index: usize, max: usize) -> usize {
+        let ptr = self.ptr.as_ptr().add(index);
+        let mut len = 1;
+
+        // An unskipped element is always zero, and an element after an unskipped one is either
+        // unskipped or the head of a skipblock (and so non-zero)
+        while len < max && *ptr.add(len) == 0 {
+            len += 1;
+        }
+
+        len
+    }
+
     // Preconditions:
     // * index is in [-1, len + 1)
     // * if there is a skipblock over index, its head is at index
@@ -139,7 +199,18 @@ mod test {
             self.skipped[index] = true;
 
             unsafe {
-                self.skipfield_mut().skip(index);
+                self.skipfield_mut().skip(index, index);
+            }
+        }
+
+        pub fn skip_range(&mut self, first: usize, last: usize) {
+            assert!(first <= last && last < self.len());
+            assert!(self.skipped[first..=last].iter().all(|&skipped| !skipped));
+
+            self.skipped[first..=last].fill(true);
+
+            unsafe {
+                self.skipfield_mut().skip(first, last);
             }
         }
 
@@ -155,6 +226,39 @@ mod test {
             }
         }
 
+        pub fn unskip_leading(&mut self, index: usize, count: usize) {
+            assert!(index + count <= self.len());
+            assert!(self.skipped[index..index + count]
+                .iter()
+                .all(|&skipped| skipped));
+            assert!(index == 0 || !self.skipped[index - 1]);
+
+            self.skipped[index..index + count].fill(false);
+
+            unsafe {
+                self.skipfield_mut().unskip_leading(index, count);
+            }
+        }
+
+        pub fn unskip_within(&mut self, index: usize) {
+            assert!(self.skipped[index]);
+
+            let start = (0..index)
+                .rev()
+                .find(|&i| !self.skipped[i])
+                .map_or(0, |i| i + 1);
+            let end = (index..self.len())
+                .find(|&i| !self.skipped[i])
+                .map_or(self.len(), |i| i)
+                - 1;
+
+            self.skipped[index] = false;
+
+            unsafe {
+                self.skipfield_mut().unskip_within(start, end, index);
+            }
+        }
+
         pub fn check(&self) {
             let mut index = 0;
 
@@ -228,6 +332,37 @@ mod test {
         model.check();
     }
 
+    #[test]
+    fn skip_ranges() {
+        for &size in N {
+            let mut model = Model::new(size);
+            let mut index = 0;
+            let mut count = 1;
+
+            // Skip every other range, then the ranges between them to join everything
+            while index < size {
+                let last = (index + count).min(size) - 1;
+                model.skip_range(index, last);
+                model.check();
+
+                index = last + 1 + count;
+                count *= 3;
+            }
+
+            while let Some(first) = model.skipped.iter().position(|&skipped| !skipped) {
+                let count = model.skipped[first..]
+                    .iter()
+                    .take_while(|&&skipped| !skipped)
+                    .count();
+
+                model.skip_range(first, first + count - 1);
+                model.check();
+            }
+
+            assert!(model.skipped.iter().all(|&skipped| skipped));
+        }
+    }
+
     #[test]
     fn unskip_all() {
         for &size in N {
@@ -244,4 +379,53 @@ mod test {
             model.check();
         }
     }
+
+    #[test]
+    fn unskip_leading() {
+        for &size in N {
+            let mut model = Model::new(size);
+
+            for i in 0..size {
+                model.skip(i);
+            }
+
+            let mut index = 0;
+            let mut count = 1;
+
+            while index < size {
+                count = count.min(size - index);
+                model.unskip_leading(index, count);
+                model.check();
+
+                index += count;
+                count *= 3;
+            }
+        }
+    }
+
+    #[test]
+    fn unskip_within() {
+        for &size in N.iter().filter(|&&size| size <= 1_000) {
+            let mut model = Model::new(size);
+
+            for i in 0..size {
+                model.skip(i);
+            }
+
+            // Split the last skipblock at uneven points until every element is unskipped
+            let mut step: usize = 1;
+
+            while let Some(end) = model.skipped.iter().rposition(|&skipped| skipped) {
+                let start = (0..end)
+                    .rev()
+                    .find(|&i| !model.skipped[i])
+                    .map_or(0, |i| i + 1);
+
+                model.unskip_within(start + step % (end - start + 1));
+                model.check();
+
+                step = step.wrapping_mul(7).wrapping_add(3);
+            }
+        }
+    }
 }
@@ -0,0 +1,248 @@
+use std::alloc::{Layout, LayoutError};
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+use std::{mem, ptr};
+
+use crate::guard::Guard;
+use crate::index_opt::IndexOpt;
+use crate::skipfield::SkipfieldElement;
+use crate::storage::Storage;
+
+// The unit of storage when using `InterleavedStorage`, only ever accessed through `Slots`
+struct Slot<T, G> {
+    guard: G,
+    inner: SlotInner<T>,
+}
+
+#[allow(dead_code)]
+union SlotInner<T> {
+    occupied: ManuallyDrop<T>,
+    unoccupied: Unoccupied,
+}
+
+#[derive(Copy, Clone)]
+pub struct Unoccupied {
+    pub prev: IndexOpt,
+    pub next: IndexOpt,
+}
+
+pub struct SlotsLayout {
+    pub layout: Layout,
+    values_offset: usize,
+    guards_offset: usize,
+    links_offset: usize,
+    pub skipfield_offset: usize,
+}
+
+// Pointers to the first element, guard and set of freelist links in an allocation
+// With `InterleavedStorage` these all point into a single array of `Slot`s, and the elements overlap the links
+// With `SeparatedStorage` each is its own array, except the links overlap the elements if they fit
+pub struct Slots<T, G: Guard, S: Storage> {
+    values: NonNull<T>,
+    guards: NonNull<G>,
+    links: NonNull<Unoccupied>,
+    _marker: PhantomData<S>,
+}
+
+impl<T, G: Guard, S: Storage> Slots<T, G, S> {
+    pub const VALUE_STRIDE: usize = if S::__SEPARATED {
+        mem::size_of::<T>()
+    } else {
+        mem::size_of::<Slot<T, G>>()
+    };
+
+    const GUARD_STRIDE: usize = if S::__SEPARATED {
+        mem::size_of::<G>()
+    } else {
+        mem::size_of::<Slot<T, G>>()
+    };
+
+    // Otherwise separated links get their own array, which every slot pays for, see `SeparatedStorage`
+    const LINKS_IN_VALUES: bool = !S::__SEPARATED
+        || (mem::size_of::<T>() >= mem::size_of::<Unoccupied>()
+            && mem::align_of::<T>() >= mem::align_of::<Unoccupied>());
+
+    const LINKS_STRIDE: usize = if Self::LINKS_IN_VALUES {
+        Self::VALUE_STRIDE
+    } else {
+        mem::size_of::<Unoccupied>()
+    };
+
+    pub fn dangling() -> Self {
+        Self {
+            values: NonNull::dangling(),
+            guards: NonNull::dangling(),
+            links: NonNull::dangling(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn layout(capacity: usize) -> Result<SlotsLayout, LayoutError> {
+        if S::__SEPARATED {
+            Self::separated_layout(capacity)
+        } else {
+            Self::interleaved_layout(capacity)
+        }
+    }
+
+    fn interleaved_layout(capacity: usize) -> Result<SlotsLayout, LayoutError> {
+        let layout = Layout::array::<Slot<T, G>>(capacity)?;
+        let (layout, skipfield_offset) = Self::extend_with_skipfield(layout, capacity)?;
+
+        let values_offset = mem::offset_of!(Slot<T, G>, inner);
+
+        Ok(SlotsLayout {
+            layout,
+            values_offset,
+            guards_offset: mem::offset_of!(Slot<T, G>, guard),
+            links_offset: values_offset,
+            skipfield_offset,
+        })
+    }
+
+    fn separated_layout(capacity: usize) -> Result<SlotsLayout, LayoutError> {
+        let layout = Layout::array::<T>(capacity)?;
+
+        let (layout, links_offset) = if Self::LINKS_IN_VALUES {
+            (layout, 0)
+        } else {
+            layout.extend(Layout::array::<Unoccupied>(capacity)?)?
+        };
+
+        let (layout, guards_offset) = layout.extend(Layout::array::<G>(capacity)?)?;
+        let (layout, skipfield_offset) = Self::extend_with_skipfield(layout, capacity)?;
+
+        Ok(SlotsLayout {
+            layout,
+            values_offset: 0,
+            guards_offset,
+            links_offset,
+            skipfield_offset,
+        })
+    }
+
+    fn extend_with_skipfield(
+        layout: Layout,
+        capacity: usize,
+    ) -> Result<(Layout, usize), LayoutError> {
+        let (layout, _) = layout.extend(Layout::new::<SkipfieldElement>())?;
+        let (layout, skipfield_offset) =
+            layout.extend(Layout::array::<SkipfieldElement>(capacity)?)?;
+        let (layout, _) = layout.extend(Layout::new::<SkipfieldElement>())?;
+
+        Ok((layout, skipfield_offset))
+    }
+
+    // Preconditions:
+    // * alloc was allocated from layout
+    pub unsafe fn from_alloc(alloc: NonNull<u8>, layout: &SlotsLayout) -> Self {
+        let alloc = alloc.as_ptr();
+
+        Self {
+            values: NonNull::new_unchecked(alloc.add(layout.values_offset) as *mut T),
+            guards: NonNull::new_unchecked(alloc.add(layout.guards_offset) as *mut G),
+            links: NonNull::new_unchecked(alloc.add(layout.links_offset) as *mut Unoccupied),
+            _marker: PhantomData,
+        }
+    }
+
+    // Preconditions:
+    // * self was created by from_alloc with the given layout
+    pub unsafe fn alloc(&self, layout: &SlotsLayout) -> *mut u8 {
+        (self.values.as_ptr() as *mut u8).sub(layout.values_offset)
+    }
+
+    // Preconditions:
+    // * index < capacity
+    pub unsafe fn value(&self, index: usize) -> *mut T {
+        Self::offset(self.values, index, Self::VALUE_STRIDE)
+    }
+
+    // Preconditions:
+    // * index < capacity
+    pub unsafe fn guard(&self, index: usize) -> *mut G {
+        Self::offset(self.guards, index, Self::GUARD_STRIDE)
+    }
+
+    // Preconditions:
+    // * index < capacity
+    pub unsafe fn links(&self, index: usize) -> *mut Unoccupied {
+        Self::offset(self.links, index, Self::LINKS_STRIDE)
+    }
+
+    unsafe fn offset<U>(ptr: NonNull<U>, index: usize, stride: usize) -> *mut U {
+        (ptr.as_ptr() as *mut u8).add(index * stride) as *mut U
+    }
+
+    // Preconditions:
+    // * index < capacity
+    // * the slot at index is uninitialized
+    pub unsafe fn write_full(&self, index: usize, value: T) {
+        self.guard(index).write(G::__new());
+        self.value(index).write(value);
+    }
+
+    // Preconditions:
+    // * index < capacity
+    // * the slot at index is unoccupied
+    pub unsafe fn fill(&self, index: usize, value: T) {
+        (*self.guard(index)).__fill();
+        self.value(index).write(value);
+    }
+
+    // Preconditions:
+    // * index < capacity
+    // * the slot at index is occupied
+    pub unsafe fn empty(&self, index: usize) -> (T, bool) {
+        let value = self.value(index).read();
+
+        self.links(index).write(Unoccupied {
+            prev: IndexOpt::none(),
+            next: IndexOpt::none(),
+        });
+
+        let reuse = (*self.guard(index)).__empty();
+        (value, reuse)
+    }
+
+    // Preconditions:
+    // * other was created from an allocation distinct from self
+    // * count <= capacity of self and other
+    pub unsafe fn copy_to(&self, other: &Self, count: usize) {
+        self.copy_range_to(0, other, 0, count);
+    }
+
+    // Copies count slots starting at from in self to the slots starting at to in other
+    // Preconditions:
+    // * other was created from an allocation distinct from self
+    // * from + count <= capacity of self, and to + count <= capacity of other
+    pub unsafe fn copy_range_to(&self, from: usize, other: &Self, to: usize, count: usize) {
+        // The pointers may be dangling
+        if count == 0 {
+            return;
+        }
+
+        if S::__SEPARATED {
+            ptr::copy_nonoverlapping(self.value(from), other.value(to), count);
+            ptr::copy_nonoverlapping(self.guard(from), other.guard(to), count);
+
+            if !Self::LINKS_IN_VALUES {
+                ptr::copy_nonoverlapping(self.links(from), other.links(to), count);
+            }
+        } else {
+            let offset = mem::offset_of!(Slot<T, G>, inner);
+            let source = (self.value(from) as *mut u8).sub(offset) as *mut Slot<T, G>;
+            let dest = (other.value(to) as *mut u8).sub(offset) as *mut Slot<T, G>;
+            ptr::copy_nonoverlapping(source, dest, count);
+        }
+    }
+}
+
+impl<T, G: Guard, S: Storage> Copy for Slots<T, G, S> {}
+
+impl<T, G: Guard, S: Storage> Clone for Slots<T, G, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
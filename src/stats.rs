@@ -0,0 +1,65 @@
+#[cfg(doc)]
+use crate::Colony;
+
+/// Statistics about the memory usage and fragmentation of a colony, as returned by [`Colony::stats`].
+///
+/// A *skipblock* is a maximal run of adjacent empty slots, which iteration jumps over in a single step.
+/// Slots that have never been used are not part of any skipblock.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The number of elements in the colony, see [`Colony::len`].
+    pub len: usize,
+    /// The number of slots that have been used since the colony was created or last cleared.
+    ///
+    /// This includes both occupied and empty slots, and is the number of slots iteration may need to touch.
+    pub touched: usize,
+    /// The number of slots allocated, see [`Colony::capacity`].
+    pub capacity: usize,
+    /// The number of skipblocks.
+    pub skipblocks: usize,
+    /// The number of slots in the largest skipblock, or zero if there are none.
+    pub largest_skipblock: usize,
+    /// The number of empty slots available for reuse.
+    pub freelist_len: usize,
+    /// The number of empty slots that will never be reused.
+    ///
+    /// When using [`GenerationGuard`](crate::GenerationGuard), a slot is retired once its generations are exhausted.
+    /// Retired slots are counted as they retire rather than derived from the freelist,
+    /// so this and [`freelist_len`](Stats::freelist_len) add up to [`empty`](Stats::empty) unless the colony is corrupted.
+    pub retired: usize,
+    /// The number of bytes currently allocated by the colony.
+    pub allocated_bytes: usize,
+}
+
+impl Stats {
+    /// Returns the number of empty slots, both reusable and retired.
+    pub fn empty(&self) -> usize {
+        self.touched - self.len
+    }
+
+    /// Returns the average number of slots in a skipblock, or zero if there are no skipblocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::Colony;
+    /// let mut colony = Colony::flagged();
+    /// colony.extend(0..10);
+    ///
+    /// colony.remove(1);
+    /// colony.remove(5);
+    /// colony.remove(6);
+    ///
+    /// let stats = colony.stats();
+    /// assert_eq!(stats.skipblocks, 2);
+    /// assert_eq!(stats.average_skipblock(), 1.5);
+    /// ```
+    pub fn average_skipblock(&self) -> f64 {
+        if self.skipblocks == 0 {
+            0.0
+        } else {
+            self.empty() as f64 / self.skipblocks as f64
+        }
+    }
+}
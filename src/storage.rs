@@ -0,0 +1,51 @@
+use crate::storage::sealed::Sealed;
+
+#[cfg(doc)]
+use crate::Colony;
+
+/// Dictates how the slots of a colony are laid out in memory.
+///
+/// This is a sealed trait, so only one of the supported storages can be used.
+/// Also, any `#[doc(hidden)]` member of this trait should not be considered as part of the public API.
+///
+/// See [`Colony`] for more information about storages.
+pub trait Storage: Sealed {
+    #[doc(hidden)]
+    const __SEPARATED: bool;
+}
+
+/// The default storage, which keeps each element alongside its guard and freelist links.
+///
+/// See [`Colony`] for more information about storages.
+#[non_exhaustive]
+#[allow(missing_debug_implementations)]
+pub struct InterleavedStorage;
+
+impl Storage for InterleavedStorage {
+    const __SEPARATED: bool = false;
+}
+
+impl Sealed for InterleavedStorage {}
+
+/// A storage that keeps elements, guards and (when necessary) freelist links in separate arrays.
+///
+/// This removes the padding between elements and guards, and packs elements densely so iterating touches less memory.
+/// It does not shrink a slot to just its element, guard and skipfield byte, however.
+/// Freelist links take two `usize`s per slot, and are only kept in place of the element when it is large and aligned enough to hold them.
+/// For smaller elements they are kept in their own array, costing 16 bytes per slot on a 64-bit system, even for occupied slots.
+/// A `SeparatedColony<u8, FlagGuard>` therefore uses 19 bytes per slot rather than 3, compared to 25 with [`InterleavedStorage`].
+///
+/// See [`Colony`] for more information about storages.
+#[non_exhaustive]
+#[allow(missing_debug_implementations)]
+pub struct SeparatedStorage;
+
+impl Storage for SeparatedStorage {
+    const __SEPARATED: bool = true;
+}
+
+impl Sealed for SeparatedStorage {}
+
+mod sealed {
+    pub trait Sealed {}
+}
@@ -0,0 +1,48 @@
+// Helpers shared by the model tests of several modules
+
+// A small deterministic generator, so that model tests are reproducible without any dependencies
+pub struct Random {
+    state: u64,
+}
+
+// A random step of a model test, which inserts, removes or changes an element
+pub enum Step<H> {
+    Insert(usize),
+    Remove(H),
+    Change(H, usize),
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Returns a number less than bound
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) as usize % bound
+    }
+
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            slice.swap(i, self.below(i + 1));
+        }
+    }
+
+    // Returns an insertion half of the time, and otherwise a removal or change of one of the handles
+    // The handle of a removal is taken out of handles, and the handle of an insertion should be added to it
+    pub fn step<H: Copy>(&mut self, handles: &mut Vec<H>) -> Step<H> {
+        match self.below(4) {
+            _ if handles.is_empty() => Step::Insert(self.below(1_000)),
+            0 | 1 => Step::Insert(self.below(1_000)),
+            2 => Step::Remove(handles.swap_remove(self.below(handles.len()))),
+            _ => {
+                let handle = handles[self.below(handles.len())];
+                Step::Change(handle, self.below(1_000))
+            }
+        }
+    }
+}
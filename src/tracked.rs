@@ -0,0 +1,318 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::{Deref, Index, IndexMut};
+
+use crate::guard::{CheckedGuard, Guard};
+use crate::{Colony, GenerationGuard, Handle, InterleavedStorage, IterMut, Storage};
+
+/// A colony that records which elements are inserted, removed and mutated, for example to replicate it elsewhere.
+///
+/// Changes accumulate until they are collected with [`drain_changes`](TrackedColony::drain_changes).
+/// An element is considered mutated whenever a mutable reference to it is handed out,
+/// whether or not it is actually written to.
+///
+/// The colony can be read through [`Deref`], but must be modified through the methods of this type to be tracked.
+///
+/// # Examples
+///
+/// ```
+/// # use colony::TrackedColony;
+/// let mut colony = TrackedColony::new();
+/// let foo = colony.insert("foo");
+/// let bar = colony.insert("bar");
+/// colony.drain_changes();
+///
+/// colony[foo] = "baz";
+/// colony.remove(bar);
+/// let qux = colony.insert("qux");
+///
+/// let changes = colony.drain_changes();
+/// assert_eq!(changes.inserted, [qux]);
+/// assert_eq!(changes.modified, [foo]);
+/// assert_eq!(changes.removed, [bar]);
+/// ```
+pub struct TrackedColony<T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    colony: Colony<T, G, S>,
+    journal: Journal<G>,
+}
+
+/// The changes made to a [`TrackedColony`], as returned by [`TrackedColony::drain_changes`].
+///
+/// Each element appears at most once:
+/// an element that was inserted and then mutated is only reported as inserted,
+/// an element that was mutated and then removed is only reported as removed,
+/// and an element that was inserted and then removed is not reported at all.
+/// The handles are ordered by when each element was first changed.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub struct Changes<H = Handle> {
+    /// The handles of elements inserted.
+    pub inserted: Vec<H>,
+    /// The handles of elements which were handed out by mutable reference.
+    pub modified: Vec<H>,
+    /// The handles of elements removed.
+    pub removed: Vec<H>,
+}
+
+impl<H> Changes<H> {
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.inserted.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+struct Journal<G: Guard> {
+    // The change to each slot, grown as slots are touched
+    states: Vec<SlotChange>,
+    // Every slot that has been changed, possibly including slots whose change was cancelled
+    changed: Vec<usize>,
+    removed: Vec<G::Handle>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum SlotChange {
+    Unchanged,
+    Inserted,
+    Modified,
+}
+
+impl<G: Guard> Journal<G> {
+    fn new() -> Self {
+        Self {
+            states: Vec::new(),
+            changed: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, index: usize) {
+        if index >= self.states.len() {
+            self.states.resize(index + 1, SlotChange::Unchanged);
+        }
+
+        debug_assert!(self.states[index] == SlotChange::Unchanged);
+        self.states[index] = SlotChange::Inserted;
+        self.changed.push(index);
+    }
+
+    fn modify(&mut self, index: usize) {
+        if index >= self.states.len() {
+            self.states.resize(index + 1, SlotChange::Unchanged);
+        }
+
+        if self.states[index] == SlotChange::Unchanged {
+            self.states[index] = SlotChange::Modified;
+            self.changed.push(index);
+        }
+    }
+
+    fn remove(&mut self, index: usize, handle: G::Handle) {
+        let state = self.states.get_mut(index);
+        let state = state.map_or(SlotChange::Unchanged, |state| {
+            std::mem::replace(state, SlotChange::Unchanged)
+        });
+
+        if state != SlotChange::Inserted {
+            self.removed.push(handle);
+        }
+    }
+}
+
+impl<T> TrackedColony<T> {
+    /// Constructs an empty tracked colony using [`GenerationGuard`].
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, G: Guard, S: Storage> TrackedColony<T, G, S> {
+    /// Inserts an element into the colony, recording it as inserted.
+    ///
+    /// See [`Colony::insert`].
+    pub fn insert(&mut self, value: T) -> G::Handle {
+        let handle = self.colony.insert(value);
+        self.journal.insert(G::__extract_index(&handle));
+        handle
+    }
+
+    /// Returns a mutable reference to the element with the given handle if it exists, recording it as modified.
+    ///
+    /// See [`Colony::get_mut`].
+    pub fn get_mut(&mut self, handle: G::Handle) -> Option<&mut T>
+    where
+        G: CheckedGuard,
+    {
+        let index = G::__extract_index(&handle);
+        let value = self.colony.get_mut(handle)?;
+        self.journal.modify(index);
+        Some(value)
+    }
+
+    /// Records the element with the given handle as modified, returning whether it exists.
+    ///
+    /// This is useful for elements which are mutated through interior mutability.
+    pub fn mark_modified(&mut self, handle: G::Handle) -> bool
+    where
+        G: CheckedGuard,
+    {
+        self.get_mut(handle).is_some()
+    }
+
+    /// Removes the element with the given handle if it exists, recording it as removed.
+    ///
+    /// See [`Colony::remove`].
+    pub fn remove(&mut self, handle: G::Handle) -> Option<T>
+    where
+        G: CheckedGuard,
+        G::Handle: Clone,
+    {
+        let index = G::__extract_index(&handle);
+        let value = self.colony.remove(handle.clone())?;
+        self.journal.remove(index, handle);
+        Some(value)
+    }
+
+    /// Removes all elements from the colony, recording each as removed.
+    ///
+    /// Unlike [`Colony::clear`], this is an `O(n)` operation even if the elements don't need to be dropped.
+    pub fn clear(&mut self) {
+        for (handle, _) in self.colony.iter() {
+            self.journal.remove(G::__extract_index(&handle), handle);
+        }
+
+        self.colony.clear();
+    }
+
+    /// Creates an iterator over the handles and elements of the colony by mutable reference,
+    /// recording each element as modified when it is yielded.
+    ///
+    /// See [`Colony::iter_mut`].
+    pub fn iter_mut(&mut self) -> TrackedIterMut<'_, T, G, S> {
+        TrackedIterMut {
+            inner: self.colony.iter_mut(),
+            journal: &mut self.journal,
+        }
+    }
+
+    /// Returns the changes made since the last call, and starts recording afresh.
+    ///
+    /// This is an `O(c)` operation, where `c` is the number of changes recorded.
+    pub fn drain_changes(&mut self) -> Changes<G::Handle> {
+        let mut changes = Changes {
+            inserted: Vec::new(),
+            modified: Vec::new(),
+            removed: std::mem::take(&mut self.journal.removed),
+        };
+
+        for index in self.journal.changed.drain(..) {
+            let state = std::mem::replace(&mut self.journal.states[index], SlotChange::Unchanged);
+
+            let changed = match state {
+                SlotChange::Unchanged => continue,
+                SlotChange::Inserted => &mut changes.inserted,
+                SlotChange::Modified => &mut changes.modified,
+            };
+
+            // Slots are reset when their element is removed, so any slot still changed is occupied
+            let handle =
+                unsafe { G::__new_handle(self.colony.guard(index), index, self.colony.id) };
+            changed.push(handle);
+        }
+
+        changes
+    }
+
+    /// Returns a reference to the underlying colony.
+    pub fn as_colony(&self) -> &Colony<T, G, S> {
+        &self.colony
+    }
+
+    /// Consumes the tracked colony, returning the underlying colony and discarding any changes recorded.
+    pub fn into_inner(self) -> Colony<T, G, S> {
+        self.colony
+    }
+}
+
+impl<T, G: Guard, S: Storage> Default for TrackedColony<T, G, S> {
+    fn default() -> Self {
+        Self::from(Colony::default())
+    }
+}
+
+impl<T, G: Guard, S: Storage> From<Colony<T, G, S>> for TrackedColony<T, G, S> {
+    /// Starts tracking changes to a colony, treating its current elements as unchanged.
+    fn from(colony: Colony<T, G, S>) -> Self {
+        Self {
+            colony,
+            journal: Journal::new(),
+        }
+    }
+}
+
+impl<T, G: Guard, S: Storage> Deref for TrackedColony<T, G, S> {
+    type Target = Colony<T, G, S>;
+
+    fn deref(&self) -> &Colony<T, G, S> {
+        &self.colony
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> Index<G::Handle> for TrackedColony<T, G, S> {
+    type Output = T;
+
+    fn index(&self, index: G::Handle) -> &T {
+        &self.colony[index]
+    }
+}
+
+impl<T, G: CheckedGuard, S: Storage> IndexMut<G::Handle> for TrackedColony<T, G, S> {
+    fn index_mut(&mut self, index: G::Handle) -> &mut T {
+        self.get_mut(index)
+            .expect("no element with that handle exists in this colony")
+    }
+}
+
+impl<T: Debug, G: Guard, S: Storage> Debug for TrackedColony<T, G, S>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&self.colony, f)
+    }
+}
+
+/// The iterator returned by [`TrackedColony::iter_mut`].
+pub struct TrackedIterMut<'a, T, G: Guard = GenerationGuard, S: Storage = InterleavedStorage> {
+    inner: IterMut<'a, T, G, S>,
+    journal: &'a mut Journal<G>,
+}
+
+impl<'a, T, G: Guard, S: Storage> Iterator for TrackedIterMut<'a, T, G, S> {
+    type Item = (G::Handle, &'a mut T);
+
+    fn next(&mut self) -> Option<(G::Handle, &'a mut T)> {
+        let (handle, value) = self.inner.next()?;
+        self.journal.modify(G::__extract_index(&handle));
+        Some((handle, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T, G: Guard, S: Storage> FusedIterator for TrackedIterMut<'a, T, G, S> {}
+
+impl<'a, T, G: Guard, S: Storage> ExactSizeIterator for TrackedIterMut<'a, T, G, S> {}
+
+impl<'a, T: Debug, G: Guard, S: Storage> Debug for TrackedIterMut<'a, T, G, S>
+where
+    G::Handle: Debug,
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
@@ -0,0 +1,1033 @@
+//! Trees whose nodes are stored in a [`Colony`] and linked by [`Handle`]s.
+//!
+//! Each node links to its parent, its first and last children and its previous and next siblings,
+//! so nodes can be added, removed and moved in `O(1)` (apart from removing their descendants),
+//! and traversed without any allocation except for breadth-first traversal.
+//!
+//! [`Tree`] owns its nodes and keeps a list of roots.
+//! To link the elements of a colony of your own instead, embed [`Links`] in the element type, implement [`Linked`] for it,
+//! and use the functions of this module, such as [`append_child`] and [`depth_first`].
+//! Those functions know nothing of roots: a node without a parent is simply not linked to any other.
+//! Graphs in which a node can have several parents are out of scope.
+//!
+//! Handles to removed nodes are detected through [`GenerationGuard`](crate::GenerationGuard):
+//! every method taking a handle returns `None` or `false` for them, rather than following a dangling link.
+//! Links to nodes removed directly from a colony are treated as missing,
+//! so [`remove_subtree`] should be used to remove linked nodes without cutting off their siblings and descendants.
+//!
+//! # Examples
+//!
+//! ```
+//! # use colony::Colony;
+//! # use colony::tree::{self, Linked, Links};
+//! struct Item {
+//!     name: &'static str,
+//!     links: Links,
+//! }
+//!
+//! impl Linked for Item {
+//!     fn links(&self) -> &Links {
+//!         &self.links
+//!     }
+//!
+//!     fn links_mut(&mut self) -> &mut Links {
+//!         &mut self.links
+//!     }
+//! }
+//!
+//! let mut items = Colony::new();
+//! let mut item = |name| items.insert(Item { name, links: Links::new() });
+//! let (root, foo, bar) = (item("root"), item("foo"), item("bar"));
+//!
+//! tree::append_child(&mut items, root, foo);
+//! tree::append_child(&mut items, foo, bar);
+//!
+//! let names: Vec<_> = tree::depth_first(&items, root).map(|(_, item)| item.name).collect();
+//! assert_eq!(names, ["root", "foo", "bar"]);
+//!
+//! // Removing a node removes its descendants
+//! tree::remove_subtree(&mut items, foo);
+//! assert_eq!(items.len(), 1);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::iter::FusedIterator;
+use std::ops::{Index, IndexMut};
+
+use crate::{Colony, Handle};
+
+/// The links of a node to its parent, children and siblings, for embedding in the elements of a colony.
+///
+/// The links are only changed by the functions of the [module](self), which keep them consistent.
+/// A new node is not linked to any other.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Links {
+    parent: Option<Handle>,
+    first_child: Option<Handle>,
+    last_child: Option<Handle>,
+    prev_sibling: Option<Handle>,
+    next_sibling: Option<Handle>,
+}
+
+impl Links {
+    /// Constructs links for a node which is not linked to any other.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the handle of the parent of the node, if any.
+    pub fn parent(&self) -> Option<Handle> {
+        self.parent
+    }
+
+    /// Returns the handle of the first child of the node, if any.
+    pub fn first_child(&self) -> Option<Handle> {
+        self.first_child
+    }
+
+    /// Returns the handle of the last child of the node, if any.
+    pub fn last_child(&self) -> Option<Handle> {
+        self.last_child
+    }
+
+    /// Returns the handle of the previous sibling of the node, if any.
+    pub fn prev_sibling(&self) -> Option<Handle> {
+        self.prev_sibling
+    }
+
+    /// Returns the handle of the next sibling of the node, if any.
+    pub fn next_sibling(&self) -> Option<Handle> {
+        self.next_sibling
+    }
+}
+
+/// An element of a colony which can be linked to others by the functions of the [module](self).
+pub trait Linked {
+    /// Returns the links of the node.
+    fn links(&self) -> &Links;
+
+    /// Returns the links of the node mutably.
+    ///
+    /// The links should only be changed by the functions of the [module](self).
+    fn links_mut(&mut self) -> &mut Links;
+}
+
+/// Moves the node `child`, along with its descendants, to be the last child of `parent`.
+///
+/// Returns `false` without moving anything if either node does not exist,
+/// or if `parent` is `child` itself or one of its descendants.
+pub fn append_child<N: Linked>(nodes: &mut Colony<N>, parent: Handle, child: Handle) -> bool {
+    if !can_move(nodes, child, parent) {
+        return false;
+    }
+
+    // Nodes without a parent are not linked to each other, so have no list to be removed from
+    let mut roots = Links::new();
+    unlink(nodes, &mut roots, child);
+    link_last(nodes, &mut roots, Some(parent), child);
+    true
+}
+
+/// Moves the node `child`, along with its descendants, to be the first child of `parent`.
+///
+/// Returns `false` without moving anything if either node does not exist,
+/// or if `parent` is `child` itself or one of its descendants.
+pub fn prepend_child<N: Linked>(nodes: &mut Colony<N>, parent: Handle, child: Handle) -> bool {
+    if !can_move(nodes, child, parent) {
+        return false;
+    }
+
+    let mut roots = Links::new();
+    unlink(nodes, &mut roots, child);
+    link_first(nodes, &mut roots, Some(parent), child);
+    true
+}
+
+/// Detaches the node with the given handle, along with its descendants, from its parent and siblings.
+///
+/// Returns whether the node exists.
+pub fn detach<N: Linked>(nodes: &mut Colony<N>, handle: Handle) -> bool {
+    if nodes.get(handle).is_none() {
+        return false;
+    }
+
+    unlink(nodes, &mut Links::new(), handle);
+
+    let links = nodes[handle].links_mut();
+    links.parent = None;
+    links.prev_sibling = None;
+    links.next_sibling = None;
+    true
+}
+
+/// Removes the node with the given handle and all of its descendants, returning the node.
+///
+/// This is an `O(n)` operation, where `n` is the number of nodes removed.
+pub fn remove_subtree<N: Linked>(nodes: &mut Colony<N>, handle: Handle) -> Option<N> {
+    remove_linked(nodes, &mut Links::new(), handle)
+}
+
+/// Creates an iterator over the children of the node with the given handle, in order.
+///
+/// The iterator is empty if the node does not exist.
+pub fn children<N: Linked>(nodes: &Colony<N>, handle: Handle) -> LinkedSiblings<'_, N> {
+    LinkedSiblings {
+        nodes,
+        next: nodes.get(handle).and_then(|node| node.links().first_child),
+    }
+}
+
+/// Creates an iterator over the ancestors of the node with the given handle, starting with its parent.
+pub fn ancestors<N: Linked>(nodes: &Colony<N>, handle: Handle) -> LinkedAncestors<'_, N> {
+    LinkedAncestors {
+        nodes,
+        next: nodes.get(handle).and_then(|node| node.links().parent),
+    }
+}
+
+/// Creates an iterator over the node with the given handle and its descendants, in depth-first pre-order.
+///
+/// The iterator is empty if the node does not exist.
+pub fn depth_first<N: Linked>(nodes: &Colony<N>, handle: Handle) -> LinkedDepthFirst<'_, N> {
+    LinkedDepthFirst {
+        nodes,
+        next: Some(handle),
+        root: Some(handle),
+    }
+}
+
+/// Creates an iterator over the node with the given handle and its descendants, in breadth-first order.
+///
+/// The iterator is empty if the node does not exist.
+pub fn breadth_first<N: Linked>(nodes: &Colony<N>, handle: Handle) -> LinkedBreadthFirst<'_, N> {
+    LinkedBreadthFirst {
+        nodes,
+        queue: VecDeque::from([handle]),
+    }
+}
+
+// Returns whether the node with the given handle can be moved below parent
+fn can_move<N: Linked>(nodes: &Colony<N>, handle: Handle, parent: Handle) -> bool {
+    nodes.get(handle).is_some()
+        && nodes.get(parent).is_some()
+        && parent != handle
+        && !ancestors(nodes, parent).any(|(ancestor, _)| ancestor == handle)
+}
+
+// The links to the first and last child of a node, or to the first and last root
+fn ends_mut<'a, N: Linked>(
+    nodes: &'a mut Colony<N>,
+    roots: &'a mut Links,
+    parent: Option<Handle>,
+) -> &'a mut Links {
+    match parent.and_then(|parent| nodes.get_mut(parent)) {
+        Some(parent) => parent.links_mut(),
+        None => roots,
+    }
+}
+
+// Sets a link of the node with the given handle, or of the parent's ends if there is no such node
+// Links to removed nodes are left as they are, rather than treated as the end of the list
+fn set_link<N: Linked>(
+    nodes: &mut Colony<N>,
+    roots: &mut Links,
+    handle: Option<Handle>,
+    parent: Option<Handle>,
+    set: impl FnOnce(&mut Links),
+    set_end: impl FnOnce(&mut Links),
+) {
+    match handle {
+        Some(handle) => {
+            if let Some(node) = nodes.get_mut(handle) {
+                set(node.links_mut());
+            }
+        }
+        None => set_end(ends_mut(nodes, roots, parent)),
+    }
+}
+
+// Preconditions:
+// * the node with the given handle exists and is not linked
+fn link_last<N: Linked>(
+    nodes: &mut Colony<N>,
+    roots: &mut Links,
+    parent: Option<Handle>,
+    handle: Handle,
+) {
+    let last = ends_mut(nodes, roots, parent).last_child;
+
+    let links = nodes[handle].links_mut();
+    links.parent = parent;
+    links.prev_sibling = last;
+    links.next_sibling = None;
+
+    set_link(
+        nodes,
+        roots,
+        last,
+        parent,
+        |last| last.next_sibling = Some(handle),
+        |ends| ends.first_child = Some(handle),
+    );
+
+    ends_mut(nodes, roots, parent).last_child = Some(handle);
+}
+
+// Preconditions:
+// * the node with the given handle exists and is not linked
+fn link_first<N: Linked>(
+    nodes: &mut Colony<N>,
+    roots: &mut Links,
+    parent: Option<Handle>,
+    handle: Handle,
+) {
+    let first = ends_mut(nodes, roots, parent).first_child;
+
+    let links = nodes[handle].links_mut();
+    links.parent = parent;
+    links.prev_sibling = None;
+    links.next_sibling = first;
+
+    set_link(
+        nodes,
+        roots,
+        first,
+        parent,
+        |first| first.prev_sibling = Some(handle),
+        |ends| ends.last_child = Some(handle),
+    );
+
+    ends_mut(nodes, roots, parent).first_child = Some(handle);
+}
+
+// Leaves the node's own links unchanged, to be overwritten when it is linked again
+// Preconditions:
+// * the node with the given handle exists
+fn unlink<N: Linked>(nodes: &mut Colony<N>, roots: &mut Links, handle: Handle) {
+    let links = *nodes[handle].links();
+    let (parent, prev, next) = (links.parent, links.prev_sibling, links.next_sibling);
+
+    set_link(
+        nodes,
+        roots,
+        prev,
+        parent,
+        |prev| prev.next_sibling = next,
+        |ends| ends.first_child = next,
+    );
+    set_link(
+        nodes,
+        roots,
+        next,
+        parent,
+        |next| next.prev_sibling = prev,
+        |ends| ends.last_child = prev,
+    );
+}
+
+fn remove_linked<N: Linked>(nodes: &mut Colony<N>, roots: &mut Links, handle: Handle) -> Option<N> {
+    nodes.get(handle)?;
+    unlink(nodes, roots, handle);
+
+    let descendants: Vec<_> = depth_first(nodes, handle)
+        .skip(1)
+        .map(|(handle, _)| handle)
+        .collect();
+    for descendant in descendants {
+        nodes.remove(descendant);
+    }
+
+    nodes.remove(handle)
+}
+
+// Returns the handle if its node has not been removed
+fn live<N>(nodes: &Colony<N>, handle: Option<Handle>) -> Option<Handle> {
+    handle.filter(|&handle| nodes.get(handle).is_some())
+}
+
+/// A tree (or rather a forest, since it may have several roots) whose nodes are stored in a [`Colony`].
+///
+/// # Examples
+///
+/// ```
+/// # use colony::tree::Tree;
+/// let mut tree = Tree::new();
+/// let root = tree.insert_root("root");
+/// let foo = tree.append_child(root, "foo").unwrap();
+/// tree.append_child(foo, "bar").unwrap();
+/// tree.append_child(root, "baz").unwrap();
+///
+/// let values: Vec<_> = tree.depth_first(root).map(|(_, &value)| value).collect();
+/// assert_eq!(values, ["root", "foo", "bar", "baz"]);
+///
+/// // Removing a node removes its descendants
+/// tree.remove(foo);
+/// assert_eq!(tree.len(), 2);
+/// ```
+pub struct Tree<T> {
+    nodes: Colony<Node<T>>,
+    // The first and last children are the first and last roots
+    roots: Links,
+}
+
+struct Node<T> {
+    value: T,
+    links: Links,
+}
+
+impl<T> Node<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            links: Links::new(),
+        }
+    }
+}
+
+impl<T> Linked for Node<T> {
+    fn links(&self) -> &Links {
+        &self.links
+    }
+
+    fn links_mut(&mut self) -> &mut Links {
+        &mut self.links
+    }
+}
+
+impl<T> Tree<T> {
+    /// Constructs an empty tree.
+    ///
+    /// Does not allocate.
+    pub fn new() -> Self {
+        Self {
+            nodes: Colony::new(),
+            roots: Links::new(),
+        }
+    }
+
+    /// Returns the number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the tree contains no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns `true` if the tree contains a node with the given handle.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.nodes.get(handle).is_some()
+    }
+
+    /// Returns a reference to the value of the node with the given handle if it exists.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        Some(&self.nodes.get(handle)?.value)
+    }
+
+    /// Returns a mutable reference to the value of the node with the given handle if it exists.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        Some(&mut self.nodes.get_mut(handle)?.value)
+    }
+
+    /// Inserts a node as the last root of the tree.
+    pub fn insert_root(&mut self, value: T) -> Handle {
+        let handle = self.nodes.insert(Node::new(value));
+        link_last(&mut self.nodes, &mut self.roots, None, handle);
+        handle
+    }
+
+    /// Inserts a node as the last child of the node with the given handle, if it exists.
+    pub fn append_child(&mut self, parent: Handle, value: T) -> Option<Handle> {
+        if !self.contains(parent) {
+            return None;
+        }
+
+        let handle = self.nodes.insert(Node::new(value));
+        link_last(&mut self.nodes, &mut self.roots, Some(parent), handle);
+        Some(handle)
+    }
+
+    /// Inserts a node as the first child of the node with the given handle, if it exists.
+    pub fn prepend_child(&mut self, parent: Handle, value: T) -> Option<Handle> {
+        if !self.contains(parent) {
+            return None;
+        }
+
+        let handle = self.nodes.insert(Node::new(value));
+        link_first(&mut self.nodes, &mut self.roots, Some(parent), handle);
+        Some(handle)
+    }
+
+    /// Removes the node with the given handle and all of its descendants, returning the node's value.
+    ///
+    /// This is an `O(n)` operation, where `n` is the number of nodes removed.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        Some(remove_linked(&mut self.nodes, &mut self.roots, handle)?.value)
+    }
+
+    /// Detaches the node with the given handle from its parent, making it the last root of the tree.
+    ///
+    /// Returns whether the node exists.
+    pub fn detach(&mut self, handle: Handle) -> bool {
+        if !self.contains(handle) {
+            return false;
+        }
+
+        unlink(&mut self.nodes, &mut self.roots, handle);
+        link_last(&mut self.nodes, &mut self.roots, None, handle);
+        true
+    }
+
+    /// Moves the node with the given handle, along with its descendants, to be the last child of `parent`.
+    ///
+    /// Returns `false` without moving anything if either node does not exist,
+    /// or if `parent` is the node itself or one of its descendants.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use colony::tree::Tree;
+    /// let mut tree = Tree::new();
+    /// let foo = tree.insert_root("foo");
+    /// let bar = tree.insert_root("bar");
+    ///
+    /// assert!(tree.reparent(bar, foo));
+    /// assert_eq!(tree.parent(bar), Some(foo));
+    /// assert!(!tree.reparent(foo, bar));
+    /// ```
+    pub fn reparent(&mut self, handle: Handle, parent: Handle) -> bool {
+        if !can_move(&self.nodes, handle, parent) {
+            return false;
+        }
+
+        unlink(&mut self.nodes, &mut self.roots, handle);
+        link_last(&mut self.nodes, &mut self.roots, Some(parent), handle);
+        true
+    }
+
+    /// Returns the handle of the parent of the node with the given handle,
+    /// or `None` if the node is a root or does not exist.
+    pub fn parent(&self, handle: Handle) -> Option<Handle> {
+        self.nodes.get(handle)?.links.parent
+    }
+
+    /// Returns the handle of the first child of the node with the given handle, if any.
+    pub fn first_child(&self, handle: Handle) -> Option<Handle> {
+        self.nodes.get(handle)?.links.first_child
+    }
+
+    /// Returns the handle of the last child of the node with the given handle, if any.
+    pub fn last_child(&self, handle: Handle) -> Option<Handle> {
+        self.nodes.get(handle)?.links.last_child
+    }
+
+    /// Returns the handle of the previous sibling of the node with the given handle, if any.
+    pub fn prev_sibling(&self, handle: Handle) -> Option<Handle> {
+        self.nodes.get(handle)?.links.prev_sibling
+    }
+
+    /// Returns the handle of the next sibling of the node with the given handle, if any.
+    pub fn next_sibling(&self, handle: Handle) -> Option<Handle> {
+        self.nodes.get(handle)?.links.next_sibling
+    }
+
+    /// Creates an iterator over the roots of the tree, in order.
+    pub fn roots(&self) -> Siblings<'_, T> {
+        Siblings {
+            inner: LinkedSiblings {
+                nodes: &self.nodes,
+                next: self.roots.first_child,
+            },
+        }
+    }
+
+    /// Creates an iterator over the children of the node with the given handle, in order.
+    ///
+    /// The iterator is empty if the node does not exist.
+    pub fn children(&self, handle: Handle) -> Siblings<'_, T> {
+        Siblings {
+            inner: children(&self.nodes, handle),
+        }
+    }
+
+    /// Creates an iterator over the ancestors of the node with the given handle, starting with its parent.
+    pub fn ancestors(&self, handle: Handle) -> Ancestors<'_, T> {
+        Ancestors {
+            inner: ancestors(&self.nodes, handle),
+        }
+    }
+
+    /// Creates an iterator over the node with the given handle and its descendants, in depth-first pre-order.
+    ///
+    /// The iterator is empty if the node does not exist.
+    pub fn depth_first(&self, handle: Handle) -> DepthFirst<'_, T> {
+        DepthFirst {
+            inner: depth_first(&self.nodes, handle),
+        }
+    }
+
+    /// Creates an iterator over the node with the given handle and its descendants, in breadth-first order.
+    ///
+    /// The iterator is empty if the node does not exist.
+    pub fn breadth_first(&self, handle: Handle) -> BreadthFirst<'_, T> {
+        BreadthFirst {
+            inner: breadth_first(&self.nodes, handle),
+        }
+    }
+
+    /// Creates an iterator over every node in the tree, in depth-first pre-order starting from the first root.
+    pub fn iter(&self) -> DepthFirst<'_, T> {
+        DepthFirst {
+            inner: LinkedDepthFirst {
+                nodes: &self.nodes,
+                next: self.roots.first_child,
+                root: None,
+            },
+        }
+    }
+
+    /// Removes all nodes from the tree.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.roots = Links::new();
+    }
+}
+
+impl<T> Default for Tree<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Index<Handle> for Tree<T> {
+    type Output = T;
+
+    fn index(&self, index: Handle) -> &T {
+        self.get(index)
+            .expect("no node with that handle exists in this tree")
+    }
+}
+
+impl<T> IndexMut<Handle> for Tree<T> {
+    fn index_mut(&mut self, index: Handle) -> &mut T {
+        self.get_mut(index)
+            .expect("no node with that handle exists in this tree")
+    }
+}
+
+impl<T: Debug> Debug for Tree<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let iter = self.iter().map(|(_, value)| value);
+        f.debug_list().entries(iter).finish()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Tree<T> {
+    type Item = (Handle, &'a T);
+    type IntoIter = DepthFirst<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The iterator returned by [`children`].
+pub struct LinkedSiblings<'a, N> {
+    nodes: &'a Colony<N>,
+    next: Option<Handle>,
+}
+
+impl<'a, N: Linked> Iterator for LinkedSiblings<'a, N> {
+    type Item = (Handle, &'a N);
+
+    fn next(&mut self) -> Option<(Handle, &'a N)> {
+        let handle = self.next.take()?;
+        let node = self.nodes.get(handle)?;
+        self.next = node.links().next_sibling;
+        Some((handle, node))
+    }
+}
+
+/// The iterator returned by [`ancestors`].
+pub struct LinkedAncestors<'a, N> {
+    nodes: &'a Colony<N>,
+    next: Option<Handle>,
+}
+
+impl<'a, N: Linked> Iterator for LinkedAncestors<'a, N> {
+    type Item = (Handle, &'a N);
+
+    fn next(&mut self) -> Option<(Handle, &'a N)> {
+        let handle = self.next.take()?;
+        let node = self.nodes.get(handle)?;
+        self.next = node.links().parent;
+        Some((handle, node))
+    }
+}
+
+/// The iterator returned by [`depth_first`].
+pub struct LinkedDepthFirst<'a, N> {
+    nodes: &'a Colony<N>,
+    next: Option<Handle>,
+    // The node whose subtree is being traversed, or None for every node after the first and its siblings
+    root: Option<Handle>,
+}
+
+impl<'a, N: Linked> Iterator for LinkedDepthFirst<'a, N> {
+    type Item = (Handle, &'a N);
+
+    fn next(&mut self) -> Option<(Handle, &'a N)> {
+        let handle = self.next.take()?;
+        let node = self.nodes.get(handle)?;
+
+        self.next = live(self.nodes, node.links().first_child).or_else(|| {
+            // Climb until there is a sibling to visit, without leaving the subtree
+            let mut current = handle;
+
+            loop {
+                if Some(current) == self.root {
+                    return None;
+                }
+
+                let links = self.nodes.get(current)?.links();
+
+                if let Some(next) = live(self.nodes, links.next_sibling) {
+                    return Some(next);
+                }
+
+                current = links.parent?;
+            }
+        });
+
+        Some((handle, node))
+    }
+}
+
+/// The iterator returned by [`breadth_first`].
+pub struct LinkedBreadthFirst<'a, N> {
+    nodes: &'a Colony<N>,
+    queue: VecDeque<Handle>,
+}
+
+impl<'a, N: Linked> Iterator for LinkedBreadthFirst<'a, N> {
+    type Item = (Handle, &'a N);
+
+    fn next(&mut self) -> Option<(Handle, &'a N)> {
+        loop {
+            let handle = self.queue.pop_front()?;
+
+            if let Some(node) = self.nodes.get(handle) {
+                let children = children(self.nodes, handle).map(|(child, _)| child);
+                self.queue.extend(children);
+                return Some((handle, node));
+            }
+        }
+    }
+}
+
+// Implements the traits shared by every iterator over linked nodes
+macro_rules! linked_iter_traits {
+    ($($name:ident { $($field:ident),* }),* $(,)?) => {
+        $(
+            impl<'a, N: Linked> FusedIterator for $name<'a, N> {}
+
+            impl<'a, N> Clone for $name<'a, N> {
+                fn clone(&self) -> Self {
+                    Self {
+                        nodes: self.nodes,
+                        $($field: self.$field.clone()),*
+                    }
+                }
+            }
+
+            impl<'a, N: Linked + Debug> Debug for $name<'a, N> {
+                fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                    f.debug_list().entries(self.clone()).finish()
+                }
+            }
+        )*
+    };
+}
+
+linked_iter_traits!(
+    LinkedSiblings { next },
+    LinkedAncestors { next },
+    LinkedDepthFirst { next, root },
+    LinkedBreadthFirst { queue },
+);
+
+/// The iterator returned by [`Tree::children`] and [`Tree::roots`].
+pub struct Siblings<'a, T> {
+    inner: LinkedSiblings<'a, Node<T>>,
+}
+
+/// The iterator returned by [`Tree::ancestors`].
+pub struct Ancestors<'a, T> {
+    inner: LinkedAncestors<'a, Node<T>>,
+}
+
+/// The iterator returned by [`Tree::depth_first`] and [`Tree::iter`].
+pub struct DepthFirst<'a, T> {
+    inner: LinkedDepthFirst<'a, Node<T>>,
+}
+
+/// The iterator returned by [`Tree::breadth_first`].
+pub struct BreadthFirst<'a, T> {
+    inner: LinkedBreadthFirst<'a, Node<T>>,
+}
+
+// Implements the iterators of a tree in terms of those over its nodes
+macro_rules! tree_iter {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl<'a, T> Iterator for $name<'a, T> {
+                type Item = (Handle, &'a T);
+
+                fn next(&mut self) -> Option<(Handle, &'a T)> {
+                    let (handle, node) = self.inner.next()?;
+                    Some((handle, &node.value))
+                }
+            }
+
+            impl<'a, T> FusedIterator for $name<'a, T> {}
+
+            impl<'a, T> Clone for $name<'a, T> {
+                fn clone(&self) -> Self {
+                    Self {
+                        inner: self.inner.clone(),
+                    }
+                }
+            }
+
+            impl<'a, T: Debug> Debug for $name<'a, T> {
+                fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                    f.debug_list().entries(self.clone()).finish()
+                }
+            }
+        )*
+    };
+}
+
+tree_iter!(Siblings, Ancestors, DepthFirst, BreadthFirst);
+
+#[cfg(test)]
+mod test {
+    use super::{Linked, Links, Tree};
+    use crate::{tree, Colony, Handle};
+
+    struct Item {
+        value: char,
+        links: Links,
+    }
+
+    impl Linked for Item {
+        fn links(&self) -> &Links {
+            &self.links
+        }
+
+        fn links_mut(&mut self) -> &mut Links {
+            &mut self.links
+        }
+    }
+
+    // Builds the same tree as sample, as a colony of items, without the second root
+    fn linked_sample() -> (Colony<Item>, Vec<Handle>) {
+        let mut items = Colony::new();
+        let handles: Vec<_> = "rabcdef"
+            .chars()
+            .map(|value| {
+                items.insert(Item {
+                    value,
+                    links: Links::new(),
+                })
+            })
+            .collect();
+
+        let [root, a, b, c, d, e, f] = handles[..] else {
+            unreachable!()
+        };
+
+        assert!(tree::append_child(&mut items, root, a));
+        assert!(tree::append_child(&mut items, root, b));
+        assert!(tree::append_child(&mut items, a, d));
+        assert!(tree::prepend_child(&mut items, a, c));
+        assert!(tree::append_child(&mut items, b, e));
+        assert!(tree::append_child(&mut items, d, f));
+
+        (items, handles)
+    }
+
+    fn item_values<'a>(iter: impl Iterator<Item = (Handle, &'a Item)>) -> String {
+        iter.map(|(_, item)| item.value).collect()
+    }
+
+    // Builds root(a(c, d(f)), b(e)) alongside a second root g
+    fn sample() -> (Tree<char>, Vec<Handle>) {
+        let mut tree = Tree::new();
+        let root = tree.insert_root('r');
+        let a = tree.append_child(root, 'a').unwrap();
+        let b = tree.append_child(root, 'b').unwrap();
+        let d = tree.append_child(a, 'd').unwrap();
+        let c = tree.prepend_child(a, 'c').unwrap();
+        let e = tree.append_child(b, 'e').unwrap();
+        let f = tree.append_child(d, 'f').unwrap();
+        let g = tree.insert_root('g');
+
+        (tree, vec![root, a, b, c, d, e, f, g])
+    }
+
+    fn values<'a>(iter: impl Iterator<Item = (Handle, &'a char)>) -> String {
+        iter.map(|(_, &value)| value).collect()
+    }
+
+    #[test]
+    fn traversal() {
+        let (tree, handles) = sample();
+        let [root, a, _, c, d, _, f, g] = handles[..] else {
+            unreachable!()
+        };
+
+        assert_eq!(values(tree.depth_first(root)), "racdfbe");
+        assert_eq!(values(tree.breadth_first(root)), "rabcdef");
+        assert_eq!(values(tree.depth_first(a)), "acdf");
+        assert_eq!(values(tree.depth_first(g)), "g");
+        assert_eq!(values(tree.iter()), "racdfbeg");
+        assert_eq!(values(tree.roots()), "rg");
+        assert_eq!(values(tree.children(a)), "cd");
+        assert_eq!(values(tree.ancestors(f)), "dar");
+
+        assert_eq!(tree.parent(c), Some(a));
+        assert_eq!(tree.first_child(a), Some(c));
+        assert_eq!(tree.last_child(a), Some(d));
+        assert_eq!(tree.next_sibling(c), Some(d));
+        assert_eq!(tree.prev_sibling(c), None);
+    }
+
+    #[test]
+    fn remove_cascades() {
+        let (mut tree, handles) = sample();
+        let [root, a, b, c, d, e, f, g] = handles[..] else {
+            unreachable!()
+        };
+
+        assert_eq!(tree.remove(a), Some('a'));
+        assert_eq!(tree.len(), 4);
+
+        for stale in [a, c, d, f] {
+            assert!(!tree.contains(stale));
+            assert_eq!(tree.parent(stale), None);
+            assert_eq!(values(tree.depth_first(stale)), "");
+            assert_eq!(tree.append_child(stale, 'x'), None);
+            assert!(!tree.detach(stale));
+        }
+
+        assert_eq!(values(tree.iter()), "rbeg");
+        assert_eq!(tree.first_child(root), Some(b));
+        assert_eq!(tree.prev_sibling(b), None);
+
+        // Slots freed by the removal are reused without reviving stale handles
+        let x = tree.append_child(e, 'x').unwrap();
+        assert_eq!(values(tree.iter()), "rbexg");
+        assert!(!tree.contains(a) && !tree.contains(f));
+
+        assert_eq!(tree.remove(root), Some('r'));
+        assert_eq!(values(tree.iter()), "g");
+        assert!(!tree.contains(x));
+        assert_eq!(values(tree.roots()), "g");
+        assert_eq!(tree[g], 'g');
+    }
+
+    #[test]
+    fn linked_items() {
+        let (mut items, handles) = linked_sample();
+        let [root, a, b, c, d, e, f] = handles[..] else {
+            unreachable!()
+        };
+
+        assert_eq!(item_values(tree::depth_first(&items, root)), "racdfbe");
+        assert_eq!(item_values(tree::breadth_first(&items, root)), "rabcdef");
+        assert_eq!(item_values(tree::children(&items, a)), "cd");
+        assert_eq!(item_values(tree::ancestors(&items, f)), "dar");
+        assert_eq!(items[c].links().parent(), Some(a));
+        assert_eq!(items[a].links().last_child(), Some(d));
+
+        // Cycles are rejected
+        assert!(!tree::append_child(&mut items, d, a));
+        assert!(!tree::prepend_child(&mut items, a, a));
+
+        assert!(tree::detach(&mut items, d));
+        assert_eq!(item_values(tree::depth_first(&items, root)), "racbe");
+        assert_eq!(item_values(tree::depth_first(&items, d)), "df");
+        assert_eq!(
+            *items[d].links(),
+            Links {
+                first_child: Some(f),
+                last_child: Some(f),
+                ..Links::new()
+            }
+        );
+
+        assert!(tree::prepend_child(&mut items, e, d));
+        assert_eq!(item_values(tree::depth_first(&items, b)), "bedf");
+
+        assert_eq!(
+            tree::remove_subtree(&mut items, b).map(|item| item.value),
+            Some('b')
+        );
+        assert_eq!(items.len(), 3);
+        assert_eq!(item_values(tree::depth_first(&items, root)), "rac");
+        assert!(!tree::detach(&mut items, f));
+        assert!(tree::remove_subtree(&mut items, e).is_none());
+    }
+
+    #[test]
+    fn linked_stale_links() {
+        let (mut items, handles) = linked_sample();
+        let [root, a, b, c, d, _, f] = handles[..] else {
+            unreachable!()
+        };
+
+        // Removing a node directly cuts it off, rather than leaving a dangling link
+        items.remove(a);
+        assert_eq!(item_values(tree::children(&items, root)), "");
+        assert_eq!(item_values(tree::depth_first(&items, root)), "r");
+        assert_eq!(item_values(tree::ancestors(&items, f)), "d");
+        assert_eq!(item_values(tree::depth_first(&items, d)), "df");
+
+        // Stale links are left alone when unlinking their neighbours
+        assert!(tree::detach(&mut items, b));
+        assert_eq!(items[root].links().first_child(), Some(a));
+        assert_eq!(items[root].links().last_child(), Some(a));
+
+        // The orphans can still be moved elsewhere
+        assert!(tree::append_child(&mut items, root, d));
+        assert!(tree::append_child(&mut items, root, c));
+        assert_eq!(item_values(tree::depth_first(&items, d)), "df");
+        assert_eq!(items[root].links().last_child(), Some(c));
+        assert_eq!(item_values(tree::breadth_first(&items, root)), "r");
+    }
+
+    #[test]
+    fn moving() {
+        let (mut tree, handles) = sample();
+        let [root, a, b, _, d, _, _, g] = handles[..] else {
+            unreachable!()
+        };
+
+        assert!(tree.detach(d));
+        assert_eq!(values(tree.roots()), "rgd");
+        assert_eq!(values(tree.iter()), "racbegdf");
+
+        assert!(tree.reparent(g, b));
+        assert_eq!(values(tree.depth_first(root)), "racbeg");
+
+        assert!(!tree.reparent(root, g));
+        assert!(!tree.reparent(a, a));
+        assert!(tree.reparent(d, g));
+        assert_eq!(values(tree.iter()), "racbegdf");
+        assert_eq!(values(tree.roots()), "r");
+    }
+}
@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+#[cfg(doc)]
+use crate::Colony;
+
+/// An inconsistency in the internal structure of a colony, as returned by [`Colony::validate`].
+///
+/// A colony can only become corrupted through incorrect use of unsafe methods such as [`Colony::remove_unchecked`],
+/// or through a bug in this crate.
+/// Each variant identifies the first slot at which the inconsistency was found.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum CorruptionError {
+    /// The skipfield is not zero at an index outside of the slots that have been used.
+    UnzeroedSkipfield {
+        /// The offending index, which may be `-1`.
+        index: isize,
+    },
+    /// A skipblock extends past the slots that have been used.
+    SkipblockOutOfBounds {
+        /// The first slot of the skipblock.
+        start: usize,
+        /// The size recorded at the head of the skipblock.
+        size: usize,
+    },
+    /// The sizes recorded at the head and tail of a skipblock differ.
+    SkipblockMismatch {
+        /// The first slot of the skipblock.
+        start: usize,
+        /// The size recorded at the head of the skipblock.
+        head: usize,
+        /// The size recorded at the tail of the skipblock.
+        tail: usize,
+    },
+    /// Two skipblocks are adjacent rather than joined into one.
+    AdjacentSkipblocks {
+        /// The first slot of the second skipblock.
+        start: usize,
+    },
+    /// A guard disagrees with the skipfield about whether its slot is occupied.
+    GuardMismatch {
+        /// The offending slot.
+        index: usize,
+        /// Whether the skipfield considers the slot occupied.
+        occupied: bool,
+    },
+    /// A slot is recorded as retired but its guard can still be reused, or the other way around.
+    RetiredMismatch {
+        /// The offending slot.
+        index: usize,
+    },
+    /// The length of the colony does not match the number of occupied slots.
+    LenMismatch {
+        /// The length of the colony.
+        len: usize,
+        /// The number of occupied slots.
+        occupied: usize,
+    },
+    /// The freelist contains a slot that is out of bounds, occupied or retired.
+    InvalidFreelistNode {
+        /// The offending slot.
+        index: usize,
+    },
+    /// The links of a slot in the freelist do not agree with those of its neighbours.
+    FreelistLinkMismatch {
+        /// The offending slot.
+        index: usize,
+    },
+    /// The freelist contains a slot more than once.
+    FreelistCycle {
+        /// The first slot to be visited twice.
+        index: usize,
+    },
+    /// A slot in the freelist follows a reusable empty slot, but is not preceded by it in the freelist.
+    FreelistOrder {
+        /// The offending slot.
+        index: usize,
+    },
+    /// An empty slot that has not been retired is missing from the freelist, so can never be reused.
+    MissingFromFreelist {
+        /// The first slot missing from the freelist.
+        index: usize,
+    },
+}
+
+impl Display for CorruptionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Self::UnzeroedSkipfield { index } => {
+                write!(f, "skipfield is not zero at unused index {index}")
+            }
+            Self::SkipblockOutOfBounds { start, size } => {
+                write!(f, "skipblock at {start} of size {size} is out of bounds")
+            }
+            Self::SkipblockMismatch { start, head, tail } => write!(
+                f,
+                "skipblock at {start} has size {head} at its head but {tail} at its tail"
+            ),
+            Self::AdjacentSkipblocks { start } => {
+                write!(f, "skipblock at {start} is adjacent to another skipblock")
+            }
+            Self::GuardMismatch { index, occupied } => write!(
+                f,
+                "guard at {index} disagrees with the skipfield, which has the slot {}",
+                if occupied { "occupied" } else { "unoccupied" }
+            ),
+            Self::RetiredMismatch { index } => {
+                write!(
+                    f,
+                    "guard at {index} disagrees with the record of retired slots"
+                )
+            }
+            Self::LenMismatch { len, occupied } => {
+                write!(f, "length is {len} but {occupied} slots are occupied")
+            }
+            Self::InvalidFreelistNode { index } => {
+                write!(
+                    f,
+                    "freelist contains out of bounds, occupied or retired slot {index}"
+                )
+            }
+            Self::FreelistLinkMismatch { index } => {
+                write!(f, "freelist links at {index} disagree with its neighbours")
+            }
+            Self::FreelistCycle { index } => write!(f, "freelist contains {index} twice"),
+            Self::FreelistOrder { index } => write!(
+                f,
+                "freelist contains {index} out of order within its skipblock"
+            ),
+            Self::MissingFromFreelist { index } => {
+                write!(f, "empty slot {index} is missing from the freelist")
+            }
+        }
+    }
+}
+
+impl Error for CorruptionError {}